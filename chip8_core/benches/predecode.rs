@@ -0,0 +1,33 @@
+use chip8_core::backend::{Backend, BlockJit};
+use chip8_core::Emu;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+const INSTRUCTIONS: usize = 100_000;
+
+fn run(predecode: bool) -> Emu {
+    let mut emu = Emu::new();
+    emu.set_predecode(predecode);
+    emu.load_rom(include_bytes!("../../roms/BRIX")).unwrap();
+    for _ in 0..INSTRUCTIONS {
+        emu.tick();
+    }
+    emu
+}
+
+fn run_jit() -> Emu {
+    let mut emu = Emu::new();
+    emu.load_rom(include_bytes!("../../roms/BRIX")).unwrap();
+    BlockJit::new().run(&mut emu, INSTRUCTIONS).unwrap();
+    emu
+}
+
+fn predecode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tick");
+    group.bench_function("decode every tick", |b| b.iter(|| run(false)));
+    group.bench_function("predecoded", |b| b.iter(|| run(true)));
+    group.bench_function("block jit", |b| b.iter(run_jit));
+    group.finish();
+}
+
+criterion_group!(benches, predecode);
+criterion_main!(benches);
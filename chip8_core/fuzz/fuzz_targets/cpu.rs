@@ -0,0 +1,19 @@
+#![no_main]
+
+use chip8_core::backend::{Backend, BlockJit, Interpreter};
+use chip8_core_fuzz::{play, Input};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some(input) = Input::parse(data) else { return; };
+    let Some(mut stepped) = input.emu() else { return; };
+    let fault = play(&input, &mut stepped, None);
+
+    // Both backends stop at the same fault, in the same state
+    let backends: [Box<dyn Backend>; 2] = [Box::new(Interpreter), Box::new(BlockJit::new())];
+    for mut backend in backends {
+        let mut emu = input.emu().unwrap();
+        assert_eq!(play(&input, &mut emu, Some(&mut *backend)), fault);
+        assert_eq!(emu.snapshot(), stepped.snapshot());
+    }
+});
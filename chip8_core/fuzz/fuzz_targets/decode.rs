@@ -0,0 +1,14 @@
+#![no_main]
+
+use chip8_core::Opcode;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    for bytes in data.chunks_exact(2) {
+        let raw = u16::from_be_bytes([bytes[0], bytes[1]]);
+        let Ok(op) = Opcode::try_new(raw) else { continue; };
+        assert_eq!(op.encode(), raw, "{:?}", op);
+        assert_eq!(Opcode::try_new(op.encode()), Ok(op));
+        let _ = op.to_string();
+    }
+});
@@ -0,0 +1,33 @@
+#![no_main]
+
+use chip8_core::Emu;
+use chip8_core_fuzz::{run_frame, Input, FRAMES};
+use libfuzzer_sys::fuzz_target;
+
+/// Run frames `from..FRAMES`, returning the final state
+fn finish(input: &Input, emu: &mut Emu, from: usize) -> chip8_core::Snapshot {
+    for frame in from..FRAMES {
+        input.press_keys(emu, frame);
+        if run_frame(emu).is_err() { break; }
+    }
+    emu.snapshot()
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Some(input) = Input::parse(data) else { return; };
+    let Some(mut emu) = input.emu() else { return; };
+    let half = FRAMES / 2;
+    for frame in 0..half {
+        input.press_keys(&mut emu, frame);
+        if run_frame(&mut emu).is_err() { return; }
+    }
+
+    let snapshot = emu.snapshot();
+    let first = finish(&input, &mut emu, half);
+
+    // Restoring gives back exactly the captured state, and replaying from it the same result
+    emu.restore(&snapshot);
+    assert_eq!(emu.snapshot(), snapshot);
+    let second = finish(&input, &mut emu, half);
+    assert_eq!(first, second);
+});
@@ -0,0 +1,11 @@
+#!/bin/sh
+# Build the seed corpus from the ROMs in `roms/`, run from this directory
+# CPU inputs are the ROM with a 2 byte header: default quirks and no key presses
+set -e
+mkdir -p corpus/cpu corpus/snapshot corpus/decode
+for rom in ../../roms/*; do
+    name=$(basename "$rom")
+    { printf '\000\000'; cat "$rom"; } > "corpus/cpu/$name"
+    cp "corpus/cpu/$name" "corpus/snapshot/$name"
+    cp "$rom" "corpus/decode/$name"
+done
@@ -0,0 +1,102 @@
+//! Fuzz targets for `chip8_core`, run with cargo-fuzz:
+//!
+//! ```sh
+//! ./seed_corpus.sh
+//! cargo fuzz run cpu corpus/cpu
+//! ```
+//!
+//! - `decode`: every decodable instruction encodes back to the same bits
+//! - `cpu`: arbitrary ROMs (of any length), key presses and quirks never panic, faults come back
+//!   from `Emu::try_tick`, and both backends end up in the same state as stepping through it
+//! - `snapshot`: restoring a snapshot gives back identical state, and identical execution from there
+
+use chip8_core::backend::Backend;
+use chip8_core::constants::{MAX_ROM_SIZE, NUM_KEYS, TICKS_PER_FRAME};
+use chip8_core::{Emu, Fault, Quirks};
+
+/// Frames each input is run for
+pub const FRAMES: usize = 120;
+
+/// A ROM plus the settings and input to run it with
+///
+/// Laid out as `[quirks, key frames, key masks (2 bytes each)..., rom...]` rather than with
+/// `Arbitrary`, so ROM files with a 2 byte header (`[0, 0]`) make a useful seed corpus.
+pub struct Input<'a> {
+    pub quirks: Quirks,
+    /// Keys held on each frame (bit N is key N), repeated
+    pub keys: Vec<u16>,
+    pub rom: &'a [u8],
+}
+impl<'a> Input<'a> {
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        let (&[flags, frames], rest) = data.split_first_chunk::<2>()?;
+        let quirks = Quirks {
+            vf_reset: flags & 0x01 != 0,
+            memory_increment: flags & 0x02 != 0,
+            shift_vy: flags & 0x04 != 0,
+            jump_vx: flags & 0x08 != 0,
+            display_wait: flags & 0x10 != 0,
+            clip: flags & 0x20 != 0,
+        };
+        let keys_len = 2 * frames as usize;
+        if rest.len() < keys_len { return None; }
+        let (keys, rom) = rest.split_at(keys_len);
+        let keys = keys.chunks(2).map(|k| u16::from_be_bytes([k[0], k[1]])).collect();
+        Some(Self { quirks, keys, rom })
+    }
+
+    /// A fresh, deterministic emulator with the ROM loaded, `None` if it doesn't fit
+    ///
+    /// Checks that a ROM that doesn't fit leaves the emulator as it was.
+    pub fn emu(&self) -> Option<Emu> {
+        let mut emu = Emu::new();
+        emu.seed_rng(0);
+        emu.set_quirks(self.quirks);
+        let before = emu.snapshot();
+        if let Err(error) = emu.load_rom(self.rom) {
+            assert!(self.rom.len() > MAX_ROM_SIZE, "{}", error);
+            assert_eq!(emu.snapshot(), before, "{} changed the state", error);
+            return None;
+        }
+        Some(emu)
+    }
+
+    /// Press the keys for `frame`
+    pub fn press_keys(&self, emu: &mut Emu, frame: usize) {
+        let mask = if self.keys.is_empty() { 0 } else { self.keys[frame % self.keys.len()] };
+        for key in 0..NUM_KEYS {
+            emu.keypress(key, mask >> key & 1 != 0);
+        }
+    }
+}
+
+/// Run one 60Hz frame on the bare emulator, like `Emu::run_frame` but through `Emu::try_tick`
+///
+/// Checks that a fault leaves the emulator as it was, and returns it.
+pub fn run_frame(emu: &mut Emu) -> Result<(), Fault> {
+    for _ in 0..TICKS_PER_FRAME {
+        if emu.is_waiting_vblank() { break; }
+        let before = emu.snapshot();
+        if let Err(fault) = emu.try_tick() {
+            assert_eq!(emu.snapshot(), before, "{} changed the state", fault);
+            return Err(fault);
+        }
+    }
+    emu.tick_timers();
+    emu.vblank();
+    Ok(())
+}
+
+/// Play `FRAMES` frames of the input, through `backend` or else `run_frame`
+/// Returns the fault that stopped it, if any.
+pub fn play(input: &Input, emu: &mut Emu, mut backend: Option<&mut dyn Backend>) -> Option<Fault> {
+    for frame in 0..FRAMES {
+        input.press_keys(emu, frame);
+        let result = match &mut backend {
+            Some(backend) => emu.run_frame_with(*backend),
+            None => run_frame(emu),
+        };
+        if let Err(fault) = result { return Some(fault); }
+    }
+    None
+}
@@ -0,0 +1,234 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::Opcode;
+use super::Rom;
+
+/// How control leaves a basic block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Terminator {
+    /// Runs straight into the next block
+    Fallthrough(u16),
+    /// `Jump`
+    Jump(u16),
+    /// A skip instruction: continues at `next`, or at `skip` if the condition holds
+    Skip { next: u16, skip: u16 },
+    /// `Call`, continuing at `ret` once the subroutine returns
+    Call { target: u16, ret: u16 },
+    /// `Return`
+    Return,
+    /// `JumpV0Distance`, target depends on `V0`
+    Indirect(u16),
+    /// Runs into bytes that aren't code (end of the ROM or an unknown opcode)
+    End,
+}
+
+#[derive(Debug, Clone)]
+pub struct BasicBlock {
+    pub start: u16,
+    pub instructions: Vec<(u16, Opcode)>,
+    pub terminator: Terminator,
+}
+impl BasicBlock {
+    /// Blocks control can move to within the same subroutine
+    /// A `Call` continues at its return address
+    pub fn successors(&self) -> Vec<u16> {
+        return match self.terminator {
+            Terminator::Fallthrough(next) | Terminator::Jump(next) => vec![next],
+            Terminator::Skip { next, skip } => vec![next, skip],
+            Terminator::Call { ret, .. } => vec![ret],
+            Terminator::Return | Terminator::Indirect(_) | Terminator::End => vec![],
+        };
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Subroutine {
+    pub entry: u16,
+    /// Start addresses of the blocks reachable from the entry without following calls
+    pub blocks: BTreeSet<u16>,
+    /// Subroutines called from this one
+    pub calls: BTreeSet<u16>,
+}
+
+/// Control-flow graph of a ROM, split into basic blocks and subroutines
+/// The entry point (`START_ADDR`) is treated as a subroutine named `main`
+#[derive(Debug, Clone)]
+pub struct Cfg {
+    pub entry: u16,
+    pub blocks: BTreeMap<u16, BasicBlock>,
+    pub subroutines: BTreeMap<u16, Subroutine>,
+}
+impl Cfg {
+    pub fn build(rom: &Rom) -> Self {
+        let entry = rom.start();
+        let code = rom.reachable(&[entry]);
+        let decoded: BTreeMap<u16, Opcode> = code.iter().map(|addr| (*addr, rom.decode(*addr).unwrap())).collect();
+
+        // Every instruction control can arrive at other than by running into it starts a block
+        let mut leaders = BTreeSet::from([entry]);
+        for (addr, op) in &decoded {
+            let addr = *addr;
+            match *op {
+                Opcode::Jump(target) => { leaders.insert(target); leaders.insert(addr + 2); },
+                Opcode::Call(target) => { leaders.insert(target); leaders.insert(addr + 2); },
+                Opcode::Return | Opcode::JumpV0Distance(_) => { leaders.insert(addr + 2); },
+                op if op.is_skip() => { leaders.insert(addr + 2); leaders.insert(addr + 4); },
+                _ => {},
+            }
+        }
+        leaders.retain(|addr| code.contains(addr));
+
+        let mut blocks = BTreeMap::new();
+        for leader in &leaders {
+            let mut instructions = Vec::new();
+            let mut addr = *leader;
+            let terminator = loop {
+                let op = decoded[&addr];
+                instructions.push((addr, op));
+                let next = addr + 2;
+                match op {
+                    Opcode::Jump(target) => break Terminator::Jump(target),
+                    Opcode::Call(target) => break Terminator::Call { target, ret: next },
+                    Opcode::Return => break Terminator::Return,
+                    Opcode::JumpV0Distance(base) => break Terminator::Indirect(base),
+                    op if op.is_skip() => break Terminator::Skip { next, skip: addr + 4 },
+                    _ => {},
+                }
+                if !code.contains(&next) { break Terminator::End; }
+                if leaders.contains(&next) { break Terminator::Fallthrough(next); }
+                addr = next;
+            };
+            blocks.insert(*leader, BasicBlock { start: *leader, instructions, terminator });
+        }
+
+        let mut subroutines = BTreeMap::new();
+        let mut pending = vec![entry];
+        while let Some(sub) = pending.pop() {
+            if subroutines.contains_key(&sub) || !blocks.contains_key(&sub) { continue; }
+            let mut owned = BTreeSet::new();
+            let mut calls = BTreeSet::new();
+            let mut queue = vec![sub];
+            while let Some(start) = queue.pop() {
+                if !owned.insert(start) { continue; }
+                let Some(block) = blocks.get(&start) else { continue; };
+                if let Terminator::Call { target, .. } = block.terminator { calls.insert(target); }
+                queue.extend(block.successors().into_iter().filter(|s| blocks.contains_key(s)));
+            }
+            owned.retain(|start| blocks.contains_key(start));
+            pending.extend(calls.iter());
+            subroutines.insert(sub, Subroutine { entry: sub, blocks: owned, calls });
+        }
+
+        return Self { entry, blocks, subroutines };
+    }
+
+    /// Name used for a subroutine in reports
+    pub fn subroutine_name(&self, addr: u16) -> String {
+        if addr == self.entry { return "main".to_string(); }
+        return format!("sub_{:03X}", addr);
+    }
+
+    /// Subroutine each block is drawn in (the first one that owns it)
+    fn owners(&self) -> BTreeMap<u16, u16> {
+        let mut owners = BTreeMap::new();
+        for sub in self.subroutines.values() {
+            for block in &sub.blocks {
+                owners.entry(*block).or_insert(sub.entry);
+            }
+        }
+        return owners;
+    }
+
+    /// The whole CFG as Graphviz DOT, with one cluster per subroutine
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "digraph cfg {{").unwrap();
+        writeln!(out, "  node [shape=box, fontname=\"monospace\"];").unwrap();
+
+        let owners = self.owners();
+        for sub in self.subroutines.values() {
+            writeln!(out, "  subgraph cluster_{:03X} {{", sub.entry).unwrap();
+            writeln!(out, "    label=\"{}\";", self.subroutine_name(sub.entry)).unwrap();
+            for start in sub.blocks.iter().filter(|b| owners[b] == sub.entry) {
+                self.write_block(&mut out, &self.blocks[start]);
+            }
+            writeln!(out, "  }}").unwrap();
+        }
+        for block in self.blocks.values().filter(|b| !owners.contains_key(&b.start)) {
+            self.write_block(&mut out, block);
+        }
+        for block in self.blocks.values() {
+            self.write_edges(&mut out, block, true);
+        }
+
+        writeln!(out, "}}").unwrap();
+        return out;
+    }
+
+    /// A single subroutine's CFG as Graphviz DOT
+    pub fn subroutine_dot(&self, entry: u16) -> Option<String> {
+        let sub = self.subroutines.get(&entry)?;
+        let mut out = String::new();
+        writeln!(out, "digraph {} {{", self.subroutine_name(entry)).unwrap();
+        writeln!(out, "  node [shape=box, fontname=\"monospace\"];").unwrap();
+        for start in &sub.blocks {
+            self.write_block(&mut out, &self.blocks[start]);
+        }
+        for start in &sub.blocks {
+            self.write_edges(&mut out, &self.blocks[start], false);
+        }
+        writeln!(out, "}}").unwrap();
+        return Some(out);
+    }
+
+    /// The call graph between subroutines as Graphviz DOT
+    pub fn call_graph_dot(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "digraph calls {{").unwrap();
+        writeln!(out, "  node [shape=ellipse, fontname=\"monospace\"];").unwrap();
+        for sub in self.subroutines.values() {
+            let indirect = sub.blocks.iter().any(|b| matches!(self.blocks[b].terminator, Terminator::Indirect(_)));
+            let style = if indirect { ", peripheries=2" } else { "" };
+            writeln!(out, "  \"{0}\" [label=\"{0}\"{1}];", self.subroutine_name(sub.entry), style).unwrap();
+        }
+        for sub in self.subroutines.values() {
+            for callee in &sub.calls {
+                writeln!(out, "  \"{}\" -> \"{}\";", self.subroutine_name(sub.entry), self.subroutine_name(*callee)).unwrap();
+            }
+        }
+        writeln!(out, "}}").unwrap();
+        return out;
+    }
+
+    fn write_block(&self, out: &mut String, block: &BasicBlock) {
+        let mut label = String::new();
+        for (addr, op) in &block.instructions {
+            write!(label, "{:03X}: {}\\l", addr, op).unwrap();
+        }
+        writeln!(out, "    \"b{:03X}\" [label=\"{}\"];", block.start, label).unwrap();
+    }
+
+    fn write_edges(&self, out: &mut String, block: &BasicBlock, calls: bool) {
+        let from = block.start;
+        match block.terminator {
+            Terminator::Fallthrough(next) => writeln!(out, "  \"b{:03X}\" -> \"b{:03X}\";", from, next).unwrap(),
+            Terminator::Jump(target) => writeln!(out, "  \"b{:03X}\" -> \"b{:03X}\" [label=\"jump\"];", from, target).unwrap(),
+            Terminator::Skip { next, skip } => {
+                writeln!(out, "  \"b{:03X}\" -> \"b{:03X}\";", from, next).unwrap();
+                writeln!(out, "  \"b{:03X}\" -> \"b{:03X}\" [label=\"skip\", style=dashed];", from, skip).unwrap();
+            },
+            Terminator::Call { target, ret } => {
+                writeln!(out, "  \"b{:03X}\" -> \"b{:03X}\" [label=\"return\", style=dotted];", from, ret).unwrap();
+                if calls {
+                    writeln!(out, "  \"b{:03X}\" -> \"b{:03X}\" [label=\"call\", color=blue];", from, target).unwrap();
+                }
+            },
+            Terminator::Indirect(base) => {
+                writeln!(out, "  \"i{:03X}\" [label=\"V0 + {:#05X}\", shape=diamond];", from, base).unwrap();
+                writeln!(out, "  \"b{:03X}\" -> \"i{:03X}\" [label=\"indirect\", style=dashed, color=red];", from, from).unwrap();
+            },
+            Terminator::Return | Terminator::End => {},
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::Opcode;
+use super::{Cfg, Rom};
+
+/// Decompile a ROM into structured pseudocode
+///
+/// Each subroutine becomes a function. Skip instructions guarding a forward jump
+/// become `if` (and `else`) blocks, backward jumps become `loop`s with `continue`
+/// and `break`, and anything that can't be structured falls back to `goto`.
+pub fn decompile(rom: &Rom) -> String {
+    let cfg = Cfg::build(rom);
+    let mut out = String::new();
+    for sub in cfg.subroutines.values() {
+        let instructions: BTreeMap<u16, Opcode> = sub.blocks.iter()
+            .flat_map(|b| cfg.blocks[b].instructions.iter().copied())
+            .collect();
+
+        let mut function = Function { cfg: &cfg, instructions, lines: Vec::new(), gotos: BTreeSet::new() };
+        let end = function.instructions.keys().next_back().map_or(sub.entry, |a| a + 2);
+        let start = *function.instructions.keys().next().unwrap_or(&sub.entry);
+        function.range(start, end, 1, None, None);
+
+        if !out.is_empty() { out.push('\n'); }
+        out.push_str(&format!("fn {}() {{\n", cfg.subroutine_name(sub.entry)));
+        if start != sub.entry {
+            out.push_str(&format!("    goto label_{:03X};\n", sub.entry));
+        }
+        out.push_str(&function.render(sub.entry));
+        out.push_str("}\n");
+    }
+    return out;
+}
+
+#[derive(Clone, Copy)]
+struct Loop {
+    head: u16,
+    exit: u16,
+}
+
+struct Line {
+    indent: usize,
+    addr: Option<u16>,
+    text: String,
+}
+
+struct Function<'a> {
+    cfg: &'a Cfg,
+    instructions: BTreeMap<u16, Opcode>,
+    lines: Vec<Line>,
+    gotos: BTreeSet<u16>,
+}
+impl Function<'_> {
+    fn push(&mut self, indent: usize, addr: Option<u16>, text: String) {
+        self.lines.push(Line { indent, addr, text });
+    }
+
+    fn op(&self, addr: u16) -> Option<Opcode> {
+        return self.instructions.get(&addr).copied();
+    }
+
+    /// Emit every instruction in `[from, to)`
+    /// `entered` is a loop head that has already been opened, so it isn't opened again
+    fn range(&mut self, from: u16, to: u16, indent: usize, current: Option<Loop>, entered: Option<u16>) {
+        let mut cursor = from;
+        while let Some((&addr, &op)) = self.instructions.range(cursor..to).next() {
+            // Loops: the furthest backward jump to this address within the range
+            let back_edge = self.instructions.range(addr..to)
+                .filter(|(_, op)| **op == Opcode::Jump(addr))
+                .map(|(a, _)| *a)
+                .next_back();
+            if let Some(last) = back_edge.filter(|_| entered != Some(addr)) {
+                let inner = Loop { head: addr, exit: last + 2 };
+                self.push(indent, Some(addr), "loop {".to_string());
+                self.range(addr, inner.exit, indent + 1, Some(inner), Some(addr));
+                // A conditional `continue` at the end of the loop falls out of it otherwise
+                if self.op(last.wrapping_sub(2)).is_some_and(|op| op.is_skip()) {
+                    self.push(indent + 1, None, "break;".to_string());
+                }
+                self.push(indent, None, "}".to_string());
+                cursor = inner.exit;
+                continue;
+            }
+
+            if op.is_skip() {
+                cursor = self.conditional(addr, op, to, indent, current);
+                continue;
+            }
+
+            let text = self.statement(addr, op, current);
+            if !text.is_empty() { self.push(indent, Some(addr), text); }
+            cursor = addr + 2;
+        }
+    }
+
+    /// Emit a skip instruction and whatever it guards, returning where to continue
+    fn conditional(&mut self, addr: u16, op: Opcode, to: u16, indent: usize, current: Option<Loop>) -> u16 {
+        let next = addr + 2;
+        let skip = condition(op);
+
+        // `skip if C; jump L` around a forward block: `if C { ... }`
+        if let Some(Opcode::Jump(target)) = self.op(next) {
+            let structured = target > next + 2 && target <= to
+                && current.is_none_or(|l| target != l.exit && target != l.head);
+            if structured {
+                let body = next + 2;
+                // A jump at the end of the body over a following block: `else { ... }`
+                let else_jump = target - 2;
+                let has_else = else_jump >= body
+                    && !self.op(else_jump.wrapping_sub(2)).is_some_and(|op| op.is_skip());
+                if let (true, Some(Opcode::Jump(end))) = (has_else, self.op(else_jump)) {
+                    if end > target && end <= to && current.is_none_or(|l| end != l.exit) {
+                        self.push(indent, Some(addr), format!("if {} {{", skip));
+                        self.range(body, else_jump, indent + 1, current, None);
+                        self.push(indent, None, "} else {".to_string());
+                        self.range(target, end, indent + 1, current, None);
+                        self.push(indent, None, "}".to_string());
+                        return end;
+                    }
+                }
+                self.push(indent, Some(addr), format!("if {} {{", skip));
+                self.range(body, target, indent + 1, current, None);
+                self.push(indent, None, "}".to_string());
+                return target;
+            }
+        }
+
+        // Otherwise the skip only guards the next instruction
+        match self.op(next) {
+            Some(guarded) if next < to => {
+                let text = self.statement(next, guarded, current);
+                let text = if text.is_empty() { "continue;".to_string() } else { text };
+                self.push(indent, Some(addr), format!("if {} {{", negate(op)));
+                self.push(indent + 1, Some(next), text);
+                self.push(indent, None, "}".to_string());
+                return next + 2;
+            },
+            _ => {
+                self.push(indent, Some(addr), format!("if {} {{ skip; }}", skip));
+                return next;
+            },
+        }
+    }
+
+    fn statement(&mut self, addr: u16, op: Opcode, current: Option<Loop>) -> String {
+        use Opcode::*;
+        return match op {
+            Nop => "// nop".to_string(),
+            ClearScreen => "clear();".to_string(),
+            Return => "return;".to_string(),
+            Jump(target) => match current {
+                // The loop's closing jump is implied by the loop itself
+                Some(l) if target == l.head && addr + 2 == l.exit => String::new(),
+                Some(l) if target == l.head => "continue;".to_string(),
+                Some(l) if target == l.exit => "break;".to_string(),
+                _ => {
+                    self.gotos.insert(target);
+                    format!("goto label_{:03X};", target)
+                },
+            },
+            Call(target) => format!("{}();", self.cfg.subroutine_name(target)),
+            SetToVal(x, nn) => format!("v{:X} = {:#04X};", x, nn),
+            AddVal(x, nn) => format!("v{:X} += {:#04X};", x, nn),
+            SetToReg(x, y) => format!("v{:X} = v{:X};", x, y),
+            BitwiseOr(x, y) => format!("v{:X} |= v{:X};", x, y),
+            BitwiseAnd(x, y) => format!("v{:X} &= v{:X};", x, y),
+            BitwiseXor(x, y) => format!("v{:X} ^= v{:X};", x, y),
+            AddReg(x, y) => format!("v{:X} += v{:X}; // vF = carry", x, y),
+            SubReg(x, y) => format!("v{:X} -= v{:X}; // vF = !borrow", x, y),
+            ShiftRight(x, _) => format!("v{:X} >>= 1; // vF = dropped bit", x),
+            SubFromReg(x, y) => format!("v{:X} = v{:X} - v{:X}; // vF = !borrow", x, y, x),
+            ShiftLeft(x, _) => format!("v{:X} <<= 1; // vF = overflowed bit", x),
+            SetIndex(target) => format!("i = {:#05X};", target),
+            JumpV0Distance(base) => format!("goto *(v0 + {:#05X});", base),
+            Rand(x, nn) => format!("v{:X} = random() & {:#04X};", x, nn),
+            DrawSprite(x, y, n) => format!("vF = draw(v{:X}, v{:X}, {});", x, y, n),
+            GetDelayTimer(x) => format!("v{:X} = delay;", x),
+            WaitKey(x) => format!("v{:X} = wait_key();", x),
+            SetDelayTimer(x) => format!("delay = v{:X};", x),
+            SetSoundTimer(x) => format!("sound = v{:X};", x),
+            IncrementI(x) => format!("i += v{:X};", x),
+            LoadFontChar(x) => format!("i = font(v{:X});", x),
+            BCD(x) => format!("{}[0..3] = bcd(v{:X});", self.array(addr), x),
+            LoadIntoRam(x) => format!("{}[0..={}] = {};", self.array(addr), x, registers(x)),
+            LoadFromRam(x) => format!("{} = {}[0..={}];", registers(x), self.array(addr), x),
+            SkipIfValEQ(..) | SkipIfValNE(..) | SkipIfRegEQ(..) | SkipIfRegNE(..) |
+            SkipIfKeyPressed(_) | SkipIfKeyNotPressed(_) => format!("if {} {{ skip; }}", condition(op)),
+        };
+    }
+
+    /// Name for the memory `I` points at when accessed from `addr`
+    /// A `SetIndex` right before gives it a name, otherwise it's plain memory at `I`
+    fn array(&self, addr: u16) -> String {
+        return match self.op(addr.wrapping_sub(2)) {
+            Some(Opcode::SetIndex(target)) => format!("data_{:03X}", target),
+            _ => "mem[i..]".to_string(),
+        };
+    }
+
+    /// Lines with labels for every `goto` target, and the indentation applied
+    fn render(&self, entry: u16) -> String {
+        let mut gotos = self.gotos.clone();
+        if self.lines.first().and_then(|l| l.addr) != Some(entry) { gotos.insert(entry); }
+
+        let mut out = String::new();
+        let mut labelled = BTreeSet::new();
+        for line in &self.lines {
+            if let Some(addr) = line.addr {
+                if gotos.contains(&addr) && labelled.insert(addr) {
+                    out.push_str(&format!("{}label_{:03X}:\n", "    ".repeat(line.indent.saturating_sub(1)), addr));
+                }
+            }
+            out.push_str(&format!("{}{}\n", "    ".repeat(line.indent), line.text));
+        }
+        return out;
+    }
+}
+
+/// Condition under which a skip instruction skips
+fn condition(op: Opcode) -> String {
+    use Opcode::*;
+    return match op {
+        SkipIfValEQ(x, nn) => format!("v{:X} == {:#04X}", x, nn),
+        SkipIfValNE(x, nn) => format!("v{:X} != {:#04X}", x, nn),
+        SkipIfRegEQ(x, y) => format!("v{:X} == v{:X}", x, y),
+        SkipIfRegNE(x, y) => format!("v{:X} != v{:X}", x, y),
+        SkipIfKeyPressed(x) => format!("key(v{:X})", x),
+        SkipIfKeyNotPressed(x) => format!("!key(v{:X})", x),
+        _ => unreachable!("{:?} is not a skip", op),
+    };
+}
+
+/// Condition under which a skip instruction doesn't skip
+fn negate(op: Opcode) -> String {
+    use Opcode::*;
+    return match op {
+        SkipIfValEQ(x, nn) => condition(SkipIfValNE(x, nn)),
+        SkipIfValNE(x, nn) => condition(SkipIfValEQ(x, nn)),
+        SkipIfRegEQ(x, y) => condition(SkipIfRegNE(x, y)),
+        SkipIfRegNE(x, y) => condition(SkipIfRegEQ(x, y)),
+        SkipIfKeyPressed(x) => condition(SkipIfKeyNotPressed(x)),
+        SkipIfKeyNotPressed(x) => condition(SkipIfKeyPressed(x)),
+        _ => unreachable!("{:?} is not a skip", op),
+    };
+}
+
+/// `[v0, v1, ..., vX]`
+fn registers(x: usize) -> String {
+    let regs: Vec<String> = (0..=x).map(|r| format!("v{:X}", r)).collect();
+    return format!("[{}]", regs.join(", "));
+}
@@ -0,0 +1,253 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::constants::{RAM_SIZE, START_ADDR, STACK_SIZE};
+use crate::Opcode;
+use super::Rom;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Extension {
+    SuperChip,
+    XoChip,
+}
+impl fmt::Display for Extension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            Extension::SuperChip => write!(f, "SCHIP"),
+            Extension::XoChip => write!(f, "XO-CHIP"),
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub addr: u16,
+    pub message: String,
+}
+
+/// Result of linting a ROM
+#[derive(Debug, Clone)]
+pub struct LintReport {
+    /// Size of the ROM in bytes
+    pub size: usize,
+    /// Number of statically reachable instructions
+    pub instructions: usize,
+    /// Extensions the ROM uses instructions from
+    pub extensions: BTreeSet<Extension>,
+    /// Deepest chain of nested `Call`s, `None` if the ROM is recursive
+    pub max_call_depth: Option<usize>,
+    pub findings: Vec<Finding>,
+}
+impl LintReport {
+    /// Whether the ROM only needs the base instruction set and no quirks
+    pub fn is_clean(&self) -> bool {
+        return self.findings.iter().all(|f| f.severity == Severity::Info);
+    }
+
+    /// Overview of the ROM, without the individual findings
+    pub fn summary(&self) -> String {
+        let extensions: Vec<String> = self.extensions.iter().map(|e| e.to_string()).collect();
+        let extensions = if extensions.is_empty() { "none".to_string() } else { extensions.join(", ") };
+        let depth = match self.max_call_depth {
+            Some(depth) => format!("{} (stack size {})", depth, STACK_SIZE),
+            None => "unbounded (recursive)".to_string(),
+        };
+        return format!(
+            "Size: {} bytes\nReachable instructions: {}\nExtensions: {}\nMax call depth: {}\n",
+            self.size, self.instructions, extensions, depth,
+        );
+    }
+}
+impl fmt::Display for LintReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.summary())?;
+        for finding in &self.findings {
+            let severity = match finding.severity {
+                Severity::Info => "info",
+                Severity::Warning => "warning",
+                Severity::Error => "error",
+            };
+            writeln!(f, "{:#05X}: {}: {}", finding.addr, severity, finding.message)?;
+        }
+        return Ok(());
+    }
+}
+
+/// Instructions from the SCHIP and XO-CHIP extensions
+/// Returns the extension and a short description
+fn extension(opcode: u16) -> Option<(Extension, &'static str)> {
+    use Extension::*;
+    let x = (opcode & 0x0F00) >> 8;
+    return Some(match (opcode & 0xF000) >> 12 {
+        0x0 => match opcode & 0x0FFF {
+            0x0C1..=0x0CF => (SuperChip, "scroll down"),
+            0x0D1..=0x0DF => (XoChip, "scroll up"),
+            0x0FB => (SuperChip, "scroll right"),
+            0x0FC => (SuperChip, "scroll left"),
+            0x0FD => (SuperChip, "exit"),
+            0x0FE => (SuperChip, "low resolution"),
+            0x0FF => (SuperChip, "high resolution"),
+            _ => return None,
+        },
+        0x5 => match opcode & 0x000F {
+            0x2 => (XoChip, "save register range"),
+            0x3 => (XoChip, "load register range"),
+            _ => return None,
+        },
+        0xD if opcode & 0x000F == 0 => (SuperChip, "16x16 sprite"),
+        0xF => match opcode & 0x00FF {
+            0x00 if x == 0 => (XoChip, "long index load"),
+            0x01 => (XoChip, "select plane"),
+            0x02 if x == 0 => (XoChip, "load audio pattern"),
+            0x30 => (SuperChip, "large font character"),
+            0x3A => (XoChip, "set pitch"),
+            0x75 => (SuperChip, "save flags"),
+            0x85 => (SuperChip, "load flags"),
+            _ => return None,
+        },
+        _ => return None,
+    });
+}
+
+/// Lint a ROM without running it
+pub fn lint(bytes: &[u8]) -> LintReport {
+    let rom = Rom::new(bytes);
+    let mut findings = Vec::new();
+    let mut extensions = BTreeSet::new();
+
+    let space = RAM_SIZE - START_ADDR as usize;
+    if bytes.len() > space {
+        findings.push(Finding { severity: Severity::Error, addr: rom.start(), message: format!("ROM is {} bytes, only {} fit in RAM", bytes.len(), space) });
+    }
+
+    // Walk the code, stepping over extension instructions rather than stopping at them
+    let mut code = BTreeSet::new();
+    let mut queue = vec![rom.start()];
+    while let Some(addr) = queue.pop() {
+        if code.contains(&addr) { continue; }
+        let Some(raw) = rom.fetch(addr) else {
+            findings.push(Finding { severity: Severity::Error, addr, message: "execution runs off the end of the ROM".to_string() });
+            continue;
+        };
+        code.insert(addr);
+
+        if let Some((ext, desc)) = extension(raw) {
+            extensions.insert(ext);
+            findings.push(Finding { severity: Severity::Warning, addr, message: format!("{} instruction {:04X} ({})", ext, raw, desc) });
+            match raw {
+                0x00FD => {},
+                0xF000 => queue.push(addr + 4), // Followed by a 16-bit address
+                _ => queue.push(addr + 2),
+            }
+            continue;
+        }
+
+        let Ok(op) = Opcode::try_new(raw) else {
+            findings.push(Finding { severity: Severity::Error, addr, message: format!("unknown instruction {:04X}", raw) });
+            continue;
+        };
+        queue.extend(Rom::successors(addr, op));
+    }
+
+    let data: BTreeSet<u16> = code.iter()
+        .filter_map(|addr| match rom.decode(*addr) { Some(Opcode::SetIndex(target)) => Some(target), _ => None })
+        .collect();
+    let stores = code.iter().any(|addr| matches!(rom.decode(*addr), Some(Opcode::BCD(_) | Opcode::LoadIntoRam(_))));
+
+    for addr in &code {
+        let addr = *addr;
+        let raw = rom.fetch(addr).unwrap();
+        let Ok(op) = Opcode::try_new(raw) else { continue; };
+
+        let quirk = |message: &str| Finding { severity: Severity::Warning, addr, message: message.to_string() };
+        match op {
+            Opcode::ShiftRight(x, y) | Opcode::ShiftLeft(x, y) if x != y => {
+                findings.push(quirk("shift with VX != VY depends on the shift quirk (VY is ignored by default)"));
+            },
+            Opcode::LoadIntoRam(_) | Opcode::LoadFromRam(_) => {
+                findings.push(quirk("register save/load depends on the memory quirk (I is not incremented by default)"));
+            },
+            Opcode::JumpV0Distance(_) => {
+                findings.push(quirk("jump with offset depends on the jump quirk and is an indirect jump"));
+            },
+            Opcode::SetIndex(target) if stores && code.contains(&target) => {
+                findings.push(Finding { severity: Severity::Warning, addr, message: format!("I points into code at {:#05X}, likely self-modifying", target) });
+            },
+            Opcode::Jump(target) | Opcode::Call(target) => {
+                if !rom.contains(target) {
+                    findings.push(Finding { severity: Severity::Error, addr, message: format!("jump to {:#05X} outside the ROM", target) });
+                } else if data.contains(&target) {
+                    findings.push(Finding { severity: Severity::Warning, addr, message: format!("jump to {:#05X}, which is also loaded into I as data", target) });
+                }
+            },
+            _ => {},
+        }
+    }
+
+    let max_call_depth = max_call_depth(&rom);
+    match max_call_depth {
+        None => findings.push(Finding { severity: Severity::Warning, addr: rom.start(), message: "recursive calls, stack depth is unbounded".to_string() }),
+        Some(depth) if depth >= STACK_SIZE => findings.push(Finding {
+            severity: Severity::Error,
+            addr: rom.start(),
+            message: format!("call depth {} overflows the stack, which holds {} return addresses", depth, STACK_SIZE - 1),
+        }),
+        _ => {},
+    }
+
+    findings.sort_by(|a, b| a.addr.cmp(&b.addr).then(b.severity.cmp(&a.severity)));
+    findings.dedup();
+    return LintReport {
+        size: bytes.len(),
+        instructions: code.len(),
+        extensions,
+        max_call_depth,
+        findings,
+    };
+}
+
+/// Deepest static nesting of `Call`s from the entry point, `None` if recursive
+fn max_call_depth(rom: &Rom) -> Option<usize> {
+    // Subroutines called directly from each subroutine's body
+    let mut callees: BTreeMap<u16, BTreeSet<u16>> = BTreeMap::new();
+    let mut pending = vec![rom.start()];
+    while let Some(sub) = pending.pop() {
+        if callees.contains_key(&sub) { continue; }
+        let mut calls = BTreeSet::new();
+        let mut seen = BTreeSet::new();
+        let mut queue = vec![sub];
+        while let Some(addr) = queue.pop() {
+            if !seen.insert(addr) { continue; }
+            let Some(op) = rom.decode(addr) else { continue; };
+            match op {
+                // Don't follow into the callee, it has its own body
+                Opcode::Call(target) => { calls.insert(target); queue.push(addr + 2); },
+                _ => queue.extend(Rom::successors(addr, op)),
+            }
+        }
+        pending.extend(calls.iter());
+        callees.insert(sub, calls);
+    }
+
+    fn depth(sub: u16, callees: &BTreeMap<u16, BTreeSet<u16>>, path: &mut Vec<u16>, memo: &mut BTreeMap<u16, usize>) -> Option<usize> {
+        if let Some(d) = memo.get(&sub) { return Some(*d); }
+        if path.contains(&sub) { return None; }
+        path.push(sub);
+        let mut deepest = 0;
+        for callee in &callees[&sub] {
+            deepest = deepest.max(1 + depth(*callee, callees, path, memo)?);
+        }
+        path.pop();
+        memo.insert(sub, deepest);
+        return Some(deepest);
+    }
+    return depth(rom.start(), &callees, &mut Vec::new(), &mut BTreeMap::new());
+}
@@ -0,0 +1,98 @@
+use std::collections::BTreeSet;
+
+use crate::constants::{RAM_SIZE, START_ADDR};
+use crate::Opcode;
+
+pub mod lint;
+pub use lint::{lint, LintReport};
+
+pub mod cfg;
+pub use cfg::Cfg;
+
+pub mod decompile;
+pub use decompile::decompile;
+
+/// A ROM image as it would be laid out in RAM
+/// Bytes that wouldn't fit in RAM are left out.
+#[derive(Debug, Clone, Copy)]
+pub struct Rom<'a> {
+    bytes: &'a [u8],
+    base: u16,
+}
+impl<'a> Rom<'a> {
+    /// A ROM loaded at `START_ADDR`
+    pub fn new(bytes: &'a [u8]) -> Self {
+        return Self::with_base(bytes, START_ADDR);
+    }
+
+    /// A memory image starting at `base` (e.g. all of RAM with a base of 0)
+    pub fn with_base(bytes: &'a [u8], base: u16) -> Self {
+        let len = bytes.len().min(RAM_SIZE.saturating_sub(base as usize));
+        return Self { bytes: &bytes[..len], base };
+    }
+
+    pub fn bytes(&self) -> &'a [u8] {
+        return self.bytes;
+    }
+
+    /// First address of the image
+    pub fn start(&self) -> u16 {
+        return self.base;
+    }
+
+    /// One past the last address of the image
+    pub fn end(&self) -> u16 {
+        return self.base + self.bytes.len() as u16;
+    }
+
+    pub fn contains(&self, addr: u16) -> bool {
+        return addr >= self.start() && addr < self.end();
+    }
+
+    pub fn byte(&self, addr: u16) -> Option<u8> {
+        if !self.contains(addr) { return None; }
+        return Some(self.bytes[(addr - self.base) as usize]);
+    }
+
+    /// The raw two-byte opcode at `addr`
+    pub fn fetch(&self, addr: u16) -> Option<u16> {
+        let higher_byte = self.byte(addr)? as u16;
+        let lower_byte = self.byte(addr + 1)? as u16;
+        return Some((higher_byte << 8) | lower_byte);
+    }
+
+    /// The decoded opcode at `addr`, if it is part of the base instruction set
+    pub fn decode(&self, addr: u16) -> Option<Opcode> {
+        return Opcode::try_new(self.fetch(addr)?).ok();
+    }
+
+    /// Addresses execution can continue at after `op` at `addr`
+    /// `Call` continues at both the target and the return address,
+    /// `Return` and `JumpV0Distance` have no static successors
+    pub fn successors(addr: u16, op: Opcode) -> Vec<u16> {
+        use Opcode::*;
+        let next = |offset: u16| addr.checked_add(offset);
+        let successors = match op {
+            Jump(target) => vec![Some(target)],
+            Call(target) => vec![Some(target), next(2)],
+            Return | JumpV0Distance(_) => vec![],
+            op if op.is_skip() => vec![next(2), next(4)],
+            _ => vec![next(2)],
+        };
+        return successors.into_iter().flatten().collect();
+    }
+
+    /// Every instruction address reachable from `entries`
+    /// Stops at addresses outside the image and at opcodes unknown to the base set
+    pub fn reachable(&self, entries: &[u16]) -> BTreeSet<u16> {
+        let mut found = BTreeSet::new();
+        let mut queue = entries.to_vec();
+        while let Some(addr) = queue.pop() {
+            if !self.contains(addr) || found.contains(&addr) { continue; }
+            let Some(op) = self.decode(addr) else { continue; };
+            found.insert(addr);
+            queue.extend(Self::successors(addr, op));
+        }
+        return found;
+    }
+}
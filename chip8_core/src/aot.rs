@@ -0,0 +1,250 @@
+use std::fmt::Write;
+use std::io;
+use std::path::Path;
+
+use crate::analysis::cfg::BasicBlock;
+use crate::analysis::{Cfg, Rom};
+use crate::Opcode;
+
+/// Translate a ROM ahead of time into a Rust module
+///
+/// Every basic block found by control-flow analysis becomes a function operating on an `Emu`.
+/// The module exports the original `ROM` and a `Translated` backend that dispatches on `pc`,
+/// to be run with `Emu::run_frame_with` after loading the ROM. Anything that isn't the start
+/// of a translated block (`JumpV0Distance` targets, self-modified code) is interpreted.
+///
+/// `name` is only used in the generated module's doc comment.
+/// See `translate_crate` to build it into a binary.
+pub fn translate(rom: &Rom, name: &str) -> String {
+    let cfg = Cfg::build(rom);
+    let mut out = String::new();
+
+    writeln!(out, "//! `{}`, translated ahead of time by `chip8_core::aot`", name).unwrap();
+    writeln!(out, "//! Generated code, do not edit").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "use chip8_core::backend::{{Backend, Interpreter}};").unwrap();
+    writeln!(out, "use chip8_core::{{Emu, Fault, Opcode}};").unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "/// The original ROM, to be loaded with `Emu::load_rom`").unwrap();
+    writeln!(out, "pub const ROM: [u8; {}] = [", rom.bytes().len()).unwrap();
+    for chunk in rom.bytes().chunks(16) {
+        writeln!(out, "    {},", byte_list(chunk)).unwrap();
+    }
+    writeln!(out, "];").unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "/// Runs the translated blocks, interpreting any code that wasn't translated or has been modified").unwrap();
+    writeln!(out, "#[derive(Debug, Clone, Copy, Default)]").unwrap();
+    writeln!(out, "pub struct Translated;").unwrap();
+    writeln!(out, "impl Backend for Translated {{").unwrap();
+    writeln!(out, "    fn run(&mut self, emu: &mut Emu, budget: usize) -> Result<usize, Fault> {{").unwrap();
+    writeln!(out, "        if emu.profiler().is_some() || emu.coverage().is_some() {{").unwrap();
+    writeln!(out, "            return Interpreter.run(emu, budget);").unwrap();
+    writeln!(out, "        }}").unwrap();
+    writeln!(out, "        let mut executed = 0;").unwrap();
+    writeln!(out, "        while executed < budget && !emu.is_waiting_vblank() {{").unwrap();
+    writeln!(out, "            let left = budget - executed;").unwrap();
+    writeln!(out, "            let ran = match emu.pc() {{").unwrap();
+    for start in cfg.blocks.keys() {
+        writeln!(out, "                0x{:03X} => block_{:03x}(emu, left)?,", start, start).unwrap();
+    }
+    writeln!(out, "                _ => 0,").unwrap();
+    writeln!(out, "            }};").unwrap();
+    writeln!(out, "            executed += if ran == 0 {{ Interpreter.run(emu, 1)? }} else {{ ran }};").unwrap();
+    writeln!(out, "        }}").unwrap();
+    writeln!(out, "        Ok(executed)").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+
+    for block in cfg.blocks.values() {
+        writeln!(out).unwrap();
+        out.push_str(&translate_block(rom, block));
+    }
+    return out;
+}
+
+/// A binary crate running a translated ROM, see `translate_crate`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Crate {
+    /// `Cargo.toml`
+    pub manifest: String,
+    /// `src/main.rs`
+    pub main: String,
+    /// `src/rom.rs`, the module from `translate`
+    pub module: String,
+}
+impl Crate {
+    /// Write the crate's files into `dir`, creating it if needed
+    pub fn write(&self, dir: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(dir.join("src"))?;
+        std::fs::write(dir.join("Cargo.toml"), &self.manifest)?;
+        std::fs::write(dir.join("src/main.rs"), &self.main)?;
+        std::fs::write(dir.join("src/rom.rs"), &self.module)?;
+        return Ok(());
+    }
+}
+
+/// Translate a ROM ahead of time into a binary crate, depending on `chip8_core` at `core_path`
+///
+/// The binary plays the ROM headless: `cargo run -- [frames] [keys] [seed]` runs `frames` frames
+/// (600 by default) holding `keys` (hex digits, `-` for none) with the random number generator
+/// seeded with `seed`, then prints the screen.
+pub fn translate_crate(rom: &Rom, name: &str, core_path: &Path) -> Crate {
+    let mut package: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    if !package.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        package.insert_str(0, "rom_");
+    }
+
+    let mut manifest = String::new();
+    writeln!(manifest, "# `{}`, translated ahead of time by `chip8_core::aot`", name).unwrap();
+    writeln!(manifest, "[package]").unwrap();
+    writeln!(manifest, "name = \"{}\"", package).unwrap();
+    writeln!(manifest, "version = \"0.1.0\"").unwrap();
+    writeln!(manifest, "edition = \"2021\"").unwrap();
+    writeln!(manifest).unwrap();
+    writeln!(manifest, "[dependencies]").unwrap();
+    writeln!(manifest, "chip8_core = {{ path = {:?} }}", core_path.to_string_lossy()).unwrap();
+    writeln!(manifest).unwrap();
+    writeln!(manifest, "# Not part of any surrounding workspace").unwrap();
+    writeln!(manifest, "[workspace]").unwrap();
+
+    let main = MAIN.replace("{name}", name);
+    return Crate { manifest, main, module: translate(rom, name) };
+}
+
+/// `src/main.rs` of a translated crate
+const MAIN: &str = r#"//! `{name}`, translated ahead of time by `chip8_core::aot`
+//! Generated code, do not edit
+//!
+//! Usage: cargo run -- [frames] [keys] [seed]
+//! Runs `frames` frames (600 by default) holding `keys` (hex digits, `-` for none),
+//! then prints the screen.
+
+mod rom;
+
+use chip8_core::constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8_core::Emu;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let frames: usize = args.get(1).map_or(600, |frames| frames.parse().expect("Invalid frame count"));
+    let keys = args.get(2).map_or("-", String::as_str);
+
+    let mut emu = Emu::new();
+    if let Some(seed) = args.get(3) {
+        emu.seed_rng(seed.parse().expect("Invalid seed"));
+    }
+    emu.load_rom(&rom::ROM).expect("The ROM was translated, so it fits");
+    for key in keys.chars().filter(|key| *key != '-') {
+        let key = key.to_digit(16).expect("Keys are hex digits");
+        emu.keypress(key as usize, true);
+    }
+
+    let mut backend = rom::Translated;
+    for _ in 0..frames {
+        if let Err(fault) = emu.run_frame_with(&mut backend) {
+            eprintln!("{}", fault);
+            std::process::exit(1);
+        }
+    }
+    let screen = emu.framebuffer();
+    for y in 0..SCREEN_HEIGHT {
+        println!("{}", (0..SCREEN_WIDTH).map(|x| if screen.pixel(x, y) { '#' } else { '.' }).collect::<String>());
+    }
+}
+"#;
+
+/// A function running `block`, returning how many instructions it executed or the `Fault` it stopped at
+/// Returns 0 without doing anything if the block's code was modified or it doesn't fit in the budget
+fn translate_block(rom: &Rom, block: &BasicBlock) -> String {
+    let start = block.start;
+    let end = start + 2 * block.instructions.len() as u16;
+    let mut out = String::new();
+
+    writeln!(out, "/// `0x{:03X}..0x{:03X}`", start, end).unwrap();
+    writeln!(out, "fn block_{:03x}(emu: &mut Emu, budget: usize) -> Result<usize, Fault> {{", start).unwrap();
+    writeln!(out, "    if budget < {} || {} {{", block.instructions.len(), modified(rom, start, end)).unwrap();
+    writeln!(out, "        return Ok(0);").unwrap();
+    writeln!(out, "    }}").unwrap();
+
+    let mut pc_set = false;
+    for (n, (addr, op)) in block.instructions.iter().enumerate() {
+        let (addr, op) = (*addr, *op);
+        let next = addr + 2;
+        let executed = n + 1;
+        match statement(op) {
+            Some(code) => {
+                writeln!(out, "    {} // {:03X}: {}", code, addr, op).unwrap();
+                pc_set = false;
+            },
+            None => {
+                writeln!(out, "    emu.try_execute(0x{:03X}, Opcode::{:?})?; // {:03X}: {}", addr, op, addr, op).unwrap();
+                pc_set = true;
+            },
+        }
+
+        if executed == block.instructions.len() { break; }
+        match op {
+            // Blocked waiting for a key or a vblank, the instruction will be retried
+            Opcode::WaitKey(_) | Opcode::DrawSprite(..) => {
+                writeln!(out, "    if emu.pc() != 0x{:03X} {{", next).unwrap();
+                writeln!(out, "        return Ok({});", executed).unwrap();
+                writeln!(out, "    }}").unwrap();
+            },
+            // The rest of the block may have just been overwritten
+            Opcode::BCD(_) | Opcode::LoadIntoRam(_) => {
+                writeln!(out, "    if {} {{", modified(rom, next, end)).unwrap();
+                writeln!(out, "        return Ok({});", executed).unwrap();
+                writeln!(out, "    }}").unwrap();
+            },
+            _ => {},
+        }
+    }
+
+    if !pc_set {
+        writeln!(out, "    emu.set_pc(0x{:03X});", end).unwrap();
+    }
+    writeln!(out, "    Ok({})", block.instructions.len()).unwrap();
+    writeln!(out, "}}").unwrap();
+    return out;
+}
+
+/// Expression checking whether RAM in `start..end` differs from the ROM
+fn modified(rom: &Rom, start: u16, end: u16) -> String {
+    let bytes: Vec<u8> = (start..end).map(|addr| rom.byte(addr).unwrap_or(0)).collect();
+    return format!("emu.memory(0x{:03X}..0x{:03X}) != [{}]", start, end, byte_list(&bytes));
+}
+
+fn byte_list(bytes: &[u8]) -> String {
+    return bytes.iter().map(|b| format!("0x{:02X}", b)).collect::<Vec<_>>().join(", ");
+}
+
+/// Rust statement for instructions that only touch the registers and don't depend on `Quirks`,
+/// `None` for everything else
+fn statement(op: Opcode) -> Option<String> {
+    use Opcode::*;
+    let code = match op {
+        SetToVal(x, nn) => format!("emu.registers_mut()[0x{:X}] = 0x{:02X};", x, nn),
+        AddVal(x, nn) => format!("{{ let v = emu.registers_mut(); v[0x{:X}] = v[0x{:X}].wrapping_add(0x{:02X}); }}", x, x, nn),
+        SetToReg(x, y) => format!("{{ let v = emu.registers_mut(); v[0x{:X}] = v[0x{:X}]; }}", x, y),
+        AddReg(x, y) => format!(
+            "{{ let v = emu.registers_mut(); let (val, carry) = v[0x{:X}].overflowing_add(v[0x{:X}]); v[0x{:X}] = val; v[0xF] = carry as u8; }}",
+            x, y, x,
+        ),
+        SubReg(x, y) => format!(
+            "{{ let v = emu.registers_mut(); let (val, borrow) = v[0x{:X}].overflowing_sub(v[0x{:X}]); v[0x{:X}] = val; v[0xF] = !borrow as u8; }}",
+            x, y, x,
+        ),
+        SubFromReg(x, y) => format!(
+            "{{ let v = emu.registers_mut(); let (val, borrow) = v[0x{:X}].overflowing_sub(v[0x{:X}]); v[0x{:X}] = val; v[0xF] = !borrow as u8; }}",
+            y, x, x,
+        ),
+        SetIndex(addr) => format!("emu.set_index(0x{:03X});", addr),
+        IncrementI(x) => format!("emu.set_index(emu.index().wrapping_add(emu.registers()[0x{:X}] as u16));", x),
+        _ => return None,
+    };
+    return Some(code);
+}
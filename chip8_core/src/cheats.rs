@@ -0,0 +1,177 @@
+use std::fmt;
+
+use crate::constants::{NUM_REGS, RAM_SIZE};
+use crate::Emu;
+
+/// Something a cheat can freeze or a search can find
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheatTarget {
+    /// Byte in RAM
+    Ram(u16),
+    /// `VX`
+    Register(usize),
+}
+impl CheatTarget {
+    /// `None` if the target is outside RAM or the registers, where writes are ignored
+    pub fn read(&self, emu: &Emu) -> Option<u8> {
+        return match *self {
+            CheatTarget::Ram(addr) => emu.memory(addr as usize..addr as usize + 1).first().copied(),
+            CheatTarget::Register(reg) => emu.registers().get(reg).copied(),
+        };
+    }
+
+    pub fn write(&self, emu: &mut Emu, val: u8) {
+        match *self {
+            CheatTarget::Ram(addr) => emu.poke(addr, val),
+            CheatTarget::Register(reg) => emu.set_register(reg, val),
+        }
+    }
+
+    /// Every searchable location: all of RAM, then `V0` -> `VF`
+    fn all() -> impl Iterator<Item = CheatTarget> {
+        return (0..RAM_SIZE as u16).map(CheatTarget::Ram)
+            .chain((0..NUM_REGS).map(CheatTarget::Register));
+    }
+}
+impl fmt::Display for CheatTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            CheatTarget::Ram(addr) => write!(f, "{:#05X}", addr),
+            CheatTarget::Register(reg) => write!(f, "V{:X}", reg),
+        };
+    }
+}
+
+/// A persistent cheat, forcing a value every frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cheat {
+    pub target: CheatTarget,
+    pub value: u8,
+    pub description: String,
+}
+impl Cheat {
+    pub fn new(target: CheatTarget, value: u8) -> Self {
+        return Self { target, value, description: String::new() };
+    }
+
+    /// Parse a cheat file
+    ///
+    /// One cheat per line, in the form `TARGET = VALUE`, optionally followed by
+    /// a `#` comment which becomes the description.
+    /// `TARGET` is either a register (`V0` -> `VF`) or a RAM address (`0x2F0`, `2F0`)
+    /// and `VALUE` is either hex (`0x05`) or decimal (`5`). Blank lines are ignored.
+    ///
+    /// ```text
+    /// # BLITZ
+    /// VE = 9  # Infinite lives
+    /// ```
+    pub fn parse_file(text: &str) -> Result<Vec<Cheat>, CheatParseError> {
+        let mut cheats = Vec::new();
+        for (idx, line) in text.lines().enumerate() {
+            let error = |reason: &str| CheatParseError { line: idx + 1, reason: reason.to_string() };
+
+            let (body, comment) = match line.split_once('#') {
+                Some((body, comment)) => (body.trim(), comment.trim()),
+                None => (line.trim(), ""),
+            };
+            if body.is_empty() { continue; }
+
+            let (target, value) = body.split_once('=').ok_or_else(|| error("expected `TARGET = VALUE`"))?;
+            let target = Self::parse_target(target.trim()).ok_or_else(|| error("invalid target"))?;
+            let value = Self::parse_value(value.trim()).ok_or_else(|| error("invalid value"))?;
+
+            cheats.push(Cheat { target, value, description: comment.to_string() });
+        }
+        return Ok(cheats);
+    }
+
+    pub(crate) fn parse_target(text: &str) -> Option<CheatTarget> {
+        if let Some(reg) = text.strip_prefix('V').or_else(|| text.strip_prefix('v')) {
+            let reg = usize::from_str_radix(reg, 16).ok()?;
+            if reg >= NUM_REGS || text.len() != 2 { return None; }
+            return Some(CheatTarget::Register(reg));
+        }
+        let hex = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")).unwrap_or(text);
+        let addr = u16::from_str_radix(hex, 16).ok()?;
+        if addr as usize >= RAM_SIZE { return None; }
+        return Some(CheatTarget::Ram(addr));
+    }
+
+    pub(crate) fn parse_value(text: &str) -> Option<u8> {
+        return match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            Some(hex) => u8::from_str_radix(hex, 16).ok(),
+            None => text.parse().ok(),
+        };
+    }
+}
+impl fmt::Display for Cheat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {:#04X}", self.target, self.value)?;
+        if !self.description.is_empty() { write!(f, "  # {}", self.description)?; }
+        return Ok(());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheatParseError {
+    pub line: usize,
+    pub reason: String,
+}
+impl fmt::Display for CheatParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "line {}: {}", self.line, self.reason);
+    }
+}
+impl std::error::Error for CheatParseError {}
+
+/// Comparison used to narrow down a search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchFilter {
+    /// Value is currently equal to this
+    Equal(u8),
+    /// Value differs from the last snapshot
+    Changed,
+    /// Value is the same as the last snapshot
+    Unchanged,
+    /// Value is greater than in the last snapshot
+    Increased,
+    /// Value is less than in the last snapshot
+    Decreased,
+}
+
+/// Iterative memory search over RAM and registers
+///
+/// Start a search, play until the value you're after changes, then narrow down
+/// the candidates with a `SearchFilter`. Each filter takes a new snapshot.
+#[derive(Debug, Clone)]
+pub struct CheatSearch {
+    candidates: Vec<(CheatTarget, u8)>,
+}
+impl CheatSearch {
+    /// Begin a search with every location as a candidate
+    pub fn new(emu: &Emu) -> Self {
+        let candidates = CheatTarget::all().map(|t| (t, t.read(emu).unwrap())).collect();
+        return Self { candidates };
+    }
+
+    /// Keep only the candidates matching `filter`, and snapshot their current values
+    pub fn filter(&mut self, emu: &Emu, filter: SearchFilter) {
+        self.candidates.retain_mut(|(target, last)| {
+            let now = target.read(emu).unwrap();
+            let keep = match filter {
+                SearchFilter::Equal(val) => now == val,
+                SearchFilter::Changed => now != *last,
+                SearchFilter::Unchanged => now == *last,
+                SearchFilter::Increased => now > *last,
+                SearchFilter::Decreased => now < *last,
+            };
+            *last = now;
+            return keep;
+        });
+    }
+
+    /// Remaining candidates with their values as of the last snapshot
+    pub fn candidates(&self) -> &[(CheatTarget, u8)] {
+        return &self.candidates;
+    }
+}
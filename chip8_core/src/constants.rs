@@ -25,4 +25,9 @@ pub const SCREEN_HEIGHT: usize = 32;
 // ============== //
 pub const FONT_START_ADDR: u16 = 0x0;
 pub const START_ADDR: u16 = 0x200;
+// Largest ROM that fits in RAM after `START_ADDR`
+pub const MAX_ROM_SIZE: usize = RAM_SIZE - START_ADDR as usize;
+
+// Timing
+pub const TICKS_PER_FRAME: usize = 10;
 
@@ -0,0 +1,180 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::ops::Range;
+
+use crate::analysis::Rom;
+use crate::constants::{RAM_SIZE, START_ADDR};
+use crate::Opcode;
+
+pub const EXECUTED: u8 = 0b001;
+pub const READ: u8 = 0b010;
+pub const WRITTEN: u8 = 0b100;
+
+/// ROM coverage tracker
+///
+/// Records, per RAM address, whether it was executed as code, read as data
+/// or written, along with the outcomes of every skip instruction.
+#[derive(Debug, Clone)]
+pub struct Coverage {
+    flags: Vec<u8>,
+    hits: Vec<u64>,
+    branches: HashMap<u16, BranchStats>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BranchStats {
+    /// Times the next instruction was skipped
+    pub taken: u64,
+    /// Times execution fell through to the next instruction
+    pub not_taken: u64,
+}
+
+impl Default for Coverage {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+impl Coverage {
+    pub fn new() -> Self {
+        return Self {
+            flags: vec![0; RAM_SIZE],
+            hits: vec![0; RAM_SIZE],
+            branches: HashMap::new(),
+        };
+    }
+
+    /// Record an instruction fetch at `addr`
+    pub fn execute(&mut self, addr: u16) {
+        let addr = addr as usize % RAM_SIZE;
+        self.flags[addr] |= EXECUTED;
+        self.flags[(addr + 1) % RAM_SIZE] |= EXECUTED;
+        self.hits[addr] += 1;
+    }
+
+    /// Record the outcome of a skip instruction at `addr`
+    pub fn branch(&mut self, addr: u16, skipped: bool) {
+        let stats = self.branches.entry(addr).or_default();
+        if skipped { stats.taken += 1; } else { stats.not_taken += 1; }
+    }
+
+    /// Record `len` bytes read as data from `addr`
+    pub fn read(&mut self, addr: usize, len: usize) {
+        self.mark(addr, len, READ);
+    }
+
+    /// Record `len` bytes written from `addr`
+    pub fn write(&mut self, addr: usize, len: usize) {
+        self.mark(addr, len, WRITTEN);
+    }
+
+    fn mark(&mut self, addr: usize, len: usize, flag: u8) {
+        for a in addr..addr + len {
+            self.flags[a % RAM_SIZE] |= flag;
+        }
+    }
+
+    /// Access flags (`EXECUTED`, `READ`, `WRITTEN`) for an address
+    pub fn flags(&self, addr: u16) -> u8 {
+        return self.flags[addr as usize % RAM_SIZE];
+    }
+
+    /// Number of times an instruction was fetched from `addr`
+    pub fn hits(&self, addr: u16) -> u64 {
+        return self.hits[addr as usize % RAM_SIZE];
+    }
+
+    pub fn branch_stats(&self, addr: u16) -> Option<&BranchStats> {
+        return self.branches.get(&addr);
+    }
+
+    /// Instruction addresses in `range`: everything statically reachable from
+    /// `START_ADDR` plus everything that was actually executed
+    pub fn instructions(&self, ram: &[u8], range: Range<usize>) -> BTreeSet<u16> {
+        let rom = Rom::with_base(&ram[range.clone()], range.start as u16);
+        let mut entries = vec![START_ADDR];
+        entries.extend(range.filter(|addr| self.hits[*addr] > 0).map(|addr| addr as u16));
+        return rom.reachable(&entries);
+    }
+
+    /// Annotated disassembly of `range`
+    /// Each line shows the address, access flags (`X`ecuted, `R`ead, `W`ritten),
+    /// hit count and the instruction or data byte. Unexecuted code is marked with `!`,
+    /// skips that only ever went one way are marked with `?`
+    pub fn listing(&self, ram: &[u8], range: Range<usize>) -> String {
+        let code = self.instructions(ram, range.clone());
+        let mut out = String::new();
+
+        let mut addr = range.start;
+        while addr < range.end {
+            let flags = self.flags[addr];
+            let access = format!("{}{}{}",
+                if flags & EXECUTED != 0 { 'X' } else { '-' },
+                if flags & READ != 0 { 'R' } else { '-' },
+                if flags & WRITTEN != 0 { 'W' } else { '-' },
+            );
+
+            let op = if code.contains(&(addr as u16)) && addr + 1 < range.end {
+                Opcode::try_new((ram[addr] as u16) << 8 | ram[addr + 1] as u16).ok()
+            } else { None };
+
+            match op {
+                Some(op) => {
+                    let hits = self.hits[addr];
+                    let mut marker = if hits == 0 { '!' } else { ' ' };
+                    let mut branch = String::new();
+                    if op.is_skip() {
+                        let stats = self.branches.get(&(addr as u16)).copied().unwrap_or_default();
+                        if hits > 0 && (stats.taken == 0 || stats.not_taken == 0) { marker = '?'; }
+                        branch = format!("  ; skipped {} / fell through {}", stats.taken, stats.not_taken);
+                    }
+                    writeln!(out, "{}{:03X}  {}  {:>8}  {:02X}{:02X}  {}{}",
+                        marker, addr, access, hits, ram[addr], ram[addr + 1], op, branch).unwrap();
+                    addr += 2;
+                },
+                None => {
+                    writeln!(out, " {:03X}  {}  {:>8}  {:02X}    .byte {:#04X}",
+                        addr, access, "", ram[addr], ram[addr]).unwrap();
+                    addr += 1;
+                },
+            }
+        }
+        return out;
+    }
+
+    /// lcov tracefile for `range`, using addresses as line numbers
+    /// and skip outcomes as branches
+    pub fn lcov(&self, ram: &[u8], range: Range<usize>, name: &str) -> String {
+        let code = self.instructions(ram, range.clone());
+        let mut out = String::new();
+        writeln!(out, "TN:").unwrap();
+        writeln!(out, "SF:{}", name).unwrap();
+
+        let (mut lines_hit, mut branches_found, mut branches_hit) = (0, 0, 0);
+        for addr in &code {
+            let hits = self.hits[*addr as usize];
+            writeln!(out, "DA:{},{}", addr, hits).unwrap();
+            if hits > 0 { lines_hit += 1; }
+
+            let a = *addr as usize;
+            let is_skip = a + 1 < range.end && Opcode::try_new((ram[a] as u16) << 8 | ram[a + 1] as u16)
+                .is_ok_and(|op| op.is_skip());
+            if !is_skip { continue; }
+
+            let stats = self.branches.get(addr).copied().unwrap_or_default();
+            for (branch, count) in [(0, stats.not_taken), (1, stats.taken)] {
+                // lcov uses `-` for branches whose block was never reached
+                let taken = if hits == 0 { "-".to_string() } else { count.to_string() };
+                writeln!(out, "BRDA:{},0,{},{}", addr, branch, taken).unwrap();
+                branches_found += 1;
+                if count > 0 { branches_hit += 1; }
+            }
+        }
+
+        writeln!(out, "BRF:{}", branches_found).unwrap();
+        writeln!(out, "BRH:{}", branches_hit).unwrap();
+        writeln!(out, "LF:{}", code.len()).unwrap();
+        writeln!(out, "LH:{}", lines_hit).unwrap();
+        writeln!(out, "end_of_record").unwrap();
+        return out;
+    }
+}
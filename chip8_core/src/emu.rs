@@ -1,19 +1,45 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+
 use crate::constants::*;
+#[cfg(feature = "std")]
+use crate::profiler::Profiler;
+#[cfg(feature = "std")]
+use crate::coverage::Coverage;
+#[cfg(feature = "std")]
+use crate::cheats::Cheat;
+use crate::quirks::Quirks;
+use crate::platform::{Beeper, DefaultBeeper, Random, Xorshift};
 mod cpu;
 mod stack;
 mod timers;
 
-mod opcode; pub use opcode::Opcode;
-mod resource_loader;
+mod opcode; pub use opcode::{Opcode, UnknownOpcode};
+mod fault; pub use fault::Fault;
+mod resource_loader; pub use resource_loader::RomTooLarge;
 mod audio;
-mod screen;
+mod screen; pub use screen::{Dirty, Framebuffer};
 mod keys;
+mod vblank;
+#[cfg(feature = "std")]
+mod profiling;
+#[cfg(feature = "std")]
+mod coverage;
+mod inspect;
+#[cfg(feature = "std")]
+mod cheats;
+mod predecode;
+mod quirks;
+mod snapshot; pub use snapshot::{Snapshot, SnapshotError};
+pub mod backend;
 
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct Emu {
     pc: u16, // Program counter
     ram: [u8; RAM_SIZE], // Main RAM
+    rom_len: usize, // Size of the loaded ROM
     v_reg: [u8; NUM_REGS], // Main registers
     i_reg: u16, // Used for indexing into RAM for reading/writing
     sp: u16, // Stack pointer
@@ -21,8 +47,26 @@ pub struct Emu {
     keys: [bool; NUM_KEYS], // Keys
     dt: u8, // Delay timer
     st: u8, // Sound timer
+    rng: Box<dyn Random>, // Random number generator for `Rand`
+    beeper: Box<dyn Beeper>, // Sound output
+
+    quirks: Quirks, // Interpreter behaviours to emulate
+    vblank_waiting: bool, // Blocked on `DrawSprite` until the next vblank
+    vblank: bool, // A vblank has happened since the last draw
+
+    screen: Framebuffer, // Screen data
+
+    predecode: bool, // Whether decoded instructions are cached
+    decoded: Vec<Option<Opcode>>, // Decoded instruction at each address, if cached
+    write_epoch: u64, // Number of RAM writes that may have modified code
+    written: Vec<u64>, // Epoch of the last write to each address
 
-    screen: [bool; SCREEN_WIDTH * SCREEN_HEIGHT], // Screen data
+    #[cfg(feature = "std")]
+    profiler: Option<Profiler>, // Execution profiler, when enabled
+    #[cfg(feature = "std")]
+    coverage: Option<Coverage>, // Coverage tracker, when enabled
+    #[cfg(feature = "std")]
+    cheats: Vec<Cheat>, // Values forced every frame
 }
 impl Default for Emu {
     fn default() -> Self {
@@ -34,7 +78,8 @@ impl Emu {
         let mut emu = Self {
             pc: START_ADDR,
             ram: [0; RAM_SIZE],
-            screen: [false; SCREEN_WIDTH * SCREEN_HEIGHT],
+            rom_len: 0,
+            screen: Framebuffer::new(),
             v_reg: [0; NUM_REGS],
             i_reg: 0,
             sp: 0,
@@ -42,6 +87,21 @@ impl Emu {
             keys: [false; NUM_KEYS],
             dt: 0,
             st: 0,
+            rng: Box::new(Xorshift::from_entropy()),
+            beeper: Box::new(DefaultBeeper),
+            quirks: Quirks::default(),
+            vblank_waiting: false,
+            vblank: false,
+            predecode: true,
+            decoded: vec![None; RAM_SIZE],
+            write_epoch: 0,
+            written: vec![0; RAM_SIZE],
+            #[cfg(feature = "std")]
+            profiler: None,
+            #[cfg(feature = "std")]
+            coverage: None,
+            #[cfg(feature = "std")]
+            cheats: Vec::new(),
         };
 
         emu.load_font();
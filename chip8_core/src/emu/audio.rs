@@ -1,6 +1,15 @@
+use alloc::boxed::Box;
+
+use crate::platform::Beeper;
+
 impl super::Emu {
+    /// Set where beeps go, replacing the default (see `DefaultBeeper`)
+    pub fn set_beeper(&mut self, beeper: Box<dyn Beeper>) {
+        self.beeper = beeper;
+    }
+
     /// Beep - CHIP-8's only audio
-    pub(super) fn beep() {
-        println!("BEEP!");
+    pub(super) fn beep(&mut self) {
+        self.beeper.beep();
     }
 }
@@ -0,0 +1,182 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use super::{Emu, Fault, Opcode};
+
+/// Strategy for executing CHIP-8 code on an `Emu`
+pub trait Backend {
+    /// Run up to `budget` instructions, returning how many were executed
+    /// Stops early if the emulator blocks waiting for a vblank. On a `Fault`, `pc` is left at
+    /// the faulting instruction and the instructions before it have run.
+    fn run(&mut self, emu: &mut Emu, budget: usize) -> Result<usize, Fault>;
+}
+
+/// Fetch, decode and execute one instruction at a time (`Emu::try_tick`)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Interpreter;
+impl Backend for Interpreter {
+    fn run(&mut self, emu: &mut Emu, budget: usize) -> Result<usize, Fault> {
+        for executed in 0..budget {
+            if emu.vblank_waiting { return Ok(executed); }
+            emu.try_tick()?;
+        }
+        return Ok(budget);
+    }
+}
+
+/// Longest run of instructions translated into a single block
+const MAX_BLOCK_LEN: usize = 32;
+
+type Op = Box<dyn Fn(&mut Emu) -> Result<(), Fault>>;
+
+/// An `Op` that can't fault
+fn infallible(op: impl Fn(&mut Emu) + 'static) -> Op {
+    return Box::new(move |emu| {
+        op(emu);
+        return Ok(());
+    });
+}
+
+/// A straight-line run of instructions, translated into closures
+struct Block {
+    start: u16,
+    end: u16,
+    epoch: u64,
+    ops: Vec<Op>,
+}
+
+/// Basic-block translating backend
+///
+/// Straight-line code is translated once into a chain of closures with their operands
+/// pre-bound, then a whole block runs per dispatch. Blocks end at anything that changes
+/// control flow, may block (`WaitKey`, `DrawSprite`) or writes to RAM, so a block never
+/// runs past code it just modified. Blocks are retranslated when their bytes are written.
+///
+/// Falls back to the interpreter while the profiler or coverage tracking is enabled.
+pub struct BlockJit {
+    blocks: Vec<Option<Block>>, // Translated block starting at each address
+}
+impl Default for BlockJit {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+impl BlockJit {
+    pub fn new() -> Self {
+        return Self { blocks: (0..super::RAM_SIZE).map(|_| None).collect() };
+    }
+
+    /// Number of blocks currently translated
+    pub fn cached_blocks(&self) -> usize {
+        return self.blocks.iter().filter(|b| b.is_some()).count();
+    }
+
+    fn translate(emu: &Emu, start: u16) -> Block {
+        let mut ops: Vec<Op> = Vec::new();
+        let mut addr = start;
+        while ops.len() < MAX_BLOCK_LEN && (addr as usize) + 1 < super::RAM_SIZE {
+            let raw = (emu.ram[addr as usize] as u16) << 8 | emu.ram[addr as usize + 1] as u16;
+            let op = match Opcode::try_new(raw) {
+                Ok(op) => op,
+                // Only fault once execution actually gets there, like the interpreter
+                Err(_) if ops.is_empty() => {
+                    ops.push(Box::new(move |_| Err(Fault::UnknownOpcode { addr: start, opcode: raw })));
+                    addr += 2;
+                    break;
+                },
+                Err(_) => break,
+            };
+            ops.push(Self::compile(op));
+            addr += 2;
+            if Self::ends_block(op) { break; }
+        }
+        return Block { start, end: addr, epoch: emu.write_epoch, ops };
+    }
+
+    fn ends_block(op: Opcode) -> bool {
+        use Opcode::*;
+        return op.is_skip() || matches!(op,
+            Jump(_) | Call(_) | Return | JumpV0Distance(_) |
+            WaitKey(_) | DrawSprite(..) |
+            BCD(_) | LoadIntoRam(_)
+        );
+    }
+
+    /// Closure executing `op`, with its operands bound
+    /// Simple register operations are specialised, everything else goes through the interpreter
+    fn compile(op: Opcode) -> Op {
+        use Opcode::*;
+        return match op {
+            SetToVal(x, nn) => infallible(move |emu| emu.v_reg[x] = nn),
+            AddVal(x, nn) => infallible(move |emu| emu.v_reg[x] = emu.v_reg[x].wrapping_add(nn)),
+            SetToReg(x, y) => infallible(move |emu| emu.v_reg[x] = emu.v_reg[y]),
+            BitwiseOr(x, y) => infallible(move |emu| {
+                emu.v_reg[x] |= emu.v_reg[y];
+                if emu.quirks.vf_reset { emu.v_reg[0xF] = 0; }
+            }),
+            BitwiseAnd(x, y) => infallible(move |emu| {
+                emu.v_reg[x] &= emu.v_reg[y];
+                if emu.quirks.vf_reset { emu.v_reg[0xF] = 0; }
+            }),
+            BitwiseXor(x, y) => infallible(move |emu| {
+                emu.v_reg[x] ^= emu.v_reg[y];
+                if emu.quirks.vf_reset { emu.v_reg[0xF] = 0; }
+            }),
+            AddReg(x, y) => infallible(move |emu| {
+                let (val, carry) = emu.v_reg[x].overflowing_add(emu.v_reg[y]);
+                emu.v_reg[x] = val;
+                emu.v_reg[0xF] = carry as u8;
+            }),
+            SubReg(x, y) => infallible(move |emu| {
+                let (val, borrow) = emu.v_reg[x].overflowing_sub(emu.v_reg[y]);
+                emu.v_reg[x] = val;
+                emu.v_reg[0xF] = !borrow as u8;
+            }),
+            SubFromReg(x, y) => infallible(move |emu| {
+                let (val, borrow) = emu.v_reg[y].overflowing_sub(emu.v_reg[x]);
+                emu.v_reg[x] = val;
+                emu.v_reg[0xF] = !borrow as u8;
+            }),
+            SetIndex(addr) => infallible(move |emu| emu.i_reg = addr),
+            IncrementI(x) => infallible(move |emu| emu.i_reg = emu.i_reg.wrapping_add(emu.v_reg[x] as u16)),
+            // `run` has already moved `pc` past the instruction
+            op => Box::new(move |emu| emu.try_execute(emu.pc - 2, op)),
+        };
+    }
+}
+impl Backend for BlockJit {
+    fn run(&mut self, emu: &mut Emu, budget: usize) -> Result<usize, Fault> {
+        #[cfg(feature = "std")]
+        if emu.profiler.is_some() || emu.coverage.is_some() {
+            return Interpreter.run(emu, budget);
+        }
+
+        let mut executed = 0;
+        while executed < budget && !emu.vblank_waiting {
+            let pc = emu.pc;
+            if pc as usize + 1 >= super::RAM_SIZE { return Err(Fault::PcOutOfRange(pc)); }
+            let slot = &mut self.blocks[pc as usize];
+            let stale = match slot {
+                Some(block) => emu.written_since(block.start, block.end, block.epoch),
+                None => true,
+            };
+            if stale {
+                *slot = Some(Self::translate(emu, pc));
+            }
+            let block = slot.as_mut().unwrap();
+            block.epoch = emu.write_epoch;
+
+            let mut addr = pc;
+            for op in block.ops.iter().take(budget - executed) {
+                emu.pc = addr + 2;
+                if let Err(fault) = op(emu) {
+                    emu.pc = addr;
+                    return Err(fault);
+                }
+                addr += 2;
+                executed += 1;
+            }
+        }
+        return Ok(executed);
+    }
+}
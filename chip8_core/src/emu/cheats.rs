@@ -0,0 +1,25 @@
+use crate::cheats::Cheat;
+
+impl super::Emu {
+    /// Add a cheat, applied at the start of every frame
+    pub fn add_cheat(&mut self, cheat: Cheat) {
+        self.cheats.push(cheat);
+    }
+
+    pub fn clear_cheats(&mut self) {
+        self.cheats.clear();
+    }
+
+    pub fn cheats(&self) -> &[Cheat] {
+        return &self.cheats;
+    }
+
+    /// Force every cheat's value
+    /// Called by `run_frame`; frontends with their own frame loop should call it once per frame
+    pub fn apply_cheats(&mut self) {
+        for i in 0..self.cheats.len() {
+            let Cheat { target, value, .. } = self.cheats[i];
+            target.write(self, value);
+        }
+    }
+}
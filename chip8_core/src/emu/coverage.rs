@@ -0,0 +1,42 @@
+use crate::coverage::Coverage;
+
+impl super::Emu {
+    /// Start tracking coverage, discarding any previous data
+    pub fn enable_coverage(&mut self) {
+        self.coverage = Some(Coverage::new());
+    }
+
+    /// Stop tracking coverage and return the collected data
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        return self.coverage.take();
+    }
+
+    pub fn coverage(&self) -> Option<&Coverage> {
+        return self.coverage.as_ref();
+    }
+
+    /// Annotated disassembly of the loaded ROM
+    pub fn coverage_listing(&self) -> Option<String> {
+        let coverage = self.coverage.as_ref()?;
+        return Some(coverage.listing(&self.ram, self.rom_range()));
+    }
+
+    /// lcov tracefile for the loaded ROM
+    pub fn coverage_lcov(&self, name: &str) -> Option<String> {
+        let coverage = self.coverage.as_ref()?;
+        return Some(coverage.lcov(&self.ram, self.rom_range(), name));
+    }
+
+    fn rom_range(&self) -> std::ops::Range<usize> {
+        let start = super::START_ADDR as usize;
+        return start..start + self.rom_len;
+    }
+
+    pub(super) fn cover_read(&mut self, addr: usize, len: usize) {
+        if let Some(coverage) = &mut self.coverage { coverage.read(addr, len); }
+    }
+
+    pub(super) fn cover_write(&mut self, addr: usize, len: usize) {
+        if let Some(coverage) = &mut self.coverage { coverage.write(addr, len); }
+    }
+}
@@ -1,34 +1,84 @@
-use crate::emu::SCREEN_WIDTH;
-
-use super::Opcode;
+use super::{Fault, Opcode};
 
 impl super::Emu {
+    /// Fetch, decode and execute one instruction
+    /// Panics on a `Fault`, see `try_tick`
     pub fn tick(&mut self) {
-        let op = Self::decode_opcode(self.fetch_opcode());
-        self.execute_opcode(op);
+        if let Err(fault) = self.try_tick() { panic!("{}", fault); }
     }
 
-    fn fetch_opcode(&mut self) -> u16 {
-        let higher_byte = self.ram[self.pc as usize] as u16;
-        let lower_byte = self.ram[(self.pc + 1) as usize] as u16;
-        // Combine the two u8's into one u16 opcode
-        let opcode = (higher_byte << 8) | lower_byte;
+    /// Fetch, decode and execute one instruction, or return why it can't run
+    /// Nothing changes on a fault, so `pc` still points at the faulting instruction.
+    pub fn try_tick(&mut self) -> Result<(), Fault> {
+        if self.vblank_waiting { return Ok(()); }
+        let pc = self.pc;
+        if pc as usize + 1 >= super::RAM_SIZE { return Err(Fault::PcOutOfRange(pc)); }
+        let op = match self.decoded[pc as usize] {
+            Some(op) => op,
+            None => {
+                let opcode = self.fetch_opcode();
+                let op = Opcode::try_new(opcode).map_err(|_| Fault::UnknownOpcode { addr: pc, opcode })?;
+                if self.predecode { self.decoded[pc as usize] = Some(op); }
+                op
+            },
+        };
+        self.check(op)?;
         self.skip();
-        return opcode;
+        self.execute_opcode(op);
+        // A blocked `DrawSprite` or `WaitKey` hasn't run yet, it's counted when it's retried and goes ahead
+        #[cfg(feature = "std")]
+        let retrying = matches!(op, Opcode::DrawSprite(..) | Opcode::WaitKey(_)) && self.pc == pc;
+        #[cfg(feature = "std")]
+        if let Some(profiler) = &mut self.profiler {
+            if !retrying { profiler.record(pc, op); }
+        }
+        #[cfg(feature = "std")]
+        if let Some(coverage) = &mut self.coverage {
+            if !retrying { coverage.execute(pc); }
+        }
+        #[cfg(feature = "std")]
+        if op.is_skip() {
+            if let Some(coverage) = &mut self.coverage {
+                coverage.branch(pc, self.pc == pc + 4);
+            }
+        }
+        return Ok(());
     }
 
+    /// Execute `op` as if it had just been fetched from `addr`
+    /// Used by translated code, which has already decoded its instructions.
+    /// Panics on a `Fault`, see `try_execute`
+    pub fn execute(&mut self, addr: u16, op: Opcode) {
+        if let Err(fault) = self.try_execute(addr, op) { panic!("{}", fault); }
+    }
 
-    fn decode_opcode(opcode: u16) -> Opcode {
-        return Opcode::new(opcode);
+    /// Execute `op` as if it had just been fetched from `addr`, or return why it can't run
+    /// On a fault nothing else changes, and `pc` is left at `addr`.
+    pub fn try_execute(&mut self, addr: u16, op: Opcode) -> Result<(), Fault> {
+        if let Err(fault) = self.check(op) {
+            self.pc = addr;
+            return Err(fault);
+        }
+        self.pc = addr + 2;
+        self.execute_opcode(op);
+        return Ok(());
     }
 
-    fn execute_opcode(&mut self, opcode: Opcode) {
+    fn fetch_opcode(&self) -> u16 {
+        let higher_byte = self.ram[self.pc as usize] as u16;
+        let lower_byte = self.ram[(self.pc + 1) as usize] as u16;
+        // Combine the two u8's into one u16 opcode
+        return (higher_byte << 8) | lower_byte;
+    }
+
+    /// Run an instruction that has passed `check`
+    pub(super) fn execute_opcode(&mut self, opcode: Opcode) {
         use super::Opcode::*;
         match opcode {
             Nop => return,
 
             ClearScreen => {
-                self.screen = [false; super::SCREEN_WIDTH * super::SCREEN_HEIGHT];
+                self.screen.clear();
             },
 
             Return => {
@@ -77,14 +127,17 @@ impl super::Emu {
 
             BitwiseOr(reg1, reg2) => {
                 self.v_reg[reg1] |= self.v_reg[reg2];
+                if self.quirks.vf_reset { self.v_reg[0xF] = 0; }
             },
 
             BitwiseAnd(reg1, reg2) => {
                 self.v_reg[reg1] &= self.v_reg[reg2];
+                if self.quirks.vf_reset { self.v_reg[0xF] = 0; }
             },
 
             BitwiseXor(reg1, reg2) => {
                 self.v_reg[reg1] ^= self.v_reg[reg2];
+                if self.quirks.vf_reset { self.v_reg[0xF] = 0; }
             },
 
             AddReg(reg1, reg2) => {
@@ -99,10 +152,10 @@ impl super::Emu {
                 self.v_reg[0xF] = if borrow { 0 } else { 1 };
             },
 
-            ShiftRight(reg) => {
-                let lsb = self.v_reg[reg] & 1; // Dropped bit
-                self.v_reg[reg] >>= 1;
-                self.v_reg[0xF] = lsb;
+            ShiftRight(reg, src) => {
+                let val = self.v_reg[if self.quirks.shift_vy { src } else { reg }];
+                self.v_reg[reg] = val >> 1;
+                self.v_reg[0xF] = val & 1; // Dropped bit
             },
 
             SubFromReg(reg1, reg2) => {
@@ -111,10 +164,10 @@ impl super::Emu {
                 self.v_reg[0xF] = if borrow { 0 } else { 1 };
             },
 
-            ShiftLeft(reg) => {
-                let msb = (self.v_reg[reg] >> 7) & 1; // Overflowed bit
-                self.v_reg[reg] <<= 1;
-                self.v_reg[0xF] = msb;
+            ShiftLeft(reg, src) => {
+                let val = self.v_reg[if self.quirks.shift_vy { src } else { reg }];
+                self.v_reg[reg] = val << 1;
+                self.v_reg[0xF] = (val >> 7) & 1; // Overflowed bit
             },
 
 
@@ -129,45 +182,36 @@ impl super::Emu {
             },
 
             JumpV0Distance(distance) => {
-                self.pc = (self.v_reg[0] as u16) + distance;
+                // `BXNN` uses `VX` with the jump quirk
+                let reg = if self.quirks.jump_vx { (distance >> 8) as usize } else { 0 };
+                self.pc = (self.v_reg[reg] as u16) + distance;
             },
 
             Rand(reg, num) => {
-                let rng: u8 = rand::random();
+                let rng = self.rng.next_u8();
                 self.v_reg[reg] = rng & num;
             },
 
             DrawSprite(x_coord, y_coord, height) => {
-                let mut flipped = false;
-
-                for row in 0..height {
-                    let addr = self.i_reg + row as u16; // Sprite data address for the row
-                    let pixels = self.ram[addr as usize]; // Sprite data for the row
-
-                    for col in 0..8 { // Sprites always have 8 colums
-                        // If the sprite's pixel is a 1, then the screen pixel will flip
-                        // If the screen pixel flips from 0 -> 1, VF is not set
-                        // If the screen pixel flips from 1 -> 0, VF is set
-
-                        let mask = 0b1000_0000 >> col; // mask for the current bit
-                        let sprite_pixel = pixels & mask;
-                        if sprite_pixel != 0 { // If the pixel is on
-                            // Position of pixel on the screen. Overflows wrap using the modulo.
-                            let x = (x_coord + col) as usize % super::SCREEN_WIDTH;
-                            let y = (y_coord + row as u16) as usize % super::SCREEN_HEIGHT;
-                            // Memory index for the screen pixel
-                            let index = SCREEN_WIDTH * y + x;
-                            let screen_pixel = self.screen[index];
-                            if screen_pixel {
-                                self.screen[index] = false;
-                                flipped = true;
-                            } else {
-                                self.screen[index] = true;
-                            }
-                        }
-                    }
+                if !self.wait_for_vblank() {
+                    // Redo the instruction after the vblank
+                    self.pc -= 2;
+                    return;
+                }
 
-                    self.v_reg[0xF] = if flipped { 1 } else { 0 };
+                #[cfg(feature = "std")]
+                self.cover_read(self.i_reg as usize, height as usize);
+                // Sprites running past the end of RAM wrap around to the start
+                let mut sprite = [0; 15];
+                for (row, byte) in sprite.iter_mut().enumerate().take(height as usize) {
+                    *byte = self.ram[(self.i_reg as usize + row) % super::RAM_SIZE];
+                }
+                let sprite = &sprite[..height as usize];
+                // Any lit pixel that gets turned off sets VF
+                let (x, y) = (self.v_reg[x_coord as usize], self.v_reg[y_coord as usize]);
+                let collision = self.screen.draw_sprite(x as usize, y as usize, sprite, self.quirks.clip);
+                if height > 0 {
+                    self.v_reg[0xF] = collision as u8;
                 }
             },
 
@@ -230,6 +274,9 @@ impl super::Emu {
                 let hundreds = (num - ones - (tens*10)) / 100;
 
                 let base_addr = self.i_reg as usize;
+                #[cfg(feature = "std")]
+                self.cover_write(base_addr, 3);
+                self.invalidate(base_addr, 3);
                 self.ram[base_addr] = hundreds;
                 self.ram[base_addr + 1] = tens;
                 self.ram[base_addr + 2] = ones;
@@ -237,16 +284,23 @@ impl super::Emu {
 
             LoadIntoRam(reg) => {
                 let i = self.i_reg as usize;
+                #[cfg(feature = "std")]
+                self.cover_write(i, reg + 1);
+                self.invalidate(i, reg + 1);
                 for idx in 0..=reg {
                     self.ram[i + idx] = self.v_reg[idx];
                 }
+                if self.quirks.memory_increment { self.i_reg = self.i_reg.wrapping_add(reg as u16 + 1); }
             },
 
             LoadFromRam(reg) => {
                 let i = self.i_reg as usize;
+                #[cfg(feature = "std")]
+                self.cover_read(i, reg + 1);
                 for idx in 0..=reg {
                     self.v_reg[idx] = self.ram[i + idx];
                 }
+                if self.quirks.memory_increment { self.i_reg = self.i_reg.wrapping_add(reg as u16 + 1); }
             },
         }
     }
@@ -0,0 +1,55 @@
+use core::fmt;
+
+/// Why `Emu::try_tick` couldn't execute the next instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// The instruction at `addr` isn't part of the instruction set
+    UnknownOpcode { addr: u16, opcode: u16 },
+    /// `PC` points past the end of RAM
+    PcOutOfRange(u16),
+    /// `Call` with every return address in use
+    StackOverflow,
+    /// `Return` with nothing on the stack
+    StackUnderflow,
+    /// `BCD` or a register load or store would access `len` bytes from `addr`, past the end of RAM
+    MemoryOutOfRange { addr: u16, len: usize },
+    /// A key instruction with a register holding more than `0xF`
+    InvalidKey(u8),
+}
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            Fault::UnknownOpcode { addr, opcode } => write!(f, "unknown opcode {:04X} at {:03X}", opcode, addr),
+            Fault::PcOutOfRange(pc) => write!(f, "program counter {:04X} is past the end of RAM", pc),
+            Fault::StackOverflow => write!(f, "stack pointer overflow"),
+            Fault::StackUnderflow => write!(f, "stack pointer underflow"),
+            Fault::MemoryOutOfRange { addr, len } => write!(f, "{} bytes from {:04X} run past the end of RAM", len, addr),
+            Fault::InvalidKey(key) => write!(f, "key {:#04X} doesn't exist", key),
+        };
+    }
+}
+#[cfg(feature = "std")]
+impl std::error::Error for Fault {}
+
+impl super::Emu {
+    /// Whether `op` can run in the current state, checked before anything is changed
+    pub(super) fn check(&self, op: super::Opcode) -> Result<(), Fault> {
+        use super::Opcode::*;
+        let memory = |len: usize| match self.i_reg as usize + len > super::RAM_SIZE {
+            true => Err(Fault::MemoryOutOfRange { addr: self.i_reg, len }),
+            false => Ok(()),
+        };
+        let key = |reg: usize| match self.v_reg[reg] as usize >= super::NUM_KEYS {
+            true => Err(Fault::InvalidKey(self.v_reg[reg])),
+            false => Ok(()),
+        };
+        return match op {
+            Call(_) if self.sp as usize >= super::STACK_SIZE - 1 => Err(Fault::StackOverflow),
+            Return if self.sp == 0 => Err(Fault::StackUnderflow),
+            BCD(_) => memory(3),
+            LoadIntoRam(reg) | LoadFromRam(reg) => memory(reg + 1),
+            SkipIfKeyPressed(reg) | SkipIfKeyNotPressed(reg) => key(reg),
+            _ => Ok(()),
+        };
+    }
+}
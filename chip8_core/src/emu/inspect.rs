@@ -0,0 +1,125 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::fmt::Write;
+use core::ops::Range;
+
+use crate::platform::{Random, Xorshift};
+use crate::resources::font::FONTSET_SIZE;
+
+impl super::Emu {
+    // ============= //
+    // == READING == //
+    // ============= //
+
+    /// `V0` -> `VF`
+    pub fn registers(&self) -> &[u8] {
+        return &self.v_reg;
+    }
+
+    pub fn pc(&self) -> u16 {
+        return self.pc;
+    }
+
+    /// The `I` register
+    pub fn index(&self) -> u16 {
+        return self.i_reg;
+    }
+
+    /// Return addresses currently on the stack, oldest first
+    pub fn stack(&self) -> &[u16] {
+        return &self.stack[..self.sp as usize];
+    }
+
+    /// `(delay, sound)`
+    pub fn timers(&self) -> (u8, u8) {
+        return (self.dt, self.st);
+    }
+
+    /// A slice of RAM
+    /// The range is clamped to the size of RAM
+    pub fn memory(&self, range: Range<usize>) -> &[u8] {
+        let end = range.end.min(super::RAM_SIZE);
+        let start = range.start.min(end);
+        return &self.ram[start..end];
+    }
+
+    // ============== //
+    // == MUTATION == //
+    // ============== //
+
+    /// Write a byte into RAM
+    pub fn poke(&mut self, addr: u16, val: u8) {
+        let addr = addr as usize;
+        if addr >= super::RAM_SIZE { return; }
+        self.ram[addr] = val;
+        self.invalidate(addr, 1);
+    }
+
+    pub fn set_register(&mut self, reg: usize, val: u8) {
+        if reg >= super::NUM_REGS { return; }
+        self.v_reg[reg] = val;
+    }
+
+    /// `V0` -> `VF`, for code that operates on the registers directly
+    pub fn registers_mut(&mut self) -> &mut [u8] {
+        return &mut self.v_reg;
+    }
+
+    pub fn set_index(&mut self, addr: u16) {
+        self.i_reg = addr;
+    }
+
+    pub fn set_pc(&mut self, pc: u16) {
+        self.pc = pc;
+    }
+
+    /// Reseed the random number generator, making `Rand` deterministic
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = Box::new(Xorshift::new(seed));
+    }
+
+    /// Replace the random number generator used by `Rand`
+    pub fn set_random(&mut self, rng: Box<dyn Random>) {
+        self.rng = rng;
+    }
+
+    // ============== //
+    // == HEX DUMP == //
+    // ============== //
+
+    /// Hex + ASCII dump of RAM, 16 bytes per line
+    /// Lines are annotated with the region they belong to (font, program)
+    pub fn hex_dump(&self, range: Range<usize>) -> String {
+        let end = range.end.min(super::RAM_SIZE);
+        let font = super::FONT_START_ADDR as usize..super::FONT_START_ADDR as usize + FONTSET_SIZE;
+        let program = super::START_ADDR as usize..super::START_ADDR as usize + self.rom_len;
+
+        let mut out = String::new();
+        let mut line_start = range.start - range.start % 16;
+        while line_start < end {
+            write!(out, "{:03X}:", line_start).unwrap();
+
+            let mut ascii = String::new();
+            for addr in line_start..line_start + 16 {
+                if addr < range.start || addr >= end {
+                    out.push_str("   ");
+                    ascii.push(' ');
+                    continue;
+                }
+                let byte = self.ram[addr];
+                write!(out, " {:02X}", byte).unwrap();
+                ascii.push(if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' });
+            }
+            write!(out, "  |{}|", ascii).unwrap();
+
+            let line = line_start..line_start + 16;
+            let overlaps = |region: &Range<usize>| line.start < region.end && region.start < line.end;
+            if overlaps(&font) { out.push_str("  ; font"); }
+            if overlaps(&program) { out.push_str("  ; program"); }
+            out.push('\n');
+
+            line_start += 16;
+        }
+        return out;
+    }
+}
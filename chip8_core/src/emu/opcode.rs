@@ -1,3 +1,17 @@
+use core::fmt;
+
+/// A 16 bit value that isn't a CHIP-8 instruction, from `Opcode::try_new`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownOpcode(pub u16);
+impl fmt::Display for UnknownOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "unknown opcode {:04X}", self.0);
+    }
+}
+#[cfg(feature = "std")]
+impl std::error::Error for UnknownOpcode {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Opcode {
     /// Do nothing
     /// Opcode: `0000`
@@ -95,12 +109,12 @@ pub enum Opcode {
     /// Opcode: `8XY5`
     SubReg(usize, usize),
 
-    /// Perform a single right shift on `VX`
+    /// Perform a single right shift on `VX` (or `VY` with the shift quirk, see `Quirks`)
     /// Dropped bit is stored in `VF`
     ///
-    /// Arguments: `(VX)`
+    /// Arguments: `(VX, VY)`
     /// Opcode: `8XY6`
-    ShiftRight(usize),
+    ShiftRight(usize, usize),
 
     /// Subtract `VX` from `VY` and store it in `VX` (`VX = VY - VX`)
     ///
@@ -108,12 +122,12 @@ pub enum Opcode {
     /// Opcode: `8XY7`
     SubFromReg(usize, usize),
 
-    /// Perform a single left shift on `VX`
+    /// Perform a single left shift on `VX` (or `VY` with the shift quirk, see `Quirks`)
     /// Overflowed bit is stored in `VF`
     ///
-    /// Arguments: `(VX)`
+    /// Arguments: `(VX, VY)`
     /// Opcode: `8XYE`
-    ShiftLeft(usize),
+    ShiftLeft(usize, usize),
 
     /// Skip an instruction if `VX != VY`
     ///
@@ -226,7 +240,17 @@ pub enum Opcode {
     LoadFromRam(usize),
 }
 impl Opcode {
+    /// Decode an opcode
+    /// Panics if it isn't part of the instruction set, see `try_new`
     pub fn new(opcode: u16) -> Self {
+        return match Opcode::try_new(opcode) {
+            Ok(op) => op,
+            Err(error) => panic!("{}", error),
+        };
+    }
+
+    /// Decode an opcode, failing if it isn't part of the instruction set
+    pub fn try_new(opcode: u16) -> Result<Self, UnknownOpcode> {
         use Opcode::*;
         const A: u16 = 0xA;
         const B: u16 = 0xB;
@@ -238,7 +262,7 @@ impl Opcode {
         let nn = (opcode & 0x00FF) as u8;
         let nnn = opcode & 0x0FFF;
 
-        return match parts {
+        return Ok(match parts {
             (0,0,0,0) => Nop,
             (0,0,E,0) => ClearScreen,
             (0,0,E,E) => Return,
@@ -255,9 +279,9 @@ impl Opcode {
             (8,x,y,3) => BitwiseXor(x as usize, y as usize),
             (8,x,y,4) => AddReg(x as usize, y as usize),
             (8,x,y,5) => SubReg(x as usize, y as usize),
-            (8,x,_,6) => ShiftRight(x as usize),
+            (8,x,y,6) => ShiftRight(x as usize, y as usize),
             (8,x,y,7) => SubFromReg(x as usize, y as usize),
-            (8,x,_,E) => ShiftLeft(x as usize),
+            (8,x,y,E) => ShiftLeft(x as usize, y as usize),
             (9,x,y,0) => SkipIfRegNE(x as usize, y as usize),
             (A,_,_,_) => SetIndex(nnn),
             (B,_,_,_) => JumpV0Distance(nnn),
@@ -275,10 +299,106 @@ impl Opcode {
             (F,x,5,5) => LoadIntoRam(x as usize),
             (F,x,6,5) => LoadFromRam(x as usize),
 
-            _ => unimplemented!("Nonexistent opcode {}!", opcode)
+            _ => return Err(UnknownOpcode(opcode)),
+        });
+    }
+
+    /// Encode back into the 16 bit instruction, the inverse of `try_new`
+    pub fn encode(&self) -> u16 {
+        use Opcode::*;
+        let xy = |x: usize, y: usize| (x as u16) << 8 | (y as u16) << 4;
+        let xnn = |x: usize, nn: u8| (x as u16) << 8 | nn as u16;
+        let x = |x: usize| (x as u16) << 8;
+        return match *self {
+            Nop => 0x0000,
+            ClearScreen => 0x00E0,
+            Return => 0x00EE,
+            Jump(nnn) => 0x1000 | nnn,
+            Call(nnn) => 0x2000 | nnn,
+            SkipIfValEQ(vx, nn) => 0x3000 | xnn(vx, nn),
+            SkipIfValNE(vx, nn) => 0x4000 | xnn(vx, nn),
+            SkipIfRegEQ(vx, vy) => 0x5000 | xy(vx, vy),
+            SetToVal(vx, nn) => 0x6000 | xnn(vx, nn),
+            AddVal(vx, nn) => 0x7000 | xnn(vx, nn),
+            SetToReg(vx, vy) => 0x8000 | xy(vx, vy),
+            BitwiseOr(vx, vy) => 0x8001 | xy(vx, vy),
+            BitwiseAnd(vx, vy) => 0x8002 | xy(vx, vy),
+            BitwiseXor(vx, vy) => 0x8003 | xy(vx, vy),
+            AddReg(vx, vy) => 0x8004 | xy(vx, vy),
+            SubReg(vx, vy) => 0x8005 | xy(vx, vy),
+            ShiftRight(vx, vy) => 0x8006 | xy(vx, vy),
+            SubFromReg(vx, vy) => 0x8007 | xy(vx, vy),
+            ShiftLeft(vx, vy) => 0x800E | xy(vx, vy),
+            SkipIfRegNE(vx, vy) => 0x9000 | xy(vx, vy),
+            SetIndex(nnn) => 0xA000 | nnn,
+            JumpV0Distance(nnn) => 0xB000 | nnn,
+            Rand(vx, nn) => 0xC000 | xnn(vx, nn),
+            DrawSprite(vx, vy, n) => 0xD000 | vx << 8 | vy << 4 | n as u16,
+            SkipIfKeyPressed(vx) => 0xE09E | x(vx),
+            SkipIfKeyNotPressed(vx) => 0xE0A1 | x(vx),
+            GetDelayTimer(vx) => 0xF007 | x(vx),
+            WaitKey(vx) => 0xF00A | x(vx),
+            SetDelayTimer(vx) => 0xF015 | x(vx),
+            SetSoundTimer(vx) => 0xF018 | x(vx),
+            IncrementI(vx) => 0xF01E | x(vx),
+            LoadFontChar(vx) => 0xF029 | x(vx),
+            BCD(vx) => 0xF033 | x(vx),
+            LoadIntoRam(vx) => 0xF055 | x(vx),
+            LoadFromRam(vx) => 0xF065 | x(vx),
+        };
+    }
+
+    /// Name of the opcode's variant, for reports and statistics
+    pub fn name(&self) -> &'static str {
+        use Opcode::*;
+        return match self {
+            Nop => "Nop",
+            ClearScreen => "ClearScreen",
+            Return => "Return",
+            Jump(_) => "Jump",
+            Call(_) => "Call",
+            SkipIfValEQ(..) => "SkipIfValEQ",
+            SkipIfValNE(..) => "SkipIfValNE",
+            SkipIfRegEQ(..) => "SkipIfRegEQ",
+            SetToVal(..) => "SetToVal",
+            AddVal(..) => "AddVal",
+            SetToReg(..) => "SetToReg",
+            BitwiseOr(..) => "BitwiseOr",
+            BitwiseAnd(..) => "BitwiseAnd",
+            BitwiseXor(..) => "BitwiseXor",
+            AddReg(..) => "AddReg",
+            SubReg(..) => "SubReg",
+            ShiftRight(..) => "ShiftRight",
+            SubFromReg(..) => "SubFromReg",
+            ShiftLeft(..) => "ShiftLeft",
+            SkipIfRegNE(..) => "SkipIfRegNE",
+            SetIndex(_) => "SetIndex",
+            JumpV0Distance(_) => "JumpV0Distance",
+            Rand(..) => "Rand",
+            DrawSprite(..) => "DrawSprite",
+            SkipIfKeyPressed(_) => "SkipIfKeyPressed",
+            SkipIfKeyNotPressed(_) => "SkipIfKeyNotPressed",
+            GetDelayTimer(_) => "GetDelayTimer",
+            WaitKey(_) => "WaitKey",
+            SetDelayTimer(_) => "SetDelayTimer",
+            SetSoundTimer(_) => "SetSoundTimer",
+            IncrementI(_) => "IncrementI",
+            LoadFontChar(_) => "LoadFontChar",
+            BCD(_) => "BCD",
+            LoadIntoRam(_) => "LoadIntoRam",
+            LoadFromRam(_) => "LoadFromRam",
         };
     }
 
+    /// Whether the opcode conditionally skips the next instruction
+    pub fn is_skip(&self) -> bool {
+        use Opcode::*;
+        return matches!(self,
+            SkipIfValEQ(..) | SkipIfValNE(..) | SkipIfRegEQ(..) | SkipIfRegNE(..) |
+            SkipIfKeyPressed(_) | SkipIfKeyNotPressed(_)
+        );
+    }
+
     fn split(opcode: u16) -> (u16,u16,u16,u16) {
         return (
             (opcode & 0xF000) >> 12,
@@ -288,3 +408,49 @@ impl Opcode {
         );
     }
 }
+
+/// Disassembly, using the common Cowgod-style mnemonics
+impl core::fmt::Display for Opcode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        use Opcode::*;
+        return match *self {
+            Nop => write!(f, "NOP"),
+            ClearScreen => write!(f, "CLS"),
+            Return => write!(f, "RET"),
+            Jump(addr) => write!(f, "JP {:#05X}", addr),
+            Call(addr) => write!(f, "CALL {:#05X}", addr),
+            SkipIfValEQ(x, nn) => write!(f, "SE V{:X}, {:#04X}", x, nn),
+            SkipIfValNE(x, nn) => write!(f, "SNE V{:X}, {:#04X}", x, nn),
+            SkipIfRegEQ(x, y) => write!(f, "SE V{:X}, V{:X}", x, y),
+            SetToVal(x, nn) => write!(f, "LD V{:X}, {:#04X}", x, nn),
+            AddVal(x, nn) => write!(f, "ADD V{:X}, {:#04X}", x, nn),
+            SetToReg(x, y) => write!(f, "LD V{:X}, V{:X}", x, y),
+            BitwiseOr(x, y) => write!(f, "OR V{:X}, V{:X}", x, y),
+            BitwiseAnd(x, y) => write!(f, "AND V{:X}, V{:X}", x, y),
+            BitwiseXor(x, y) => write!(f, "XOR V{:X}, V{:X}", x, y),
+            AddReg(x, y) => write!(f, "ADD V{:X}, V{:X}", x, y),
+            SubReg(x, y) => write!(f, "SUB V{:X}, V{:X}", x, y),
+            ShiftRight(x, y) if x == y => write!(f, "SHR V{:X}", x),
+            ShiftRight(x, y) => write!(f, "SHR V{:X}, V{:X}", x, y),
+            SubFromReg(x, y) => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            ShiftLeft(x, y) if x == y => write!(f, "SHL V{:X}", x),
+            ShiftLeft(x, y) => write!(f, "SHL V{:X}, V{:X}", x, y),
+            SkipIfRegNE(x, y) => write!(f, "SNE V{:X}, V{:X}", x, y),
+            SetIndex(addr) => write!(f, "LD I, {:#05X}", addr),
+            JumpV0Distance(addr) => write!(f, "JP V0, {:#05X}", addr),
+            Rand(x, nn) => write!(f, "RND V{:X}, {:#04X}", x, nn),
+            DrawSprite(x, y, n) => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            SkipIfKeyPressed(x) => write!(f, "SKP V{:X}", x),
+            SkipIfKeyNotPressed(x) => write!(f, "SKNP V{:X}", x),
+            GetDelayTimer(x) => write!(f, "LD V{:X}, DT", x),
+            WaitKey(x) => write!(f, "LD V{:X}, K", x),
+            SetDelayTimer(x) => write!(f, "LD DT, V{:X}", x),
+            SetSoundTimer(x) => write!(f, "LD ST, V{:X}", x),
+            IncrementI(x) => write!(f, "ADD I, V{:X}", x),
+            LoadFontChar(x) => write!(f, "LD F, V{:X}", x),
+            BCD(x) => write!(f, "LD B, V{:X}", x),
+            LoadIntoRam(x) => write!(f, "LD [I], V{:X}", x),
+            LoadFromRam(x) => write!(f, "LD V{:X}, [I]", x),
+        };
+    }
+}
@@ -0,0 +1,26 @@
+impl super::Emu {
+    /// Enable or disable the predecoded instruction cache (enabled by default)
+    pub fn set_predecode(&mut self, enabled: bool) {
+        self.predecode = enabled;
+        self.decoded.fill(None);
+    }
+
+    /// Whether anything in `start..end` was written after `epoch`
+    pub(super) fn written_since(&self, start: u16, end: u16, epoch: u64) -> bool {
+        if self.write_epoch == epoch { return false; }
+        let end = (end as usize).min(super::RAM_SIZE);
+        return self.written[start as usize..end].iter().any(|e| *e > epoch);
+    }
+
+    /// Forget decoded instructions overlapping `len` bytes written at `addr`
+    /// The instruction starting one byte earlier overlaps the first byte too
+    pub(super) fn invalidate(&mut self, addr: usize, len: usize) {
+        let end = (addr + len).min(super::RAM_SIZE);
+        let start = addr.saturating_sub(1);
+        if start < end { self.decoded[start..end].fill(None); }
+
+        // Let block backends know their code may be stale
+        self.write_epoch += 1;
+        if addr < end { self.written[addr..end].fill(self.write_epoch); }
+    }
+}
@@ -0,0 +1,17 @@
+use crate::profiler::Profiler;
+
+impl super::Emu {
+    /// Start profiling execution, discarding any previous profile
+    pub fn enable_profiler(&mut self) {
+        self.profiler = Some(Profiler::new());
+    }
+
+    /// Stop profiling and return the collected profile
+    pub fn take_profiler(&mut self) -> Option<Profiler> {
+        return self.profiler.take();
+    }
+
+    pub fn profiler(&self) -> Option<&Profiler> {
+        return self.profiler.as_ref();
+    }
+}
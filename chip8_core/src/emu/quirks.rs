@@ -0,0 +1,13 @@
+use super::Quirks;
+
+impl super::Emu {
+    pub fn quirks(&self) -> Quirks {
+        return self.quirks;
+    }
+
+    /// Choose which interpreter's behaviour to emulate (see `Quirks::PRESETS`)
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+        if !quirks.display_wait { self.vblank_waiting = false; }
+    }
+}
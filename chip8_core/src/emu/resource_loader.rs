@@ -1,12 +1,34 @@
+use core::fmt;
+
+/// A ROM that doesn't fit in memory, from `Emu::load_rom`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RomTooLarge {
+    /// Length of the ROM in bytes
+    pub len: usize,
+}
+impl fmt::Display for RomTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "ROM is {} bytes, the most that fits is {}", self.len, super::MAX_ROM_SIZE);
+    }
+}
+#[cfg(feature = "std")]
+impl std::error::Error for RomTooLarge {}
+
 impl super::Emu {
     pub(super) fn load_font(&mut self) {
         use crate::resources::font::*;
         self.ram[..FONTSET_SIZE].copy_from_slice(&FONTSET);
     }
 
-    pub fn load_rom(&mut self, data: &[u8]) {
+    /// Copy a ROM into memory at `START_ADDR`
+    /// Fails without changing anything if it's longer than `MAX_ROM_SIZE`
+    pub fn load_rom(&mut self, data: &[u8]) -> Result<(), RomTooLarge> {
+        if data.len() > super::MAX_ROM_SIZE { return Err(RomTooLarge { len: data.len() }); }
         let start = super::START_ADDR as usize;
         let end = start + data.len();
         self.ram[start..end].copy_from_slice(data);
+        self.rom_len = data.len();
+        self.invalidate(start, data.len());
+        return Ok(());
     }
 }
@@ -1,5 +1,177 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use super::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// One row of pixels, column 0 in the most significant bit
+/// Wide enough for the 128 pixel high resolution screen, bits past the screen's width are unused.
+pub type Row = u128;
+
+const _: () = assert!(SCREEN_WIDTH <= Row::BITS as usize);
+
+/// The bits of a `Row` that are on the screen
+const VISIBLE: Row = !0 << (Row::BITS as usize - SCREEN_WIDTH);
+
+// Dirty rows are tracked as one bit each
+const _: () = assert!(SCREEN_HEIGHT <= u64::BITS as usize);
+
+/// Rows of the screen that changed, see `Emu::take_dirty`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Dirty {
+    rows: u64, // Bit `y` is set if row `y` changed
+}
+impl Dirty {
+    pub fn is_empty(&self) -> bool {
+        return self.rows == 0;
+    }
+
+    pub fn contains(&self, y: usize) -> bool {
+        return y < SCREEN_HEIGHT && self.rows >> y & 1 != 0;
+    }
+
+    /// Indices of the changed rows, top to bottom
+    pub fn rows(&self) -> impl Iterator<Item = usize> + '_ {
+        return (0..SCREEN_HEIGHT).filter(|y| self.contains(*y));
+    }
+
+    /// Runs of consecutive changed rows, to redraw as rectangles spanning the screen's width
+    pub fn spans(&self) -> Vec<Range<usize>> {
+        let mut spans: Vec<Range<usize>> = Vec::new();
+        for y in self.rows() {
+            match spans.last_mut() {
+                Some(span) if span.end == y => span.end += 1,
+                _ => spans.push(y..y + 1),
+            }
+        }
+        return spans;
+    }
+}
+
+/// Bit-packed monochrome framebuffer, one `Row` per line
+#[derive(Debug, Clone, Copy)]
+pub struct Framebuffer {
+    rows: [Row; SCREEN_HEIGHT],
+    dirty: Dirty, // Rows changed since the last `take_dirty`
+    version: u64, // Incremented on every change
+}
+impl PartialEq for Framebuffer {
+    /// Compares the pixels only
+    fn eq(&self, other: &Self) -> bool {
+        return self.rows == other.rows;
+    }
+}
+impl Eq for Framebuffer {}
+impl Default for Framebuffer {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+impl Framebuffer {
+    pub fn new() -> Self {
+        return Self {
+            rows: [0; SCREEN_HEIGHT],
+            dirty: Dirty::default(),
+            version: 0,
+        };
+    }
+
+    pub fn clear(&mut self) {
+        for y in 0..SCREEN_HEIGHT {
+            if self.rows[y] != 0 { self.set_row(y, 0); }
+        }
+    }
+
+    /// Rows changed since the last call, resetting the tracking
+    pub fn take_dirty(&mut self) -> Dirty {
+        return core::mem::take(&mut self.dirty);
+    }
+
+    /// Counter incremented whenever the contents change
+    /// Frontends can skip redrawing while it stays the same
+    pub fn version(&self) -> u64 {
+        return self.version;
+    }
+
+    /// A framebuffer showing `rows`, for reading save states
+    /// `None` if a row has pixels past the screen's width.
+    pub(super) fn from_rows(rows: [Row; SCREEN_HEIGHT]) -> Option<Self> {
+        if rows.iter().any(|row| row & !VISIBLE != 0) { return None; }
+        let mut screen = Self::new();
+        for (y, row) in rows.into_iter().enumerate() {
+            screen.set_row(y, row);
+        }
+        return Some(Self { dirty: Dirty::default(), version: 0, ..screen });
+    }
+
+    /// Replace the pixels with `other`'s, as a change to every row
+    pub(super) fn restore(&mut self, other: &Framebuffer) {
+        self.rows = other.rows;
+        self.dirty.rows = !0 >> (u64::BITS as usize - SCREEN_HEIGHT);
+        self.version += 1;
+    }
+
+    fn set_row(&mut self, y: usize, row: Row) {
+        self.rows[y] = row;
+        self.dirty.rows |= 1 << y;
+        self.version += 1;
+    }
+
+    /// Packed rows, top to bottom
+    pub fn rows(&self) -> &[Row] {
+        return &self.rows;
+    }
+
+    /// Whether the pixel at `(x, y)` is lit
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        if x >= SCREEN_WIDTH || y >= SCREEN_HEIGHT { return false; }
+        return self.rows[y] >> (Row::BITS as usize - 1 - x) & 1 != 0;
+    }
+
+    /// XOR an 8 pixel wide sprite onto the screen with its top left corner at `(x, y)`
+    /// The position wraps around the screen. Parts of the sprite past the edges wrap around too,
+    /// or are cut off if `clip` is set.
+    /// Returns whether any lit pixel was turned off (a collision)
+    pub fn draw_sprite(&mut self, x: usize, y: usize, sprite: &[u8], clip: bool) -> bool {
+        let (x, y) = (x % SCREEN_WIDTH, y % SCREEN_HEIGHT);
+        let mut collision = false;
+        for (i, byte) in sprite.iter().enumerate() {
+            if clip && y + i >= SCREEN_HEIGHT { break; }
+            let bits = (*byte as Row) << (Row::BITS - 8);
+            // The columns past the right edge, moved round to the left edge
+            let wrapped = if clip || x + 8 <= SCREEN_WIDTH { 0 } else { bits << (SCREEN_WIDTH - x) };
+            let bits = (bits >> x | wrapped) & VISIBLE;
+            let y = (y + i) % SCREEN_HEIGHT;
+            collision |= self.rows[y] & bits != 0;
+            if bits != 0 { self.set_row(y, self.rows[y] ^ bits); }
+        }
+        return collision;
+    }
+
+    /// Whether each pixel is lit, row by row, unpacked as they're read
+    pub fn pixels(&self) -> impl Iterator<Item = bool> + '_ {
+        return (0..SCREEN_HEIGHT).flat_map(move |y| (0..SCREEN_WIDTH).map(move |x| self.pixel(x, y)));
+    }
+}
+
 impl super::Emu {
-    pub fn get_display(&self) -> &[bool] {
+    /// The screen as one `bool` per pixel, row by row
+    pub fn get_display(&self) -> impl Iterator<Item = bool> + '_ {
+        return self.screen.pixels();
+    }
+
+    /// The bit-packed framebuffer
+    pub fn framebuffer(&self) -> &Framebuffer {
         return &self.screen;
     }
+
+    /// Rows of the screen changed since the last call
+    /// Frontends can redraw just these rather than the whole screen
+    pub fn take_dirty(&mut self) -> Dirty {
+        return self.screen.take_dirty();
+    }
+
+    /// Counter incremented whenever the screen changes, so unchanged frames can be skipped
+    pub fn display_version(&self) -> u64 {
+        return self.screen.version();
+    }
 }
@@ -0,0 +1,256 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::screen::Row;
+use super::{Framebuffer, Quirks, NUM_KEYS, NUM_REGS, RAM_SIZE, SCREEN_HEIGHT, STACK_SIZE, START_ADDR};
+use crate::platform::{Random, Xorshift};
+
+/// Complete machine state, to go back to with `Emu::restore`
+/// Debugging tools (profiler, coverage), cheats and the beeper aren't part of it
+///
+/// Snapshots compare equal when everything but the random number generator matches,
+/// since generators can't be compared
+///
+/// `to_bytes` and `from_bytes` convert to and from a fixed size save state format.
+#[derive(Debug)]
+pub struct Snapshot {
+    pc: u16,
+    ram: Box<[u8; RAM_SIZE]>,
+    rom_len: usize,
+    v_reg: [u8; NUM_REGS],
+    i_reg: u16,
+    sp: u16,
+    stack: [u16; STACK_SIZE],
+    keys: [bool; NUM_KEYS],
+    dt: u8,
+    st: u8,
+    rng: Option<Box<dyn Random>>, // Left as it is on restore if `None`, see `from_bytes`
+    quirks: Quirks,
+    vblank_waiting: bool,
+    vblank: bool,
+    screen: Framebuffer,
+}
+impl Clone for Snapshot {
+    fn clone(&self) -> Self {
+        return Self { ram: self.ram.clone(), rng: self.rng.as_ref().map(|rng| rng.duplicate()), ..*self };
+    }
+}
+impl PartialEq for Snapshot {
+    fn eq(&self, other: &Self) -> bool {
+        return self.pc == other.pc
+            && self.ram == other.ram
+            && self.rom_len == other.rom_len
+            && self.v_reg == other.v_reg
+            && self.i_reg == other.i_reg
+            && self.sp == other.sp
+            && self.stack == other.stack
+            && self.keys == other.keys
+            && self.dt == other.dt
+            && self.st == other.st
+            && self.quirks == other.quirks
+            && self.vblank_waiting == other.vblank_waiting
+            && self.vblank == other.vblank
+            && self.screen == other.screen;
+    }
+}
+impl Eq for Snapshot {}
+
+// ================= //
+// == SAVE STATES == //
+// ================= //
+
+/// Start of every save state
+const MAGIC: [u8; 4] = *b"C8SS";
+/// Bumped whenever the layout changes
+const VERSION: u8 = 2;
+/// Size of a saved row of pixels, a whole `Row`
+const ROW_BYTES: usize = core::mem::size_of::<Row>();
+
+/// Why `Snapshot::from_bytes` failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// Not `Snapshot::SIZE` bytes long
+    WrongSize,
+    /// Doesn't start with the magic number, so isn't a save state
+    NotSnapshot,
+    /// Saved in another version of the format
+    Version(u8),
+    /// A value is out of range
+    Corrupt,
+}
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            SnapshotError::WrongSize => write!(f, "save state should be {} bytes", Snapshot::SIZE),
+            SnapshotError::NotSnapshot => write!(f, "not a save state"),
+            SnapshotError::Version(version) => write!(f, "save state version {} isn't supported", version),
+            SnapshotError::Corrupt => write!(f, "save state is corrupt"),
+        };
+    }
+}
+#[cfg(feature = "std")]
+impl std::error::Error for SnapshotError {}
+
+impl Snapshot {
+    /// Size of a save state from `to_bytes`
+    pub const SIZE: usize = 4 + 1 // Magic, version
+        + 2 * 4 // PC, I, SP, ROM length
+        + NUM_REGS + 2 * STACK_SIZE
+        + 2 + 2 // Keys, timers
+        + 1 + 1 // Quirks, flags
+        + 8 // Random number generator
+        + ROW_BYTES * SCREEN_HEIGHT + RAM_SIZE;
+
+    /// The snapshot as a save state, `SIZE` bytes in little endian
+    ///
+    /// The random number generator is only saved if it's a `Xorshift` (see `Random::save_state`).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::SIZE);
+        out.extend_from_slice(&MAGIC);
+        out.push(VERSION);
+        for val in [self.pc, self.i_reg, self.sp, self.rom_len as u16] {
+            out.extend_from_slice(&val.to_le_bytes());
+        }
+        out.extend_from_slice(&self.v_reg);
+        for addr in self.stack {
+            out.extend_from_slice(&addr.to_le_bytes());
+        }
+        let keys = self.keys.iter().enumerate().fold(0u16, |mask, (key, held)| mask | (*held as u16) << key);
+        out.extend_from_slice(&keys.to_le_bytes());
+        out.extend_from_slice(&[self.dt, self.st]);
+
+        let Quirks { vf_reset, memory_increment, shift_vy, jump_vx, display_wait, clip } = self.quirks;
+        let quirks = [vf_reset, memory_increment, shift_vy, jump_vx, display_wait, clip];
+        out.push(quirks.iter().enumerate().fold(0, |bits, (bit, on)| bits | (*on as u8) << bit));
+        let rng = self.rng.as_ref().and_then(|rng| rng.save_state());
+        out.push(self.vblank_waiting as u8 | (self.vblank as u8) << 1 | (rng.is_some() as u8) << 2);
+        out.extend_from_slice(&rng.unwrap_or(0).to_le_bytes());
+
+        for row in self.screen.rows() {
+            out.extend_from_slice(&row.to_le_bytes());
+        }
+        out.extend_from_slice(&*self.ram);
+        return out;
+    }
+
+    /// Read a save state from `to_bytes`
+    /// If the generator wasn't saved, restoring leaves the emulator's generator as it is.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        if bytes.len() != Self::SIZE { return Err(SnapshotError::WrongSize); }
+        if bytes[..4] != MAGIC { return Err(SnapshotError::NotSnapshot); }
+        if bytes[4] != VERSION { return Err(SnapshotError::Version(bytes[4])); }
+        let mut reader = Reader(&bytes[5..]);
+
+        let (pc, i_reg, sp, rom_len) = (reader.u16(), reader.u16(), reader.u16(), reader.u16() as usize);
+        let v_reg = reader.take(NUM_REGS).try_into().unwrap();
+        let stack = core::array::from_fn(|_| reader.u16());
+        let key_mask = reader.u16();
+        let keys = core::array::from_fn(|key| key_mask >> key & 1 != 0);
+        let [dt, st] = reader.take(2).try_into().unwrap();
+
+        let [quirk_bits, flags] = reader.take(2).try_into().unwrap();
+        let quirk = |bit: u8| quirk_bits >> bit & 1 != 0;
+        let quirks = Quirks {
+            vf_reset: quirk(0),
+            memory_increment: quirk(1),
+            shift_vy: quirk(2),
+            jump_vx: quirk(3),
+            display_wait: quirk(4),
+            clip: quirk(5),
+        };
+        let rng_state = reader.u64();
+        let rng: Option<Box<dyn Random>> = match flags & 0b100 != 0 {
+            true => Some(Box::new(Xorshift::from_state(rng_state).ok_or(SnapshotError::Corrupt)?)),
+            false => None,
+        };
+
+        let rows = core::array::from_fn(|_| Row::from_le_bytes(reader.take(ROW_BYTES).try_into().unwrap()));
+        let screen = Framebuffer::from_rows(rows).ok_or(SnapshotError::Corrupt)?;
+        let ram: Box<[u8; RAM_SIZE]> = Box::new(reader.take(RAM_SIZE).try_into().unwrap());
+
+        let in_range = (pc as usize) < RAM_SIZE
+            && sp as usize <= STACK_SIZE
+            && rom_len <= RAM_SIZE - START_ADDR as usize;
+        if !in_range { return Err(SnapshotError::Corrupt); }
+        return Ok(Self {
+            pc,
+            ram,
+            rom_len,
+            v_reg,
+            i_reg,
+            sp,
+            stack,
+            keys,
+            dt,
+            st,
+            rng,
+            quirks,
+            vblank_waiting: flags & 0b001 != 0,
+            vblank: flags & 0b010 != 0,
+            screen,
+        });
+    }
+}
+
+/// Reads a save state front to back, the size has already been checked
+struct Reader<'a>(&'a [u8]);
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> &'a [u8] {
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        return taken;
+    }
+
+    fn u16(&mut self) -> u16 {
+        return u16::from_le_bytes(self.take(2).try_into().unwrap());
+    }
+
+    fn u64(&mut self) -> u64 {
+        return u64::from_le_bytes(self.take(8).try_into().unwrap());
+    }
+}
+
+impl super::Emu {
+    /// Capture the current state
+    pub fn snapshot(&self) -> Snapshot {
+        return Snapshot {
+            pc: self.pc,
+            ram: Box::new(self.ram),
+            rom_len: self.rom_len,
+            v_reg: self.v_reg,
+            i_reg: self.i_reg,
+            sp: self.sp,
+            stack: self.stack,
+            keys: self.keys,
+            dt: self.dt,
+            st: self.st,
+            rng: Some(self.rng.duplicate()),
+            quirks: self.quirks,
+            vblank_waiting: self.vblank_waiting,
+            vblank: self.vblank,
+            screen: self.screen,
+        };
+    }
+
+    /// Go back to a previously captured state
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.pc = snapshot.pc;
+        self.ram = *snapshot.ram;
+        self.rom_len = snapshot.rom_len;
+        self.v_reg = snapshot.v_reg;
+        self.i_reg = snapshot.i_reg;
+        self.sp = snapshot.sp;
+        self.stack = snapshot.stack;
+        self.keys = snapshot.keys;
+        self.dt = snapshot.dt;
+        self.st = snapshot.st;
+        if let Some(rng) = &snapshot.rng { self.rng = rng.duplicate(); }
+        self.quirks = snapshot.quirks;
+        self.vblank_waiting = snapshot.vblank_waiting;
+        self.vblank = snapshot.vblank;
+        self.screen.restore(&snapshot.screen);
+        // All of RAM may have changed
+        self.invalidate(0, RAM_SIZE);
+    }
+}
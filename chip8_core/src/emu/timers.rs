@@ -3,7 +3,7 @@ impl super::Emu {
         if self.dt > 0 { self.dt -= 1; }
         if self.st > 0 {
             if self.st == 1 {
-                Self::beep();
+                self.beep();
             }
             self.st -= 1;
         }
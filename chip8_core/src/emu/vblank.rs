@@ -0,0 +1,51 @@
+use super::Fault;
+
+impl super::Emu {
+    /// Enable or disable the display wait quirk
+    /// When enabled, `DrawSprite` blocks until the next vblank,
+    /// limiting draws to one per frame like the original interpreter
+    pub fn set_display_wait(&mut self, enabled: bool) {
+        self.set_quirks(super::Quirks { display_wait: enabled, ..self.quirks });
+    }
+
+    /// Whether the emulator is blocked waiting for a vblank
+    /// Frontends can stop calling `tick` until `vblank` is signalled
+    pub fn is_waiting_vblank(&self) -> bool {
+        return self.vblank_waiting;
+    }
+
+    /// Signal a vertical blank (frame boundary)
+    pub fn vblank(&mut self) {
+        self.vblank = true;
+        self.vblank_waiting = false;
+    }
+
+    /// Run a single 60Hz frame
+    /// Applies cheats, ticks the CPU up to `TICKS_PER_FRAME` times (stopping early if blocked on vblank),
+    /// then ticks the timers and signals the vblank
+    pub fn run_frame(&mut self) -> Result<(), Fault> {
+        return self.run_frame_with(&mut super::backend::Interpreter);
+    }
+
+    /// Run a single 60Hz frame, executing instructions with `backend`
+    /// Stops at a `Fault` without ticking the timers, leaving `pc` at the faulting instruction
+    pub fn run_frame_with(&mut self, backend: &mut dyn super::backend::Backend) -> Result<(), Fault> {
+        #[cfg(feature = "std")]
+        self.apply_cheats();
+        backend.run(self, super::TICKS_PER_FRAME)?;
+        self.tick_timers();
+        self.vblank();
+        return Ok(());
+    }
+
+    /// Returns `true` if a `DrawSprite` may go ahead, otherwise blocks until the next vblank
+    pub(super) fn wait_for_vblank(&mut self) -> bool {
+        if !self.quirks.display_wait { return true; }
+        if self.vblank {
+            self.vblank = false;
+            return true;
+        }
+        self.vblank_waiting = true;
+        return false;
+    }
+}
@@ -0,0 +1,225 @@
+//! Gym-style environments for reinforcement learning
+//!
+//! An `Env` plays one ROM as an episodic task, its `GameSpec` mapping actions to keys and
+//! reading rewards and the end of the episode out of the game's registers or RAM.
+//! `VecEnv` steps many environments at once, spread across threads.
+
+use std::{fmt, thread};
+
+use crate::constants::NUM_KEYS;
+use crate::{Emu, Fault, Framebuffer, Silent, Snapshot};
+
+pub mod spec;
+pub use spec::{Comparison, Condition, GameSpec, Reward, SpecParseError, Value, BUILTIN};
+
+/// The result of `Env::step`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Step {
+    /// The screen after the step
+    pub observation: Framebuffer,
+    pub reward: f32,
+    /// The episode is over, either ended by the game or truncated
+    pub done: bool,
+    pub info: Info,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Info {
+    /// Frames since the episode started
+    pub frame: usize,
+    /// The episode hit `GameSpec::max_frames` rather than ending
+    pub truncated: bool,
+}
+
+/// A ROM being played as an episodic task
+#[derive(Debug)]
+pub struct Env {
+    emu: Emu,
+    spec: GameSpec,
+    initial: Snapshot, // State with the ROM freshly loaded
+    values: Vec<i64>, // Each reward's value as of the last frame
+    frame: usize, // Frames into the episode
+    seed: u64, // Seed of the current episode
+    done: bool,
+}
+impl Env {
+    /// An environment ready to play, reset with seed 0
+    ///
+    /// Panics if `rom` doesn't fit in memory.
+    pub fn new(rom: &[u8], spec: GameSpec) -> Result<Self, Fault> {
+        let mut emu = Emu::new();
+        emu.set_beeper(Box::new(Silent));
+        emu.set_quirks(spec.quirks);
+        if let Err(error) = emu.load_rom(rom) { panic!("{}", error); }
+        let initial = emu.snapshot();
+        let mut env = Self { emu, spec, initial, values: Vec::new(), frame: 0, seed: 0, done: false };
+        env.reset(0)?;
+        return Ok(env);
+    }
+
+    /// Start a new episode, with the random number generator seeded with `seed`
+    /// Returns the first observation, or the `Fault` if the ROM faults during the start frames
+    pub fn reset(&mut self, seed: u64) -> Result<Framebuffer, Fault> {
+        self.emu.restore(&self.initial);
+        self.emu.seed_rng(seed);
+        self.done = false;
+        for _ in 0..self.spec.start_frames {
+            self.emu.run_frame()?;
+        }
+        self.values = self.spec.rewards.iter().map(|reward| reward.value.read(&self.emu)).collect();
+        self.frame = 0;
+        self.seed = seed;
+        return Ok(*self.emu.framebuffer());
+    }
+
+    /// Hold the keys for `action` for `frame_skip` frames, or until the episode ends
+    /// A `Fault` stops the step part way through, `reset` to play on.
+    ///
+    /// Panics if the episode is already over or `action` is out of range.
+    pub fn step(&mut self, action: usize) -> Result<Step, Fault> {
+        assert!(!self.done, "the episode is over, call `reset`");
+        let keys = &self.spec.actions[action];
+        for key in 0..NUM_KEYS {
+            self.emu.keypress(key, keys.contains(&key));
+        }
+
+        let mut reward = 0.0;
+        let mut truncated = false;
+        for _ in 0..self.spec.frame_skip {
+            self.emu.run_frame()?;
+            self.frame += 1;
+            reward += self.frame_reward();
+            if self.spec.done.iter().any(|condition| condition.holds(&self.emu)) {
+                self.done = true;
+                break;
+            }
+            if self.spec.max_frames.is_some_and(|max| self.frame >= max) {
+                self.done = true;
+                truncated = true;
+                break;
+            }
+        }
+        let info = Info { frame: self.frame, truncated };
+        return Ok(Step { observation: *self.emu.framebuffer(), reward, done: self.done, info });
+    }
+
+    /// Reward for the changes in value since the last frame
+    fn frame_reward(&mut self) -> f32 {
+        let mut total = 0.0;
+        for (reward, last) in self.spec.rewards.iter().zip(&mut self.values) {
+            let value = reward.value.read(&self.emu);
+            total += (value - *last) as f32 * reward.scale;
+            *last = value;
+        }
+        return total;
+    }
+
+    pub fn num_actions(&self) -> usize {
+        return self.spec.actions.len();
+    }
+
+    pub fn spec(&self) -> &GameSpec {
+        return &self.spec;
+    }
+
+    pub fn emu(&self) -> &Emu {
+        return &self.emu;
+    }
+
+    pub fn is_done(&self) -> bool {
+        return self.done;
+    }
+}
+
+/// A `Fault` in one of a `VecEnv`'s environments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnvFault {
+    /// Index of the environment that faulted
+    pub env: usize,
+    pub fault: Fault,
+}
+impl fmt::Display for EnvFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "environment {}: {}", self.env, self.fault);
+    }
+}
+impl std::error::Error for EnvFault {}
+
+/// Many copies of an environment, stepped together
+///
+/// Finished episodes are reset automatically, so every `step` returns a live observation
+/// for each environment. Environment `i` plays episodes seeded `seed + i`, `seed + i + n`,
+/// `seed + i + 2n`... (wrapping around) so results don't depend on the number of threads.
+#[derive(Debug)]
+pub struct VecEnv {
+    envs: Vec<Env>,
+    threads: usize,
+}
+impl VecEnv {
+    /// `count` environments, reset with `seed`, stepped on as many threads as there are cores
+    ///
+    /// Panics if `rom` doesn't fit in memory.
+    pub fn new(rom: &[u8], spec: GameSpec, count: usize, seed: u64) -> Result<Self, EnvFault> {
+        let envs = (0..count).map(|env| Env::new(rom, spec.clone()).map_err(|fault| EnvFault { env, fault })).collect::<Result<_, _>>()?;
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        let mut vec_env = Self { envs, threads };
+        vec_env.reset(seed)?;
+        return Ok(vec_env);
+    }
+
+    /// Reset every environment, `i` with seed `seed + i`
+    pub fn reset(&mut self, seed: u64) -> Result<Vec<Framebuffer>, EnvFault> {
+        return self.envs.iter_mut().enumerate().map(|(i, env)| {
+            env.reset(seed.wrapping_add(i as u64)).map_err(|fault| EnvFault { env: i, fault })
+        }).collect();
+    }
+
+    /// Step environment `i` with `actions[i]`
+    /// Every environment is stepped even if one faults, then the first fault is returned.
+    pub fn step(&mut self, actions: &[usize]) -> Result<Vec<Step>, EnvFault> {
+        assert_eq!(actions.len(), self.envs.len(), "one action per environment");
+        let count = self.envs.len() as u64;
+        let step = move |env: &mut Env, action: usize| {
+            let mut step = env.step(action)?;
+            if step.done {
+                step.observation = env.reset(env.seed.wrapping_add(count))?;
+            }
+            return Ok(step);
+        };
+
+        let chunk = self.envs.len().div_ceil(self.threads).max(1);
+        if chunk == self.envs.len() {
+            let steps: Vec<_> = self.envs.iter_mut().zip(actions).map(|(env, action)| step(env, *action)).collect();
+            return collect_steps(steps);
+        }
+        return thread::scope(|scope| {
+            let handles: Vec<_> = self.envs.chunks_mut(chunk).zip(actions.chunks(chunk)).map(|(envs, actions)| {
+                scope.spawn(move || envs.iter_mut().zip(actions).map(|(env, action)| step(env, *action)).collect::<Vec<_>>())
+            }).collect();
+            let steps: Vec<_> = handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect();
+            return collect_steps(steps);
+        });
+    }
+
+    /// Threads to step on, at least 1
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    pub fn envs(&self) -> &[Env] {
+        return &self.envs;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.envs.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.envs.is_empty();
+    }
+}
+
+/// The steps, or the first environment's fault
+fn collect_steps(steps: Vec<Result<Step, Fault>>) -> Result<Vec<Step>, EnvFault> {
+    return steps.into_iter().enumerate().map(|(env, step)| step.map_err(|fault| EnvFault { env, fault })).collect();
+}
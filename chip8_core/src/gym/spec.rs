@@ -0,0 +1,238 @@
+use std::fmt;
+
+use crate::cheats::{Cheat, CheatTarget};
+use crate::constants::NUM_KEYS;
+use crate::{Emu, Quirks};
+
+/// Specs for games in `roms/`, by ROM name
+pub const BUILTIN: [(&str, &str); 2] = [
+    ("PONG", include_str!("games/pong.txt")),
+    ("BRIX", include_str!("games/brix.txt")),
+];
+
+/// A number read out of the game: `target / div % modulo`
+/// Dividing and taking the remainder picks out part of a packed score.
+/// A target outside RAM or the registers reads as 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Value {
+    pub target: CheatTarget,
+    pub div: u32,
+    pub modulo: Option<u32>,
+}
+impl Value {
+    pub fn new(target: CheatTarget) -> Self {
+        return Self { target, div: 1, modulo: None };
+    }
+
+    pub fn read(&self, emu: &Emu) -> i64 {
+        let value = self.target.read(emu).unwrap_or(0) as u32 / self.div;
+        return self.modulo.map_or(value, |modulo| value % modulo) as i64;
+    }
+}
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.target)?;
+        if self.div != 1 { write!(f, " / {}", self.div)?; }
+        if let Some(modulo) = self.modulo { write!(f, " % {}", modulo)?; }
+        return Ok(());
+    }
+}
+
+/// Reward of `scale` for every unit `value` goes up by (and minus that for every unit down)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reward {
+    pub value: Value,
+    pub scale: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+impl Comparison {
+    pub fn test(&self, left: i64, right: i64) -> bool {
+        return match self {
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+            Comparison::Less => left < right,
+            Comparison::LessEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterEqual => left >= right,
+        };
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        return match text {
+            "==" => Some(Comparison::Equal),
+            "!=" => Some(Comparison::NotEqual),
+            "<" => Some(Comparison::Less),
+            "<=" => Some(Comparison::LessEqual),
+            ">" => Some(Comparison::Greater),
+            ">=" => Some(Comparison::GreaterEqual),
+            _ => None,
+        };
+    }
+}
+
+/// Ends the episode once `value` compares true with `threshold`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Condition {
+    pub value: Value,
+    pub comparison: Comparison,
+    pub threshold: i64,
+}
+impl Condition {
+    pub fn holds(&self, emu: &Emu) -> bool {
+        return self.comparison.test(self.value.read(emu), self.threshold);
+    }
+}
+
+/// How to play a ROM as a reinforcement learning task
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameSpec {
+    /// Keys held for each action, the action space is `0..actions.len()`
+    pub actions: Vec<Vec<usize>>,
+    /// Summed each frame
+    pub rewards: Vec<Reward>,
+    /// The episode ends when any of these hold
+    pub done: Vec<Condition>,
+    /// Frames each action is held for
+    pub frame_skip: usize,
+    /// Frames run on reset before the agent takes over, e.g. while the game sets itself up
+    pub start_frames: usize,
+    /// Episodes are cut short (truncated) after this many frames
+    pub max_frames: Option<usize>,
+    pub quirks: Quirks,
+}
+impl Default for GameSpec {
+    /// Any single key or none, with no rewards
+    fn default() -> Self {
+        let mut actions = vec![Vec::new()];
+        actions.extend((0..NUM_KEYS).map(|key| vec![key]));
+        return Self {
+            actions,
+            rewards: Vec::new(),
+            done: Vec::new(),
+            frame_skip: 4,
+            start_frames: 0,
+            max_frames: None,
+            quirks: Quirks::default(),
+        };
+    }
+}
+impl GameSpec {
+    /// The spec for one of the games in `BUILTIN`
+    pub fn builtin(name: &str) -> Option<Self> {
+        let (_, text) = BUILTIN.iter().find(|(game, _)| game.eq_ignore_ascii_case(name))?;
+        return Some(Self::parse(text).expect("builtin specs are valid"));
+    }
+
+    /// Parse a spec file
+    ///
+    /// One `KEY = VALUE` setting per line, `#` starts a comment. Settings left out keep their
+    /// defaults, `reward` and `done` can be given any number of times. Tokens are separated by spaces.
+    ///
+    /// - `actions = -, 4, 6, 4+6`: the keys held for each action, `-` for none
+    /// - `reward = VALUE [* SCALE]`: reward the change in `VALUE` each frame, times `SCALE` (default 1)
+    /// - `done = VALUE OP NUMBER`: end the episode when the comparison holds,
+    ///   `OP` being one of `==`, `!=`, `<`, `<=`, `>`, `>=`
+    /// - `frame_skip`, `start_frames`, `max_frames`: numbers, see the fields
+    /// - `quirks = PRESET`: a name from `Quirks::PRESETS`
+    ///
+    /// A `VALUE` is a register (`VE`) or RAM address (`0x2F0`), optionally followed by `/ N` and `% N`.
+    pub fn parse(text: &str) -> Result<Self, SpecParseError> {
+        let mut spec = Self::default();
+        let mut actions = None;
+        for (idx, line) in text.lines().enumerate() {
+            let error = |reason: &str| SpecParseError { line: idx + 1, reason: reason.to_string() };
+
+            let body = line.split_once('#').map_or(line, |(body, _)| body).trim();
+            if body.is_empty() { continue; }
+            let (key, value) = body.split_once('=').ok_or_else(|| error("expected `KEY = VALUE`"))?;
+            let value = value.trim();
+            let tokens: Vec<&str> = value.split_whitespace().collect();
+
+            match key.trim() {
+                "actions" => {
+                    let parsed: Option<Vec<_>> = value.split(',').map(|action| Self::parse_keys(action.trim())).collect();
+                    actions = Some(parsed.ok_or_else(|| error("invalid keys, expected e.g. `-, 4, 6, 4+6`"))?);
+                },
+                "reward" => {
+                    let (value, scale) = match tokens[..] {
+                        [ref value @ .., "*", scale] => (value, scale.parse().map_err(|_| error("invalid scale"))?),
+                        ref value => (value, 1.0),
+                    };
+                    let value = Self::parse_value(value).ok_or_else(|| error("invalid value"))?;
+                    spec.rewards.push(Reward { value, scale });
+                },
+                "done" => {
+                    let [ref value @ .., comparison, threshold] = tokens[..] else {
+                        return Err(error("expected `VALUE OP NUMBER`"));
+                    };
+                    let value = Self::parse_value(value).ok_or_else(|| error("invalid value"))?;
+                    let comparison = Comparison::parse(comparison).ok_or_else(|| error("invalid comparison"))?;
+                    let threshold = Self::parse_number(threshold).ok_or_else(|| error("invalid number"))?;
+                    spec.done.push(Condition { value, comparison, threshold });
+                },
+                "frame_skip" => {
+                    spec.frame_skip = value.parse().ok().filter(|n| *n > 0).ok_or_else(|| error("expected a positive number"))?;
+                },
+                "start_frames" => spec.start_frames = value.parse().map_err(|_| error("expected a number"))?,
+                "max_frames" => spec.max_frames = Some(value.parse().map_err(|_| error("expected a number"))?),
+                "quirks" => spec.quirks = Quirks::preset(value).ok_or_else(|| error("unknown quirk preset"))?,
+                _ => return Err(error("unknown setting")),
+            }
+        }
+        if let Some(actions) = actions { spec.actions = actions; }
+        return Ok(spec);
+    }
+
+    /// `-`, or keys joined with `+`
+    fn parse_keys(text: &str) -> Option<Vec<usize>> {
+        if text == "-" { return Some(Vec::new()); }
+        return text.split('+').map(|key| {
+            let key = usize::from_str_radix(key.trim(), 16).ok()?;
+            return (key < NUM_KEYS).then_some(key);
+        }).collect();
+    }
+
+    fn parse_value(tokens: &[&str]) -> Option<Value> {
+        let (target, mut rest) = tokens.split_first()?;
+        let mut value = Value::new(Cheat::parse_target(target)?);
+        // Each operation at most once, dividing first, as that's the order `read` applies them in
+        while let [op, number, tail @ ..] = rest {
+            let number = u32::try_from(Self::parse_number(number)?).ok().filter(|n| *n > 0)?;
+            match *op {
+                "/" if value.div == 1 && value.modulo.is_none() => value.div = number,
+                "%" if value.modulo.is_none() => value.modulo = Some(number),
+                _ => return None,
+            }
+            rest = tail;
+        }
+        return rest.is_empty().then_some(value);
+    }
+
+    fn parse_number(text: &str) -> Option<i64> {
+        return match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            Some(hex) => i64::from_str_radix(hex, 16).ok(),
+            None => text.parse().ok(),
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecParseError {
+    pub line: usize,
+    pub reason: String,
+}
+impl fmt::Display for SpecParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "line {}: {}", self.line, self.reason);
+    }
+}
+impl std::error::Error for SpecParseError {}
@@ -1,9 +1,62 @@
 #![allow(dead_code, clippy::needless_return)]
+// Tooling (analysis, the Octo compiler, profiling, ...) needs `std`,
+// without it the emulator itself only needs `alloc`
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 
 mod emu;
-pub use emu::Emu;
+pub use emu::{Dirty, Emu, Fault, Framebuffer, Opcode, RomTooLarge, Snapshot, SnapshotError, UnknownOpcode};
+pub use emu::backend;
 
 pub mod constants;
 
 pub mod resources;
 pub use resources::font;
+
+#[cfg(feature = "std")]
+pub mod analysis;
+
+#[cfg(feature = "std")]
+pub mod octo;
+
+#[cfg(feature = "std")]
+pub mod aot;
+
+#[cfg(feature = "std")]
+pub mod profiler;
+#[cfg(feature = "std")]
+pub use profiler::Profiler;
+
+#[cfg(feature = "std")]
+pub mod coverage;
+#[cfg(feature = "std")]
+pub use coverage::Coverage;
+
+pub mod quirks;
+pub use quirks::Quirks;
+
+#[cfg(feature = "std")]
+pub mod cheats;
+#[cfg(feature = "std")]
+pub use cheats::{Cheat, CheatSearch};
+
+#[cfg(feature = "std")]
+pub mod lockstep;
+#[cfg(feature = "std")]
+pub use lockstep::Divergence;
+
+#[cfg(feature = "std")]
+pub mod gym;
+
+#[cfg(feature = "std")]
+pub mod netplay;
+
+pub mod phosphor;
+pub use phosphor::{Phosphor, PhosphorMode};
+
+pub mod render;
+pub use render::{Image, Palette};
+
+pub mod platform;
+pub use platform::{Beeper, Random, Silent, Xorshift};
@@ -0,0 +1,162 @@
+use std::fmt::{self, Write};
+
+use crate::backend::Backend;
+use crate::constants::{NUM_KEYS, NUM_REGS, RAM_SIZE, TICKS_PER_FRAME};
+use crate::{Emu, Fault, Opcode};
+
+/// One of the two configurations being compared
+pub struct Side {
+    pub name: String,
+    pub emu: Emu,
+    pub backend: Box<dyn Backend>,
+}
+
+/// Where two configurations first stopped agreeing
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    pub frame: usize,
+    /// Instructions executed by each side before the one that diverged
+    pub instruction: u64,
+    /// Address of the instruction each side had just executed
+    pub addrs: (u16, u16),
+    /// A line per differing part of the state
+    pub differences: Vec<String>,
+    report: String,
+}
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", self.report);
+    }
+}
+
+/// Run two emulators in lockstep for up to `frames` frames, comparing their full state after
+/// every instruction, and return the first divergence
+///
+/// Both sides should have the same ROM loaded and the same RNG seed. They get the same key presses
+/// (`keys(frame)`, bit N for key N) and run frames like `Emu::run_frame_with`, apart from cheats.
+/// A fault on one side only is a divergence, the same fault on both ends the run without one.
+pub fn lockstep(left: &mut Side, right: &mut Side, frames: usize, keys: impl Fn(usize) -> u16) -> Option<Divergence> {
+    let mut instruction = 0;
+    for frame in 0..frames {
+        let mask = keys(frame);
+        for emu in [&mut left.emu, &mut right.emu] {
+            for key in 0..NUM_KEYS {
+                emu.keypress(key, mask >> key & 1 != 0);
+            }
+        }
+
+        for _ in 0..TICKS_PER_FRAME {
+            if left.emu.is_waiting_vblank() && right.emu.is_waiting_vblank() { break; }
+            let addrs = (left.emu.pc(), right.emu.pc());
+            let faults = [&mut *left, &mut *right].map(|side| side.backend.run(&mut side.emu, 1).err());
+            // Both stuck at the same instruction, there's nothing more to compare
+            if faults[0].is_some() && faults[0] == faults[1] { return None; }
+            let mut differences = differences(&left.emu, &right.emu);
+            if let Some(fault) = fault_difference(left, right, faults) { differences.insert(0, fault); }
+            if !differences.is_empty() {
+                let report = report(left, right, frame, instruction, addrs, &differences);
+                return Some(Divergence { frame, instruction, addrs, differences, report });
+            }
+            instruction += 1;
+        }
+
+        for emu in [&mut left.emu, &mut right.emu] {
+            emu.tick_timers();
+            emu.vblank();
+        }
+    }
+    return None;
+}
+
+/// Which side faulted where, if they didn't fault alike
+fn fault_difference(left: &Side, right: &Side, faults: [Option<Fault>; 2]) -> Option<String> {
+    let at = |side: &Side, fault: Fault| format!("{} faulted at PC {:#05X} ({})", side.name, side.emu.pc(), fault);
+    return match faults {
+        [Some(l), Some(r)] if l != r => Some(format!("{}, {}", at(left, l), at(right, r))),
+        [Some(l), None] => Some(format!("{}, {} did not", at(left, l), right.name)),
+        [None, Some(r)] => Some(format!("{}, {} did not", at(right, r), left.name)),
+        _ => None,
+    };
+}
+
+fn differences(left: &Emu, right: &Emu) -> Vec<String> {
+    let mut out = Vec::new();
+    if left.pc() != right.pc() {
+        out.push(format!("PC: {:#05X} vs {:#05X}", left.pc(), right.pc()));
+    }
+    for reg in 0..NUM_REGS {
+        let (l, r) = (left.registers()[reg], right.registers()[reg]);
+        if l != r { out.push(format!("V{:X}: {:#04X} vs {:#04X}", reg, l, r)); }
+    }
+    if left.index() != right.index() {
+        out.push(format!("I: {:#05X} vs {:#05X}", left.index(), right.index()));
+    }
+    if left.stack() != right.stack() {
+        out.push(format!("stack: {:03X?} vs {:03X?}", left.stack(), right.stack()));
+    }
+    if left.timers() != right.timers() {
+        out.push(format!("timers (delay, sound): {:?} vs {:?}", left.timers(), right.timers()));
+    }
+    if left.is_waiting_vblank() != right.is_waiting_vblank() {
+        out.push(format!("waiting for vblank: {} vs {}", left.is_waiting_vblank(), right.is_waiting_vblank()));
+    }
+    let (l, r) = (left.memory(0..RAM_SIZE), right.memory(0..RAM_SIZE));
+    if let Some(addr) = (0..RAM_SIZE).find(|a| l[*a] != r[*a]) {
+        let count = (0..RAM_SIZE).filter(|a| l[*a] != r[*a]).count();
+        out.push(format!("memory: {} bytes differ, first at {:#05X} ({:#04X} vs {:#04X})", count, addr, l[addr], r[addr]));
+    }
+    let pixels = left.get_display().zip(right.get_display()).filter(|(l, r)| l != r).count();
+    if pixels > 0 {
+        out.push(format!("display: {} pixels differ", pixels));
+    }
+    return out;
+}
+
+/// Disassembly around `addr`, with the instruction at `addr` marked
+fn disassembly(emu: &Emu, addr: u16) -> Vec<String> {
+    let start = addr.saturating_sub(4);
+    return (0..5).map(|i| {
+        let a = start + i * 2;
+        let bytes = emu.memory(a as usize..a as usize + 2);
+        let text = match bytes {
+            [hi, lo] => match Opcode::try_new(u16::from_be_bytes([*hi, *lo])) {
+                Ok(op) => op.to_string(),
+                Err(_) => format!("db {:#04X}, {:#04X}", hi, lo),
+            },
+            _ => String::new(),
+        };
+        format!("{} {:03X}: {}", if a == addr { '>' } else { ' ' }, a, text)
+    }).collect();
+}
+
+fn report(left: &Side, right: &Side, frame: usize, instruction: u64, addrs: (u16, u16), differences: &[String]) -> String {
+    const COLUMN: usize = 30;
+    let mut out = String::new();
+    writeln!(out, "Diverged on frame {}, after {} matching instructions", frame, instruction).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "  {:<COLUMN$}  {}", left.name, right.name).unwrap();
+    let (l, r) = (disassembly(&left.emu, addrs.0), disassembly(&right.emu, addrs.1));
+    for (l, r) in l.iter().zip(&r) {
+        writeln!(out, "{:<COLUMN$}  | {}", l, r).unwrap();
+    }
+    writeln!(out).unwrap();
+
+    let row = |out: &mut String, name: &str, l: String, r: String| {
+        let mark = if l != r { '*' } else { ' ' };
+        writeln!(out, "{} {:<5} {:<COLUMN$}{}", mark, name, l, r).unwrap();
+    };
+    for reg in 0..NUM_REGS {
+        row(&mut out, &format!("V{:X}", reg), format!("{:#04X}", left.emu.registers()[reg]), format!("{:#04X}", right.emu.registers()[reg]));
+    }
+    row(&mut out, "I", format!("{:#05X}", left.emu.index()), format!("{:#05X}", right.emu.index()));
+    row(&mut out, "PC", format!("{:#05X}", left.emu.pc()), format!("{:#05X}", right.emu.pc()));
+    row(&mut out, "stack", format!("{:03X?}", left.emu.stack()), format!("{:03X?}", right.emu.stack()));
+    row(&mut out, "DT", left.emu.timers().0.to_string(), right.emu.timers().0.to_string());
+    row(&mut out, "ST", left.emu.timers().1.to_string(), right.emu.timers().1.to_string());
+    writeln!(out).unwrap();
+
+    for difference in differences {
+        writeln!(out, "{}", difference).unwrap();
+    }
+    return out;
+}
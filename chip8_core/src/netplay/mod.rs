@@ -0,0 +1,311 @@
+//! Rollback netplay, for two players sharing one keypad across machines
+//!
+//! Each peer runs the game itself and sends its keys every frame. The other player's keys are
+//! predicted (they're assumed to be held as they last were), and when the real ones turn out
+//! different the session goes back to a `Snapshot` from before them and replays the frames since.
+//! Delaying local input by a few frames gives the other player's keys time to arrive, trading
+//! responsiveness for fewer rollbacks.
+//!
+//! Both peers must start from the same state (ROM, quirks and RNG seed) and use the same
+//! `NetplayConfig`, and their keys are combined, so each player should keep to their own keys
+//! (e.g. `1`/`4` and `C`/`D` in PONG). Peers compare hashes of their state now and then to catch
+//! them drifting apart, see `Session::desync`.
+
+use std::collections::VecDeque;
+
+use crate::constants::NUM_KEYS;
+use crate::{Emu, Fault, Snapshot};
+
+mod protocol;
+use protocol::{Packet, MAX_INPUTS, MAX_PACKET};
+
+pub mod transport;
+pub use transport::{Transport, UdpTransport};
+
+/// Hashes kept for comparing with the other peer's
+const HASH_HISTORY: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetplayConfig {
+    /// Frames between pressing a key and it taking effect
+    pub input_delay: usize,
+    /// Most frames to run ahead of the other player's input before waiting for it
+    pub max_prediction: usize,
+    /// Frames between state hashes
+    pub hash_interval: usize,
+}
+impl Default for NetplayConfig {
+    fn default() -> Self {
+        return Self { input_delay: 2, max_prediction: 8, hash_interval: 60 };
+    }
+}
+
+/// What `Session::advance` did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Advance {
+    /// Ran a frame, after replaying `rolled_back` frames to correct mispredictions
+    Ran { rolled_back: usize },
+    /// Too far ahead of the other player, waiting for their input
+    /// The keys passed in weren't used, pass them again next time.
+    Stalled,
+}
+
+/// The peers' states differed on a frame where both had the same input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Desync {
+    pub frame: usize,
+    pub local: u64,
+    pub remote: u64,
+}
+
+/// Keys for a run of frames, bit N for key N
+#[derive(Debug, Clone, Default)]
+struct InputLog {
+    base: usize, // Frame of the first input kept
+    inputs: VecDeque<u16>,
+    last: u16, // Latest input, even once forgotten
+}
+impl InputLog {
+    fn get(&self, frame: usize) -> Option<u16> {
+        return self.inputs.get(frame.checked_sub(self.base)?).copied();
+    }
+
+    /// Frame after the latest input
+    fn end(&self) -> usize {
+        return self.base + self.inputs.len();
+    }
+
+    fn push(&mut self, input: u16) {
+        self.inputs.push_back(input);
+        self.last = input;
+    }
+
+    /// Forget inputs before `frame`
+    fn forget(&mut self, frame: usize) {
+        let count = frame.saturating_sub(self.base).min(self.inputs.len());
+        self.inputs.drain(..count);
+        self.base += count;
+    }
+
+    /// Forget inputs from `frame` on
+    fn truncate(&mut self, frame: usize) {
+        self.inputs.truncate(frame.saturating_sub(self.base));
+    }
+}
+
+/// One peer of a two player game
+///
+/// Call `advance` once per frame with the local player's keys, then draw `emu`.
+#[derive(Debug)]
+pub struct Session<T: Transport> {
+    emu: Emu,
+    transport: T,
+    config: NetplayConfig,
+    frame: usize, // Next frame to run
+    settled: usize, // Frames before this have had both players' input and won't be rolled back
+    local: InputLog,
+    remote: InputLog, // Only confirmed input
+    used: InputLog, // Remote input each unsettled frame ran with, confirmed or predicted
+    snapshots: VecDeque<Snapshot>, // State at the start of each unsettled frame
+    rollback: Option<usize>, // Earliest frame that ran with a wrong prediction
+    peer_ack: usize, // Frames of local input the peer has
+    local_hashes: VecDeque<(usize, u64)>,
+    remote_hashes: VecDeque<(usize, u64)>,
+    desync: Option<Desync>,
+    rolled_back: usize,
+    fault: Option<Fault>, // Fault the latest frame stopped at
+}
+impl<T: Transport> Session<T> {
+    pub fn new(emu: Emu, transport: T, config: NetplayConfig) -> Self {
+        assert!(config.max_prediction > 0 && config.hash_interval > 0, "invalid netplay config");
+        let mut session = Self {
+            emu,
+            transport,
+            config,
+            frame: 0,
+            settled: 0,
+            local: InputLog::default(),
+            remote: InputLog::default(),
+            used: InputLog::default(),
+            snapshots: VecDeque::new(),
+            rollback: None,
+            peer_ack: 0,
+            local_hashes: VecDeque::new(),
+            remote_hashes: VecDeque::new(),
+            desync: None,
+            rolled_back: 0,
+            fault: None,
+        };
+        // Neither player has input for the first frames
+        for _ in 0..config.input_delay {
+            session.local.push(0);
+            session.remote.push(0);
+        }
+        return session;
+    }
+
+    /// Run the next frame with the local player's `keys`, unless too far ahead of the other player
+    pub fn advance(&mut self, keys: u16) -> Advance {
+        let rolled_back = self.receive();
+        if self.frame >= self.remote.end() + self.config.max_prediction {
+            self.send();
+            return Advance::Stalled;
+        }
+        self.local.push(keys);
+        self.run_frame();
+        self.settle();
+        self.send();
+        return Advance::Ran { rolled_back };
+    }
+
+    /// Handle packets from the other player without running a frame, e.g. while paused
+    /// Returns how many frames were replayed.
+    pub fn poll(&mut self) -> usize {
+        let rolled_back = self.receive();
+        self.settle();
+        self.send();
+        return rolled_back;
+    }
+
+    /// Read every waiting packet, then roll back if a prediction was wrong
+    fn receive(&mut self) -> usize {
+        let mut buf = [0; MAX_PACKET];
+        while let Some(len) = self.transport.recv(&mut buf) {
+            let Some(packet) = Packet::decode(&buf[..len]) else { continue; };
+            self.peer_ack = self.peer_ack.max(packet.ack);
+            for (frame, input) in (packet.start..).zip(packet.inputs) {
+                if frame != self.remote.end() { continue; }
+                self.remote.push(input);
+                if self.used.get(frame).is_some_and(|used| used != input) {
+                    self.rollback = Some(self.rollback.map_or(frame, |earliest| earliest.min(frame)));
+                }
+            }
+            if let Some(hash) = packet.hash {
+                if !self.remote_hashes.contains(&hash) { remember(&mut self.remote_hashes, hash); }
+                self.check_hashes();
+            }
+        }
+
+        let Some(from) = self.rollback.take() else { return 0; };
+        let to = self.frame;
+        self.emu.restore(&self.snapshots[from - self.settled]);
+        self.snapshots.truncate(from - self.settled);
+        self.used.truncate(from);
+        self.frame = from;
+        while self.frame < to {
+            self.run_frame();
+        }
+        self.rolled_back += to - from;
+        return to - from;
+    }
+
+    fn run_frame(&mut self) {
+        let remote = self.remote.get(self.frame).unwrap_or(self.remote.last);
+        let keys = self.local.get(self.frame).unwrap() | remote;
+        self.snapshots.push_back(self.emu.snapshot());
+        self.used.push(remote);
+        for key in 0..NUM_KEYS {
+            self.emu.keypress(key, keys >> key & 1 != 0);
+        }
+        self.fault = self.emu.run_frame().err();
+        self.frame += 1;
+    }
+
+    /// Hash the frames that now have both players' input, and forget what's no longer needed
+    fn settle(&mut self) {
+        let settled = self.remote.end().min(self.frame);
+        for frame in self.settled + 1..=settled {
+            if frame % self.config.hash_interval != 0 { continue; }
+            let state = match frame == self.frame {
+                true => self.emu.snapshot(),
+                false => self.snapshots[frame - self.settled].clone(),
+            };
+            remember(&mut self.local_hashes, (frame, hash(&state.to_bytes())));
+        }
+        self.check_hashes();
+
+        self.snapshots.drain(..settled - self.settled);
+        self.used.forget(settled);
+        self.remote.forget(settled);
+        self.local.forget(settled.min(self.peer_ack));
+        self.settled = settled;
+    }
+
+    fn check_hashes(&mut self) {
+        if self.desync.is_some() { return; }
+        for (frame, remote) in &self.remote_hashes {
+            let local = self.local_hashes.iter().find(|(f, _)| f == frame);
+            if let Some((_, local)) = local.filter(|(_, local)| local != remote) {
+                self.desync = Some(Desync { frame: *frame, local: *local, remote: *remote });
+                return;
+            }
+        }
+    }
+
+    /// Send the local input the peer hasn't acknowledged yet, and the latest hash
+    fn send(&mut self) {
+        let start = self.peer_ack.max(self.local.base);
+        let end = self.local.end().min(start + MAX_INPUTS);
+        let packet = Packet {
+            ack: self.remote.end(),
+            hash: self.local_hashes.back().copied(),
+            start,
+            inputs: (start..end).map(|frame| self.local.get(frame).unwrap()).collect(),
+        };
+        self.transport.send(&packet.encode());
+    }
+
+    pub fn emu(&self) -> &Emu {
+        return &self.emu;
+    }
+
+    pub fn transport_mut(&mut self) -> &mut T {
+        return &mut self.transport;
+    }
+
+    pub fn config(&self) -> NetplayConfig {
+        return self.config;
+    }
+
+    /// Frames run so far
+    pub fn frame(&self) -> usize {
+        return self.frame;
+    }
+
+    /// Frames that have had both players' input, and so won't change
+    pub fn settled_frame(&self) -> usize {
+        return self.settled;
+    }
+
+    /// Total frames replayed by rollbacks
+    pub fn rolled_back(&self) -> usize {
+        return self.rolled_back;
+    }
+
+    /// The `Fault` the latest frame stopped at, if the ROM faulted
+    /// The game stays stuck at the faulting instruction, the same on both peers.
+    pub fn fault(&self) -> Option<Fault> {
+        return self.fault;
+    }
+
+    /// The first frame found where the peers' states differed
+    /// Once desynced, the game won't recover.
+    pub fn desync(&self) -> Option<Desync> {
+        return self.desync;
+    }
+}
+
+/// Add to a hash history, forgetting the oldest
+fn remember(history: &mut VecDeque<(usize, u64)>, hash: (usize, u64)) {
+    if history.len() == HASH_HISTORY { history.pop_front(); }
+    history.push_back(hash);
+}
+
+/// FNV-1a, to compare states without sending them
+fn hash(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xCBF2_9CE4_8422_2325;
+    for byte in bytes {
+        hash = (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01B3);
+    }
+    return hash;
+}
@@ -0,0 +1,59 @@
+/// Start of every packet
+const MAGIC: [u8; 2] = *b"C8";
+/// Bumped whenever the layout changes
+const VERSION: u8 = 1;
+/// Magic, version, ack, hash flag, hash frame, hash, first input's frame, input count
+const HEADER_SIZE: usize = 2 + 1 + 4 + 1 + 4 + 8 + 4 + 1;
+
+/// Most inputs sent in one packet
+pub(super) const MAX_INPUTS: usize = u8::MAX as usize;
+/// Largest packet, for receive buffers
+pub(super) const MAX_PACKET: usize = HEADER_SIZE + 2 * MAX_INPUTS;
+
+/// What each peer sends every frame
+/// Inputs are resent until acknowledged, so a lost packet is covered by the next one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Packet {
+    /// Frames of the receiver's input the sender has, all of `0..ack`
+    pub ack: usize,
+    /// The sender's latest state hash, as `(frame, hash)`
+    pub hash: Option<(usize, u64)>,
+    /// Frame of the first input
+    pub start: usize,
+    /// The sender's keys for frames `start..`, bit N for key N
+    pub inputs: Vec<u16>,
+}
+impl Packet {
+    pub fn encode(&self) -> Vec<u8> {
+        let (has_hash, (hash_frame, hash)) = (self.hash.is_some(), self.hash.unwrap_or((0, 0)));
+        let count = self.inputs.len().min(MAX_INPUTS);
+        let mut out = Vec::with_capacity(HEADER_SIZE + 2 * count);
+        out.extend_from_slice(&MAGIC);
+        out.push(VERSION);
+        out.extend_from_slice(&(self.ack as u32).to_le_bytes());
+        out.push(has_hash as u8);
+        out.extend_from_slice(&(hash_frame as u32).to_le_bytes());
+        out.extend_from_slice(&hash.to_le_bytes());
+        out.extend_from_slice(&(self.start as u32).to_le_bytes());
+        out.push(count as u8);
+        for input in &self.inputs[..count] {
+            out.extend_from_slice(&input.to_le_bytes());
+        }
+        return out;
+    }
+
+    /// `None` for anything that isn't a packet from this version
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < HEADER_SIZE || bytes[..2] != MAGIC || bytes[2] != VERSION { return None; }
+        let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as usize;
+        let count = bytes[HEADER_SIZE - 1] as usize;
+        if bytes.len() != HEADER_SIZE + 2 * count { return None; }
+
+        let hash = match bytes[7] {
+            0 => None,
+            _ => Some((u32_at(8), u64::from_le_bytes(bytes[12..20].try_into().unwrap()))),
+        };
+        let inputs = bytes[HEADER_SIZE..].chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+        return Some(Self { ack: u32_at(3), hash, start: u32_at(20), inputs });
+    }
+}
@@ -0,0 +1,52 @@
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+/// How packets get to the other peer
+/// Packets may be lost, duplicated or reordered, the session copes with all three.
+pub trait Transport {
+    fn send(&mut self, packet: &[u8]);
+
+    /// The next packet received into `buf`, returning its length, or `None` if there isn't one yet
+    /// Mustn't block.
+    fn recv(&mut self, buf: &mut [u8]) -> Option<usize>;
+}
+
+/// A UDP socket talking to a single peer
+#[derive(Debug)]
+pub struct UdpTransport {
+    socket: UdpSocket,
+}
+impl UdpTransport {
+    /// Bind to `local` (e.g. `0.0.0.0:7800`) and exchange packets with `peer` only
+    pub fn bind(local: impl ToSocketAddrs, peer: impl ToSocketAddrs) -> io::Result<Self> {
+        return Self::from_socket(UdpSocket::bind(local)?, peer);
+    }
+
+    /// Use an already bound socket, e.g. one on port 0 whose port has been given to the peer
+    pub fn from_socket(socket: UdpSocket, peer: impl ToSocketAddrs) -> io::Result<Self> {
+        socket.connect(peer)?;
+        socket.set_nonblocking(true)?;
+        return Ok(Self { socket });
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        return self.socket.local_addr();
+    }
+}
+impl Transport for UdpTransport {
+    fn send(&mut self, packet: &[u8]) {
+        // Failures are just more packet loss, e.g. before the peer has started
+        let _ = self.socket.send(packet);
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> Option<usize> {
+        loop {
+            match self.socket.recv(buf) {
+                Ok(len) => return Some(len),
+                // Reported for an earlier send while the peer wasn't listening
+                Err(err) if err.kind() == io::ErrorKind::ConnectionRefused => continue,
+                Err(_) => return None,
+            }
+        }
+    }
+}
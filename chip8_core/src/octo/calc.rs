@@ -0,0 +1,129 @@
+use super::lexer::Token;
+
+/// Evaluate a `:calc` expression
+///
+/// Like Octo, binary operators have no precedence and associate to the right,
+/// so `2 * 3 + 1` is `2 * (3 + 1)`. Use parentheses to be explicit.
+/// `lookup` resolves names (constants, labels, `HERE`), `peek` reads a byte of the ROM for `@`.
+pub fn evaluate(
+    tokens: &[Token],
+    lookup: &dyn Fn(&str) -> Option<f64>,
+    peek: &dyn Fn(usize) -> f64,
+) -> Result<f64, String> {
+    let mut calc = Calc { tokens, pos: 0, lookup, peek };
+    let value = calc.expression()?;
+    if calc.pos != tokens.len() {
+        return Err(format!("unexpected `{}` in expression", tokens[calc.pos].text));
+    }
+    return Ok(value);
+}
+
+struct Calc<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    lookup: &'a dyn Fn(&str) -> Option<f64>,
+    peek: &'a dyn Fn(usize) -> f64,
+}
+impl Calc<'_> {
+    fn next(&mut self) -> Result<&str, String> {
+        let token = self.tokens.get(self.pos).ok_or("unexpected end of expression")?;
+        self.pos += 1;
+        return Ok(&token.text);
+    }
+
+    fn expression(&mut self) -> Result<f64, String> {
+        let lhs = self.term()?;
+        let Some(op) = self.tokens.get(self.pos).map(|t| t.text.clone()) else { return Ok(lhs); };
+        if op == ")" { return Ok(lhs); }
+        self.pos += 1;
+        let rhs = self.expression()?;
+
+        let bool = |b: bool| if b { 1.0 } else { 0.0 };
+        let int = |v: f64| v as i64;
+        let shift = |shift: fn(i64, u32) -> Option<i64>| {
+            let value = u32::try_from(int(rhs)).ok().and_then(|amount| shift(int(lhs), amount));
+            return value.map(|v| v as f64).ok_or_else(|| format!("shift by {} is out of range", rhs));
+        };
+        return Ok(match op.as_str() {
+            "+" => lhs + rhs,
+            "-" => lhs - rhs,
+            "*" => lhs * rhs,
+            "/" => lhs / rhs,
+            "%" => lhs % rhs,
+            "&" => (int(lhs) & int(rhs)) as f64,
+            "|" => (int(lhs) | int(rhs)) as f64,
+            "^" => (int(lhs) ^ int(rhs)) as f64,
+            "<<" => shift(i64::checked_shl)?,
+            ">>" => shift(i64::checked_shr)?,
+            "pow" => lhs.powf(rhs),
+            "min" => lhs.min(rhs),
+            "max" => lhs.max(rhs),
+            "<" => bool(lhs < rhs),
+            ">" => bool(lhs > rhs),
+            "<=" => bool(lhs <= rhs),
+            ">=" => bool(lhs >= rhs),
+            "==" => bool(lhs == rhs),
+            "!=" => bool(lhs != rhs),
+            _ => return Err(format!("unknown operator `{}`", op)),
+        });
+    }
+
+    fn term(&mut self) -> Result<f64, String> {
+        let token = self.next()?.to_string();
+        if token == "(" {
+            let value = self.expression()?;
+            if self.next()? != ")" { return Err("expected `)`".to_string()); }
+            return Ok(value);
+        }
+
+        let unary: Option<fn(f64) -> f64> = match token.as_str() {
+            "-" => Some(|v| -v),
+            "~" => Some(|v| !(v as i64) as f64),
+            "!" => Some(|v| if v == 0.0 { 1.0 } else { 0.0 }),
+            "sin" => Some(f64::sin),
+            "cos" => Some(f64::cos),
+            "tan" => Some(f64::tan),
+            "exp" => Some(f64::exp),
+            "log" => Some(f64::ln),
+            "abs" => Some(f64::abs),
+            "sqrt" => Some(f64::sqrt),
+            "sign" => Some(f64::signum),
+            "ceil" => Some(f64::ceil),
+            "floor" => Some(f64::floor),
+            _ => None,
+        };
+        if let Some(unary) = unary {
+            return Ok(unary(self.term()?));
+        }
+        if token == "@" {
+            let addr = self.term()?;
+            return Ok((self.peek)(addr as usize));
+        }
+
+        if let Some(value) = parse_number(&token) {
+            return Ok(value as f64);
+        }
+        return match token.as_str() {
+            "PI" => Ok(std::f64::consts::PI),
+            "E" => Ok(std::f64::consts::E),
+            name => (self.lookup)(name).ok_or_else(|| format!("undefined name `{}` in expression", name)),
+        };
+    }
+}
+
+/// Decimal, `0x` hex or `0b` binary, optionally negative
+pub fn parse_number(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(bin) = digits.strip_prefix("0b").or_else(|| digits.strip_prefix("0B")) {
+        i64::from_str_radix(bin, 2).ok()?
+    } else {
+        if !digits.starts_with(|c: char| c.is_ascii_digit()) { return None; }
+        digits.parse().ok()?
+    };
+    return Some(if negative { -value } else { value });
+}
@@ -0,0 +1,638 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use crate::constants::START_ADDR;
+use super::calc::{self, parse_number};
+use super::lexer::Token;
+use super::{CompileError, Program};
+
+type Result<T> = std::result::Result<T, CompileError>;
+
+/// Most macros expanding within each other, to catch recursive ones
+const MAX_MACRO_DEPTH: usize = 64;
+
+/// Bytes to patch once a forward-referenced label is known
+#[derive(Debug, Clone, Copy)]
+enum Fixup {
+    /// 12-bit address in the low bits of the opcode at `pos`
+    Addr(u16),
+    /// 16-bit address at `pos` (after `i := long`)
+    Long(u16),
+    /// `:unpack`: `nibble` in the high bits and the address' top nibble in the low bits of the byte at `pos`
+    UnpackHi(u16, u8),
+    /// `:unpack`: low byte of the address at `pos`
+    UnpackLo(u16),
+}
+
+enum Control {
+    /// `if ... begin`, with the jump over the body to patch
+    If(u16),
+    /// `else`, with the jump over the else body to patch
+    Else(u16),
+    /// `loop`, with the jumps out of it from `while`s to patch
+    Loop(u16, Vec<u16>),
+}
+
+struct Macro {
+    args: Vec<String>,
+    body: Vec<Token>,
+}
+
+/// Condition a skip instruction can test
+#[derive(Clone, Copy)]
+enum Test {
+    EqImm(u16, u8),
+    NeImm(u16, u8),
+    EqReg(u16, u16),
+    NeReg(u16, u16),
+    Key(u16),
+    NoKey(u16),
+}
+impl Test {
+    /// Opcode that skips the next instruction when the test holds
+    fn skip_when(self) -> u16 {
+        return match self {
+            Test::EqImm(x, n) => 0x3000 | x << 8 | n as u16,
+            Test::NeImm(x, n) => 0x4000 | x << 8 | n as u16,
+            Test::EqReg(x, y) => 0x5000 | x << 8 | y << 4,
+            Test::NeReg(x, y) => 0x9000 | x << 8 | y << 4,
+            Test::Key(x) => 0xE09E | x << 8,
+            Test::NoKey(x) => 0xE0A1 | x << 8,
+        };
+    }
+
+    fn negate(self) -> Test {
+        return match self {
+            Test::EqImm(x, n) => Test::NeImm(x, n),
+            Test::NeImm(x, n) => Test::EqImm(x, n),
+            Test::EqReg(x, y) => Test::NeReg(x, y),
+            Test::NeReg(x, y) => Test::EqReg(x, y),
+            Test::Key(x) => Test::NoKey(x),
+            Test::NoKey(x) => Test::Key(x),
+        };
+    }
+}
+
+pub(super) struct Compiler {
+    tokens: VecDeque<Token>,
+    line: usize,
+    depth: usize, // Macro expansion depth of the last token
+    rom: Vec<u8>,
+    here: u16,
+    labels: BTreeMap<String, u16>,
+    constants: BTreeMap<String, f64>,
+    aliases: HashMap<String, u16>,
+    macros: HashMap<String, Macro>,
+    fixups: Vec<(Fixup, String, usize)>,
+    control: Vec<Control>,
+    breakpoints: BTreeMap<u16, String>,
+}
+impl Compiler {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        let aliases = HashMap::from([
+            ("compare-temp".to_string(), 0xF),
+            ("unpack-hi".to_string(), 0x0),
+            ("unpack-lo".to_string(), 0x1),
+        ]);
+        return Self {
+            tokens: tokens.into(),
+            line: 1,
+            depth: 0,
+            rom: Vec::new(),
+            here: START_ADDR,
+            labels: BTreeMap::new(),
+            constants: BTreeMap::new(),
+            aliases,
+            macros: HashMap::new(),
+            fixups: Vec::new(),
+            control: Vec::new(),
+            breakpoints: BTreeMap::new(),
+        };
+    }
+
+    pub fn compile(mut self) -> Result<Program> {
+        // Reserved for the jump to `main`
+        self.emit(0x0000)?;
+
+        while !self.tokens.is_empty() {
+            self.statement()?;
+        }
+
+        if let Some(open) = self.control.last() {
+            return Err(self.error(match open {
+                Control::If(_) | Control::Else(_) => "`begin` without a matching `end`",
+                Control::Loop(..) => "`loop` without a matching `again`",
+            }));
+        }
+        let main = *self.labels.get("main").ok_or_else(|| self.error("missing `main` label"))?;
+        self.write(START_ADDR, 0x1000 | main);
+
+        for (fixup, label, line) in std::mem::take(&mut self.fixups) {
+            let Some(&addr) = self.labels.get(&label) else {
+                return Err(CompileError { line, message: format!("undefined label `{}`", label) });
+            };
+            match fixup {
+                Fixup::Addr(pos) => {
+                    let opcode = (self.read(pos) as u16) << 8 | self.read(pos + 1) as u16;
+                    self.write(pos, opcode | (addr & 0x0FFF));
+                },
+                Fixup::Long(pos) => self.write(pos, addr),
+                Fixup::UnpackHi(pos, nibble) => self.rom[(pos - START_ADDR) as usize] = nibble << 4 | (addr >> 8) as u8,
+                Fixup::UnpackLo(pos) => self.rom[(pos - START_ADDR) as usize] = addr as u8,
+            }
+        }
+
+        return Ok(Program {
+            rom: self.rom,
+            labels: self.labels,
+            constants: self.constants,
+            breakpoints: self.breakpoints,
+        });
+    }
+
+    // ============ //
+    // == OUTPUT == //
+    // ============ //
+
+    fn emit_byte(&mut self, byte: u8) -> Result<()> {
+        if self.here < START_ADDR { return Err(self.error("can't emit code below 0x200")); }
+        let next = self.after_here(1)?;
+        let idx = (self.here - START_ADDR) as usize;
+        if idx >= self.rom.len() { self.rom.resize(idx + 1, 0); }
+        self.rom[idx] = byte;
+        self.here = next;
+        return Ok(());
+    }
+
+    /// The address `offset` bytes on from `here`
+    fn after_here(&self, offset: u16) -> Result<u16> {
+        return self.here.checked_add(offset).ok_or_else(|| self.error("program runs past 0xFFFF"));
+    }
+
+    fn emit(&mut self, opcode: u16) -> Result<()> {
+        self.emit_byte((opcode >> 8) as u8)?;
+        return self.emit_byte(opcode as u8);
+    }
+
+    fn read(&self, addr: u16) -> u8 {
+        return self.rom[(addr - START_ADDR) as usize];
+    }
+
+    fn write(&mut self, addr: u16, opcode: u16) {
+        let idx = (addr - START_ADDR) as usize;
+        self.rom[idx] = (opcode >> 8) as u8;
+        self.rom[idx + 1] = opcode as u8;
+    }
+
+    // ============ //
+    // == TOKENS == //
+    // ============ //
+
+    fn error(&self, message: &str) -> CompileError {
+        return CompileError { line: self.line, message: message.to_string() };
+    }
+
+    fn next(&mut self) -> Result<String> {
+        let token = self.tokens.pop_front().ok_or_else(|| self.error("unexpected end of file"))?;
+        self.line = token.line;
+        self.depth = token.depth;
+        return Ok(token.text);
+    }
+
+    fn peek(&self) -> Option<&str> {
+        return self.tokens.front().map(|t| t.text.as_str());
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<()> {
+        let token = self.next()?;
+        if token != expected {
+            return Err(self.error(&format!("expected `{}`, found `{}`", expected, token)));
+        }
+        return Ok(());
+    }
+
+    fn register_of(&self, text: &str) -> Option<u16> {
+        if let Some(reg) = self.aliases.get(text) { return Some(*reg); }
+        let digit = text.strip_prefix('v').or_else(|| text.strip_prefix('V'))?;
+        if digit.len() != 1 { return None; }
+        return u16::from_str_radix(digit, 16).ok();
+    }
+
+    fn register(&mut self) -> Result<u16> {
+        let token = self.next()?;
+        return self.register_of(&token).ok_or_else(|| self.error(&format!("expected a register, found `{}`", token)));
+    }
+
+    /// Tokens of a `{ ... }` block, the opening brace having been consumed
+    fn block(&mut self) -> Result<Vec<Token>> {
+        let mut depth = 1;
+        let mut body = Vec::new();
+        loop {
+            let token = self.tokens.pop_front().ok_or_else(|| self.error("unterminated `{`"))?;
+            match token.text.as_str() {
+                "{" => depth += 1,
+                "}" => {
+                    depth -= 1;
+                    if depth == 0 { return Ok(body); }
+                },
+                _ => {},
+            }
+            body.push(token);
+        }
+    }
+
+    /// Evaluate a `{ ... }` expression, the opening brace having been consumed
+    fn calc(&mut self) -> Result<f64> {
+        let tokens = self.block()?;
+        let lookup = |name: &str| -> Option<f64> {
+            if name == "HERE" { return Some(self.here as f64); }
+            if let Some(value) = self.constants.get(name) { return Some(*value); }
+            return self.labels.get(name).map(|addr| *addr as f64);
+        };
+        let peek = |addr: usize| -> f64 {
+            let idx = addr.wrapping_sub(START_ADDR as usize);
+            return self.rom.get(idx).copied().unwrap_or(0) as f64;
+        };
+        return calc::evaluate(&tokens, &lookup, &peek).map_err(|e| self.error(&e));
+    }
+
+    /// A number, constant, defined label or `{ ... }` expression
+    fn value_of(&mut self, token: &str) -> Result<Option<f64>> {
+        if token == "{" { return Ok(Some(self.calc()?)); }
+        if let Some(n) = parse_number(token) { return Ok(Some(n as f64)); }
+        if let Some(value) = self.constants.get(token) { return Ok(Some(*value)); }
+        return Ok(self.labels.get(token).map(|addr| *addr as f64));
+    }
+
+    fn value(&mut self) -> Result<f64> {
+        let token = self.next()?;
+        return self.value_of(&token)?.ok_or_else(|| self.error(&format!("expected a number, found `{}`", token)));
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        let value = self.value()?.floor();
+        if !(-128.0..=255.0).contains(&value) {
+            return Err(self.error(&format!("value {} doesn't fit in a byte", value)));
+        }
+        return Ok(value as i64 as u8);
+    }
+
+    fn nibble(&mut self) -> Result<u16> {
+        let value = self.value()?.floor();
+        if !(0.0..=15.0).contains(&value) {
+            return Err(self.error(&format!("value {} doesn't fit in a nibble", value)));
+        }
+        return Ok(value as u16);
+    }
+
+    /// Address operand: a value, or a label that may be defined later
+    fn address(&mut self, fixup: Fixup) -> Result<Option<u16>> {
+        let token = self.next()?;
+        if let Some(value) = self.value_of(&token)? {
+            return Ok(Some(value as u16));
+        }
+        if !is_identifier(&token) {
+            return Err(self.error(&format!("expected an address, found `{}`", token)));
+        }
+        self.fixups.push((fixup, token, self.line));
+        return Ok(None);
+    }
+
+    /// Emit `base | NNN`
+    fn emit_address(&mut self, base: u16) -> Result<()> {
+        let addr = self.address(Fixup::Addr(self.here))?.unwrap_or(0);
+        if addr > 0xFFF { return Err(self.error(&format!("address {:#X} is out of range", addr))); }
+        return self.emit(base | addr);
+    }
+
+    fn define_label(&mut self, name: String, addr: u16) -> Result<()> {
+        if !is_identifier(&name) { return Err(self.error(&format!("invalid label name `{}`", name))); }
+        if self.labels.insert(name.clone(), addr).is_some() {
+            return Err(self.error(&format!("label `{}` is already defined", name)));
+        }
+        return Ok(());
+    }
+
+    // ================ //
+    // == STATEMENTS == //
+    // ================ //
+
+    fn statement(&mut self) -> Result<()> {
+        let token = self.next()?;
+        match token.as_str() {
+            ":" => {
+                let name = self.next()?;
+                self.define_label(name, self.here)?;
+            },
+            ":next" => {
+                // Labels the operand byte of the next instruction, for self-modifying code
+                let name = self.next()?;
+                self.define_label(name, self.after_here(1)?)?;
+            },
+            ":const" => {
+                let name = self.next()?;
+                let value = self.value()?;
+                self.constants.insert(name, value);
+            },
+            ":calc" => {
+                let name = self.next()?;
+                self.expect("{")?;
+                let value = self.calc()?;
+                self.constants.insert(name, value);
+            },
+            ":alias" => {
+                let name = self.next()?;
+                let reg = match self.next()?.as_str() {
+                    "{" => self.calc()? as u16,
+                    other => self.register_of(other).ok_or_else(|| self.error("expected a register"))?,
+                };
+                if reg > 0xF { return Err(self.error("alias must be a register")); }
+                self.aliases.insert(name, reg);
+            },
+            ":macro" => {
+                let name = self.next()?;
+                let mut args = Vec::new();
+                loop {
+                    let arg = self.next()?;
+                    if arg == "{" { break; }
+                    args.push(arg);
+                }
+                let body = self.block()?;
+                self.macros.insert(name, Macro { args, body });
+            },
+            ":unpack" => {
+                let nibble = match self.peek() {
+                    Some("long") => { self.next()?; 0 },
+                    _ => self.nibble()? as u8,
+                };
+                let (hi, lo) = (self.aliases["unpack-hi"], self.aliases["unpack-lo"]);
+                let (hi_pos, lo_pos) = (self.after_here(1)?, self.after_here(3)?);
+                let addr = self.address(Fixup::UnpackHi(hi_pos, nibble))?;
+                if addr.is_none() {
+                    let (_, label, line) = self.fixups.last().unwrap().clone();
+                    self.fixups.push((Fixup::UnpackLo(lo_pos), label, line));
+                }
+                let addr = addr.unwrap_or(0);
+                self.emit(0x6000 | hi << 8 | (nibble as u16) << 4 | addr >> 8)?;
+                self.emit(0x6000 | lo << 8 | (addr & 0xFF))?;
+            },
+            ":org" => {
+                let addr = self.value()?;
+                self.here = u16::try_from(addr as i64).map_err(|_| self.error(&format!("`:org` address {} out of range", addr)))?;
+            },
+            ":byte" => {
+                let byte = self.byte()?;
+                self.emit_byte(byte)?;
+            },
+            ":call" => self.emit_address(0x2000)?,
+            ":breakpoint" => {
+                let name = self.next()?;
+                self.breakpoints.insert(self.here, name);
+            },
+            ":monitor" => {
+                // Debugger hint for Octo's IDE, no code
+                self.next()?;
+                self.next()?;
+            },
+            ":assert" => {
+                self.expect("{")?;
+                if self.calc()? == 0.0 { return Err(self.error("assertion failed")); }
+            },
+
+            "clear" => self.emit(0x00E0)?,
+            "return" | ";" => self.emit(0x00EE)?,
+            "jump" => self.emit_address(0x1000)?,
+            "jump0" => self.emit_address(0xB000)?,
+            "native" => self.emit_address(0x0000)?,
+            "bcd" => { let x = self.register()?; self.emit(0xF033 | x << 8)?; },
+            "save" => self.register_range(0xF055, 0x5002)?,
+            "load" => self.register_range(0xF065, 0x5003)?,
+            "sprite" => {
+                let x = self.register()?;
+                let y = self.register()?;
+                let n = self.nibble()?;
+                self.emit(0xD000 | x << 8 | y << 4 | n)?;
+            },
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let x = self.register()?;
+                let low = match token.as_str() { "delay" => 0x15, "buzzer" => 0x18, _ => 0x3A };
+                self.emit(0xF000 | x << 8 | low)?;
+            },
+            "i" => self.index()?,
+
+            // SCHIP
+            "hires" => self.emit(0x00FF)?,
+            "lores" => self.emit(0x00FE)?,
+            "exit" => self.emit(0x00FD)?,
+            "scroll-down" => { let n = self.nibble()?; self.emit(0x00C0 | n)?; },
+            "scroll-right" => self.emit(0x00FB)?,
+            "scroll-left" => self.emit(0x00FC)?,
+            "saveflags" => { let x = self.register()?; self.emit(0xF075 | x << 8)?; },
+            "loadflags" => { let x = self.register()?; self.emit(0xF085 | x << 8)?; },
+
+            // XO-CHIP
+            "scroll-up" => { let n = self.nibble()?; self.emit(0x00D0 | n)?; },
+            "plane" => { let n = self.nibble()?; self.emit(0xF001 | n << 8)?; },
+            "audio" => self.emit(0xF002)?,
+
+            "if" => {
+                let test = self.condition()?;
+                match self.next()?.as_str() {
+                    "then" => self.emit(test.negate().skip_when())?,
+                    "begin" => {
+                        self.emit(test.skip_when())?;
+                        self.control.push(Control::If(self.here));
+                        self.emit(0x1000)?;
+                    },
+                    other => return Err(self.error(&format!("expected `then` or `begin`, found `{}`", other))),
+                }
+            },
+            "else" => {
+                let Some(Control::If(jump)) = self.control.pop() else { return Err(self.error("`else` without `if ... begin`")); };
+                let pos = self.here;
+                self.emit(0x1000)?;
+                self.write(jump, 0x1000 | self.here);
+                self.control.push(Control::Else(pos));
+            },
+            "end" => {
+                let jump = match self.control.pop() {
+                    Some(Control::If(jump) | Control::Else(jump)) => jump,
+                    _ => return Err(self.error("`end` without `if ... begin`")),
+                };
+                self.write(jump, 0x1000 | self.here);
+            },
+            "loop" => self.control.push(Control::Loop(self.here, Vec::new())),
+            "while" => {
+                let test = self.condition()?;
+                self.emit(test.skip_when())?;
+                let pos = self.here;
+                self.emit(0x1000)?;
+                let exits = self.control.iter_mut().rev().find_map(|c| match c {
+                    Control::Loop(_, exits) => Some(exits),
+                    _ => None,
+                });
+                match exits {
+                    Some(exits) => exits.push(pos),
+                    None => return Err(self.error("`while` outside of a loop")),
+                }
+            },
+            "again" => {
+                let Some(Control::Loop(start, exits)) = self.control.pop() else { return Err(self.error("`again` without `loop`")); };
+                self.emit(0x1000 | start)?;
+                for exit in exits {
+                    self.write(exit, 0x1000 | self.here);
+                }
+            },
+
+            _ if self.register_of(&token).is_some() => {
+                let x = self.register_of(&token).unwrap();
+                self.assignment(x)?;
+            },
+            _ if self.macros.contains_key(&token) => self.expand(&token)?,
+            _ if token == "{" || parse_number(&token).is_some() || self.constants.contains_key(&token) => {
+                // Raw data
+                let value = self.value_of(&token)?.unwrap().floor();
+                if !(-128.0..=255.0).contains(&value) {
+                    return Err(self.error(&format!("value {} doesn't fit in a byte", value)));
+                }
+                self.emit_byte(value as i64 as u8)?;
+            },
+            _ if token.starts_with(':') => return Err(self.error(&format!("unknown directive `{}`", token))),
+            _ if is_identifier(&token) => {
+                // Calling a subroutine by name
+                self.tokens.push_front(Token { text: token, line: self.line, depth: self.depth });
+                self.emit_address(0x2000)?;
+            },
+            _ => return Err(self.error(&format!("unexpected `{}`", token))),
+        }
+        return Ok(());
+    }
+
+    /// `save vX` / `save vX - vY`
+    fn register_range(&mut self, single: u16, range: u16) -> Result<()> {
+        let x = self.register()?;
+        if self.peek() == Some("-") {
+            self.next()?;
+            let y = self.register()?;
+            return self.emit(range | x << 8 | y << 4);
+        }
+        return self.emit(single | x << 8);
+    }
+
+    fn index(&mut self) -> Result<()> {
+        match self.next()?.as_str() {
+            ":=" => match self.peek() {
+                Some("hex") => { self.next()?; let x = self.register()?; self.emit(0xF029 | x << 8)?; },
+                Some("bighex") => { self.next()?; let x = self.register()?; self.emit(0xF030 | x << 8)?; },
+                Some("long") => {
+                    self.next()?;
+                    self.emit(0xF000)?;
+                    let addr = self.address(Fixup::Long(self.here))?.unwrap_or(0);
+                    self.emit(addr)?;
+                },
+                _ => self.emit_address(0xA000)?,
+            },
+            "+=" => { let x = self.register()?; self.emit(0xF01E | x << 8)?; },
+            other => return Err(self.error(&format!("unexpected `{}` after `i`", other))),
+        }
+        return Ok(());
+    }
+
+    fn assignment(&mut self, x: u16) -> Result<()> {
+        let op = self.next()?;
+        let rhs = self.peek().and_then(|t| self.register_of(t));
+        let reg_op = |low: u16, rhs: Option<u16>| rhs.map(|y| 0x8000 | x << 8 | y << 4 | low);
+
+        let opcode = match op.as_str() {
+            ":=" => match self.peek() {
+                Some("random") => { self.next()?; 0xC000 | x << 8 | self.byte()? as u16 },
+                Some("delay") => { self.next()?; 0xF007 | x << 8 },
+                Some("key") => { self.next()?; 0xF00A | x << 8 },
+                _ => match reg_op(0x0, rhs) {
+                    Some(opcode) => { self.next()?; opcode },
+                    None => 0x6000 | x << 8 | self.byte()? as u16,
+                },
+            },
+            "+=" => match reg_op(0x4, rhs) {
+                Some(opcode) => { self.next()?; opcode },
+                None => 0x7000 | x << 8 | self.byte()? as u16,
+            },
+            "-=" => match reg_op(0x5, rhs) {
+                Some(opcode) => { self.next()?; opcode },
+                None => 0x7000 | x << 8 | self.byte()?.wrapping_neg() as u16,
+            },
+            "=-" | "|=" | "&=" | "^=" | ">>=" | "<<=" => {
+                let low = match op.as_str() { "=-" => 0x7, "|=" => 0x1, "&=" => 0x2, "^=" => 0x3, ">>=" => 0x6, _ => 0xE };
+                let y = self.register()?;
+                0x8000 | x << 8 | y << 4 | low
+            },
+            other => return Err(self.error(&format!("unknown operator `{}`", other))),
+        };
+        return self.emit(opcode);
+    }
+
+    /// Parse a condition, emitting any setup it needs (comparisons go through `VF`)
+    fn condition(&mut self) -> Result<Test> {
+        let x = self.register()?;
+        let op = self.next()?;
+        match op.as_str() {
+            "key" => return Ok(Test::Key(x)),
+            "-key" => return Ok(Test::NoKey(x)),
+            _ => {},
+        }
+
+        let rhs = self.peek().and_then(|t| self.register_of(t));
+        let rhs = match rhs {
+            Some(y) => { self.next()?; Ok(y) },
+            None => Err(self.byte()?),
+        };
+        return match (op.as_str(), rhs) {
+            ("==", Ok(y)) => Ok(Test::EqReg(x, y)),
+            ("==", Err(n)) => Ok(Test::EqImm(x, n)),
+            ("!=", Ok(y)) => Ok(Test::NeReg(x, y)),
+            ("!=", Err(n)) => Ok(Test::NeImm(x, n)),
+            ("<" | ">" | "<=" | ">=", rhs) => {
+                // vf := rhs, then subtract so the borrow flag answers the comparison
+                match rhs {
+                    Ok(y) => self.emit(0x8F00 | y << 4)?,
+                    Err(n) => self.emit(0x6F00 | n as u16)?,
+                }
+                match op.as_str() {
+                    "<" | ">=" => self.emit(0x8F07 | x << 4)?, // vf = vx - rhs
+                    _ => self.emit(0x8F05 | x << 4)?, // vf = rhs - vx
+                }
+                match op.as_str() {
+                    "<" | ">" => Ok(Test::EqImm(0xF, 0)),
+                    _ => Ok(Test::NeImm(0xF, 0)),
+                }
+            },
+            _ => Err(self.error(&format!("unknown comparison `{}`", op))),
+        };
+    }
+
+    fn expand(&mut self, name: &str) -> Result<()> {
+        let depth = self.depth + 1;
+        if depth > MAX_MACRO_DEPTH {
+            return Err(self.error(&format!("macro `{}` nested more than {} deep, is it recursive?", name, MAX_MACRO_DEPTH)));
+        }
+        let (params, body) = {
+            let mac = &self.macros[name];
+            (mac.args.clone(), mac.body.clone())
+        };
+        let mut args = HashMap::new();
+        for param in params {
+            let arg = self.next()?;
+            args.insert(param, arg);
+        }
+        for token in body.into_iter().rev() {
+            let text = args.get(&token.text).cloned().unwrap_or(token.text);
+            self.tokens.push_front(Token { text, line: self.line, depth });
+        }
+        return Ok(());
+    }
+}
+
+fn is_identifier(text: &str) -> bool {
+    return text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+}
@@ -0,0 +1,36 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub text: String,
+    pub line: usize,
+    /// How many macro expansions produced the token, 0 if it's from the source
+    pub depth: usize,
+}
+
+/// Split Octo source into whitespace separated tokens
+/// `#` starts a comment running to the end of the line.
+/// Braces and parentheses are always tokens of their own, so `{x}` works as well as `{ x }`
+pub fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    for (idx, line) in source.lines().enumerate() {
+        let line_no = idx + 1;
+        let mut current = String::new();
+        let flush = |current: &mut String, tokens: &mut Vec<Token>| {
+            if !current.is_empty() {
+                tokens.push(Token { text: std::mem::take(current), line: line_no, depth: 0 });
+            }
+        };
+        for c in line.chars() {
+            match c {
+                '#' if current.is_empty() => break,
+                c if c.is_whitespace() => flush(&mut current, &mut tokens),
+                '{' | '}' | '(' | ')' => {
+                    flush(&mut current, &mut tokens);
+                    tokens.push(Token { text: c.to_string(), line: line_no, depth: 0 });
+                },
+                c => current.push(c),
+            }
+        }
+        flush(&mut current, &mut tokens);
+    }
+    return tokens;
+}
@@ -0,0 +1,58 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+mod lexer;
+mod calc;
+mod compiler;
+
+/// A compiled Octo program
+#[derive(Debug, Clone, Default)]
+pub struct Program {
+    /// ROM bytes, to be loaded at `START_ADDR` with `Emu::load_rom`
+    pub rom: Vec<u8>,
+    /// Address of every label
+    pub labels: BTreeMap<String, u16>,
+    /// Value of every `:const` and `:calc`
+    pub constants: BTreeMap<String, f64>,
+    /// Addresses marked with `:breakpoint`, by name
+    pub breakpoints: BTreeMap<u16, String>,
+}
+impl Program {
+    /// Name of the label at `addr`, if there is one
+    pub fn label_at(&self, addr: u16) -> Option<&str> {
+        return self.labels.iter().find(|(_, a)| **a == addr).map(|(name, _)| name.as_str());
+    }
+
+    /// Symbol table, one `ADDR name` line per label sorted by address
+    pub fn symbol_table(&self) -> String {
+        let mut symbols: Vec<_> = self.labels.iter().collect();
+        symbols.sort_by_key(|(name, addr)| (**addr, name.as_str()));
+        let mut out = String::new();
+        for (name, addr) in symbols {
+            out.push_str(&format!("{:03X} {}\n", addr, name));
+        }
+        return out;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileError {
+    pub line: usize,
+    pub message: String,
+}
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "line {}: {}", self.line, self.message);
+    }
+}
+impl std::error::Error for CompileError {}
+
+/// Compile Octo source into a ROM
+///
+/// Supports labels, `:const`, `:alias`, `:macro`, `:calc`, `:unpack`, `:next`, `:org`,
+/// `:byte`, `:call`, `:breakpoint`, `:assert`, structured `if`/`loop`/`while`,
+/// the SCHIP instructions and the XO-CHIP extensions.
+/// Like Octo, execution starts with a jump to the `main` label.
+pub fn compile(source: &str) -> Result<Program, CompileError> {
+    return compiler::Compiler::new(lexer::tokenize(source)).compile();
+}
@@ -0,0 +1,106 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::num::NonZeroU8;
+
+use crate::constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::Framebuffer;
+
+/// Brightness of a fully lit pixel
+pub const FULL: u8 = 255;
+
+/// How successive frames are combined to hide the flicker of sprites erased and redrawn with XOR
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PhosphorMode {
+    /// Only the latest frame
+    #[default]
+    Off,
+    /// A pixel is lit if it was lit in either of the last two frames
+    Blend,
+    /// Lit pixels fade out linearly over `frames` frames once turned off
+    Decay(NonZeroU8),
+}
+impl PhosphorMode {
+    /// Parse a mode as written by `Display`: `off`, `blend` or `decay N`
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut words = text.split_whitespace();
+        let mode = match words.next()? {
+            "off" => Self::Off,
+            "blend" => Self::Blend,
+            "decay" => Self::Decay(words.next()?.parse().ok()?),
+            _ => return None,
+        };
+        if words.next().is_some() { return None; }
+        return Some(mode);
+    }
+}
+impl fmt::Display for PhosphorMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            Self::Off => write!(f, "off"),
+            Self::Blend => write!(f, "blend"),
+            Self::Decay(frames) => write!(f, "decay {}", frames),
+        };
+    }
+}
+
+/// Post-processes successive frames into per-pixel intensities
+///
+/// Feed it the framebuffer once per frame (after `Emu::run_frame`) and draw `intensity()`
+/// instead of the raw pixels.
+#[derive(Debug, Clone)]
+pub struct Phosphor {
+    mode: PhosphorMode,
+    ages: Vec<u8>, // Frames since each pixel was last lit, saturating, row by row
+    intensity: Vec<u8>, // One value per pixel, row by row
+}
+impl Phosphor {
+    pub fn new(mode: PhosphorMode) -> Self {
+        return Self {
+            mode,
+            ages: vec![u8::MAX; SCREEN_WIDTH * SCREEN_HEIGHT],
+            intensity: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+        };
+    }
+
+    pub fn mode(&self) -> PhosphorMode {
+        return self.mode;
+    }
+
+    /// Switch modes, forgetting previous frames
+    pub fn set_mode(&mut self, mode: PhosphorMode) {
+        self.mode = mode;
+        self.reset();
+    }
+
+    /// Forget previous frames (e.g. after loading a ROM)
+    pub fn reset(&mut self) {
+        self.ages.fill(u8::MAX);
+        self.intensity.fill(0);
+    }
+
+    /// Add the next frame, updating the intensities
+    pub fn push(&mut self, frame: &Framebuffer) {
+        for y in 0..SCREEN_HEIGHT {
+            for x in 0..SCREEN_WIDTH {
+                let i = y * SCREEN_WIDTH + x;
+                let age = if frame.pixel(x, y) { 0 } else { self.ages[i].saturating_add(1) };
+                self.ages[i] = age;
+                self.intensity[i] = match self.mode {
+                    _ if age == 0 => FULL,
+                    PhosphorMode::Off => 0,
+                    PhosphorMode::Blend => if age == 1 { FULL } else { 0 },
+                    PhosphorMode::Decay(frames) => {
+                        let left = frames.get().saturating_sub(age) as u32;
+                        (FULL as u32 * left / frames.get() as u32) as u8
+                    }
+                };
+            }
+        }
+    }
+
+    /// Brightness of each pixel (0 to `FULL`), row by row
+    pub fn intensity(&self) -> &[u8] {
+        return &self.intensity;
+    }
+}
@@ -0,0 +1,96 @@
+use alloc::boxed::Box;
+use core::fmt;
+
+/// Source of random numbers for `Rand`
+/// Set one with `Emu::set_random`, the default is a `Xorshift`
+pub trait Random: fmt::Debug + Send + Sync {
+    fn next_u8(&mut self) -> u8;
+
+    /// A copy with the same state, producing the same numbers from here on
+    /// Used to capture the generator in a `Snapshot`
+    fn duplicate(&self) -> Box<dyn Random>;
+
+    /// State for a save state to store, `None` if the generator can't be saved
+    /// Loading the save state continues from it with `Xorshift::from_state`, see `Snapshot::to_bytes`
+    fn save_state(&self) -> Option<u64> {
+        return None;
+    }
+}
+
+/// Sound output, set one with `Emu::set_beeper`
+pub trait Beeper: fmt::Debug + Send + Sync {
+    /// Called when the sound timer runs out
+    fn beep(&mut self);
+}
+
+/// Small, fast xorshift64* generator, the default `Random`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Xorshift {
+    state: u64,
+}
+impl Xorshift {
+    pub fn new(seed: u64) -> Self {
+        // Scramble the seed (splitmix64) so similar seeds give unrelated sequences,
+        // and avoid zero, which xorshift never leaves
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        return Self { state: if z == 0 { 1 } else { z } };
+    }
+
+    /// Continue from a state from `Random::save_state`
+    /// `None` for zero, which isn't a state xorshift can be in.
+    pub fn from_state(state: u64) -> Option<Self> {
+        return (state != 0).then_some(Self { state });
+    }
+
+    /// Seeded from the OS with `std`
+    /// Without it there's no entropy to use, so the sequence is the same every run
+    pub fn from_entropy() -> Self {
+        #[cfg(feature = "std")]
+        return Self::new(rand::random());
+        #[cfg(not(feature = "std"))]
+        return Self::new(0);
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        return x.wrapping_mul(0x2545_F491_4F6C_DD1D);
+    }
+}
+impl Random for Xorshift {
+    fn next_u8(&mut self) -> u8 {
+        // The high bits are the best quality
+        return (self.next_u64() >> 56) as u8;
+    }
+
+    fn duplicate(&self) -> Box<dyn Random> {
+        return Box::new(*self);
+    }
+
+    fn save_state(&self) -> Option<u64> {
+        return Some(self.state);
+    }
+}
+
+/// The default `Beeper`: prints "BEEP!" with `std`, silent without
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultBeeper;
+impl Beeper for DefaultBeeper {
+    fn beep(&mut self) {
+        #[cfg(feature = "std")]
+        println!("BEEP!");
+    }
+}
+
+/// A `Beeper` that ignores beeps, for hosts that poll the sound timer instead
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Silent;
+impl Beeper for Silent {
+    fn beep(&mut self) {}
+}
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::constants::START_ADDR;
+use crate::Opcode;
+
+/// Execution profiler
+///
+/// Counts executions per address, per `Opcode` variant and per subroutine.
+/// Subroutines are tracked with a shadow call stack that follows `Call` and `Return`,
+/// so every executed instruction is charged to the subroutine it ran in (exclusive)
+/// and to every subroutine on the stack at the time (inclusive).
+#[derive(Debug, Clone, Default)]
+pub struct Profiler {
+    addresses: HashMap<u16, AddressStats>,
+    opcodes: HashMap<&'static str, u64>,
+    subroutines: HashMap<u16, SubroutineStats>,
+    folded: HashMap<Vec<u16>, u64>,
+    call_stack: Vec<u16>,
+    total: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AddressStats {
+    pub count: u64,
+    /// The last opcode executed at this address (for disassembly)
+    pub opcode: Opcode,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SubroutineStats {
+    /// Number of times the subroutine was called
+    pub calls: u64,
+    /// Instructions executed in the subroutine itself
+    pub exclusive: u64,
+    /// Instructions executed in the subroutine and everything it called
+    pub inclusive: u64,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        return Self {
+            call_stack: vec![START_ADDR],
+            ..Default::default()
+        };
+    }
+
+    /// Record the execution of `opcode` at `addr`
+    pub fn record(&mut self, addr: u16, opcode: Opcode) {
+        if self.call_stack.is_empty() { self.call_stack.push(START_ADDR); }
+        self.total += 1;
+
+        let stats = self.addresses.entry(addr).or_insert(AddressStats { count: 0, opcode });
+        stats.count += 1;
+        stats.opcode = opcode;
+
+        *self.opcodes.entry(opcode.name()).or_insert(0) += 1;
+
+        // Charge the instruction to the current subroutine...
+        let current = *self.call_stack.last().unwrap();
+        self.subroutines.entry(current).or_default().exclusive += 1;
+        // ...and to every distinct subroutine on the stack, so recursion isn't counted twice
+        for (i, sub) in self.call_stack.iter().enumerate() {
+            if self.call_stack[..i].contains(sub) { continue; }
+            self.subroutines.entry(*sub).or_default().inclusive += 1;
+        }
+        *self.folded.entry(self.call_stack.clone()).or_insert(0) += 1;
+
+        match opcode {
+            Opcode::Call(target) => {
+                self.subroutines.entry(target).or_default().calls += 1;
+                self.call_stack.push(target);
+            },
+            // Never pop the entry point, a ROM may return without calling
+            Opcode::Return if self.call_stack.len() > 1 => {
+                self.call_stack.pop();
+            },
+            _ => {},
+        }
+    }
+
+    /// Total number of instructions recorded
+    pub fn total(&self) -> u64 {
+        return self.total;
+    }
+
+    pub fn address_stats(&self, addr: u16) -> Option<&AddressStats> {
+        return self.addresses.get(&addr);
+    }
+
+    pub fn subroutine_stats(&self, addr: u16) -> Option<&SubroutineStats> {
+        return self.subroutines.get(&addr);
+    }
+
+    /// Most executed addresses, hottest first
+    pub fn hot_addresses(&self, count: usize) -> Vec<(u16, AddressStats)> {
+        let mut hot: Vec<_> = self.addresses.iter().map(|(a, s)| (*a, *s)).collect();
+        hot.sort_by(|a, b| b.1.count.cmp(&a.1.count).then(a.0.cmp(&b.0)));
+        hot.truncate(count);
+        return hot;
+    }
+
+    /// Execution counts per `Opcode` variant, most executed first
+    pub fn opcode_counts(&self) -> Vec<(&'static str, u64)> {
+        let mut counts: Vec<_> = self.opcodes.iter().map(|(n, c)| (*n, *c)).collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        return counts;
+    }
+
+    /// Per-subroutine statistics, by descending inclusive time
+    pub fn subroutines(&self) -> Vec<(u16, SubroutineStats)> {
+        let mut subs: Vec<_> = self.subroutines.iter().map(|(a, s)| (*a, *s)).collect();
+        subs.sort_by(|a, b| b.1.inclusive.cmp(&a.1.inclusive).then(a.0.cmp(&b.0)));
+        return subs;
+    }
+
+    /// Folded stacks (one `frame;frame;frame count` line per stack),
+    /// as consumed by `flamegraph.pl` and `inferno`
+    pub fn folded_stacks(&self) -> String {
+        let mut lines: Vec<String> = self.folded.iter().map(|(stack, count)| {
+            let frames: Vec<String> = stack.iter().map(|addr| Self::frame_name(*addr)).collect();
+            format!("{} {}", frames.join(";"), count)
+        }).collect();
+        lines.sort();
+
+        let mut out = String::new();
+        for line in lines {
+            out.push_str(&line);
+            out.push('\n');
+        }
+        return out;
+    }
+
+    /// Human readable report with the `top` hottest addresses
+    pub fn report(&self, top: usize) -> String {
+        let mut out = String::new();
+        let percent = |n: u64| if self.total == 0 { 0.0 } else { n as f64 * 100.0 / self.total as f64 };
+
+        writeln!(out, "Total instructions: {}", self.total).unwrap();
+
+        writeln!(out, "\nHot addresses:").unwrap();
+        for (addr, stats) in self.hot_addresses(top) {
+            writeln!(out, "  {:#05X}  {:>10}  {:>6.2}%  {}", addr, stats.count, percent(stats.count), stats.opcode).unwrap();
+        }
+
+        writeln!(out, "\nOpcodes:").unwrap();
+        for (name, count) in self.opcode_counts() {
+            writeln!(out, "  {:<20}  {:>10}  {:>6.2}%", name, count, percent(count)).unwrap();
+        }
+
+        writeln!(out, "\nSubroutines:").unwrap();
+        writeln!(out, "  {:<8}  {:>8}  {:>10}  {:>10}", "addr", "calls", "inclusive", "exclusive").unwrap();
+        for (addr, stats) in self.subroutines() {
+            writeln!(out, "  {:<8}  {:>8}  {:>10}  {:>10}", Self::frame_name(addr), stats.calls, stats.inclusive, stats.exclusive).unwrap();
+        }
+
+        return out;
+    }
+
+    fn frame_name(addr: u16) -> String {
+        if addr == START_ADDR { return "main".to_string(); }
+        return format!("sub_{:03X}", addr);
+    }
+}
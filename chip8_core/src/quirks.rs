@@ -0,0 +1,68 @@
+/// Behaviours that differ between CHIP-8 interpreters
+/// The default is this emulator's original behaviour
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Quirks {
+    /// `OR`, `AND` and `XOR` reset `VF` to 0
+    pub vf_reset: bool,
+    /// `LD [I], VX` and `LD VX, [I]` leave `I` incremented past the last register
+    pub memory_increment: bool,
+    /// Shifts read `VY` rather than shifting `VX` in place
+    pub shift_vy: bool,
+    /// `BNNN` jumps to `XNN + VX` rather than `NNN + V0`
+    pub jump_vx: bool,
+    /// `DrawSprite` waits for the next vblank (see `Emu::set_display_wait`)
+    pub display_wait: bool,
+    /// Sprites are clipped at the edges of the screen instead of wrapping around
+    pub clip: bool,
+}
+impl Quirks {
+    /// The original COSMAC VIP interpreter
+    pub const CHIP8: Self = Self {
+        vf_reset: true,
+        memory_increment: true,
+        shift_vy: true,
+        jump_vx: false,
+        display_wait: true,
+        clip: true,
+    };
+    /// SUPER-CHIP 1.1 on the HP48
+    pub const SUPER_CHIP: Self = Self {
+        vf_reset: false,
+        memory_increment: false,
+        shift_vy: false,
+        jump_vx: true,
+        display_wait: false,
+        clip: true,
+    };
+    /// XO-CHIP, as implemented by Octo
+    pub const XO_CHIP: Self = Self {
+        vf_reset: false,
+        memory_increment: true,
+        shift_vy: true,
+        jump_vx: false,
+        display_wait: false,
+        clip: false,
+    };
+
+    /// Every preset, by name
+    pub const PRESETS: [(&'static str, Self); 4] = [
+        ("default", Self::DEFAULT),
+        ("chip8", Self::CHIP8),
+        ("schip", Self::SUPER_CHIP),
+        ("xochip", Self::XO_CHIP),
+    ];
+
+    const DEFAULT: Self = Self {
+        vf_reset: false,
+        memory_increment: false,
+        shift_vy: false,
+        jump_vx: false,
+        display_wait: false,
+        clip: false,
+    };
+
+    /// Look up a preset by name (see `PRESETS`)
+    pub fn preset(name: &str) -> Option<Self> {
+        return Self::PRESETS.iter().find(|(n, _)| *n == name).map(|(_, quirks)| *quirks);
+    }
+}
@@ -0,0 +1,197 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::Framebuffer;
+
+/// A colour, `[r, g, b, a]`
+pub type Rgba = [u8; 4];
+
+/// Colours indexed by pixel value
+/// CHIP-8 uses the first two (off, on), XO-CHIP's two planes use all four
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub colors: [Rgba; 4],
+}
+impl Palette {
+    /// Black and white
+    pub const MONOCHROME: Self = Self::new([
+        [0x00, 0x00, 0x00, 0xFF], [0xFF, 0xFF, 0xFF, 0xFF],
+        [0xAA, 0xAA, 0xAA, 0xFF], [0x55, 0x55, 0x55, 0xFF],
+    ]);
+    /// Octo's default colours
+    pub const OCTO: Self = Self::new([
+        [0x99, 0x66, 0x00, 0xFF], [0xFF, 0xCC, 0x00, 0xFF],
+        [0xFF, 0x66, 0x00, 0xFF], [0x66, 0x22, 0x00, 0xFF],
+    ]);
+
+    pub const fn new(colors: [Rgba; 4]) -> Self {
+        return Self { colors };
+    }
+
+    /// A CHIP-8 palette, with the other two colours left as in `MONOCHROME`
+    pub const fn two(off: Rgba, on: Rgba) -> Self {
+        let mut palette = Self::MONOCHROME;
+        palette.colors[0] = off;
+        palette.colors[1] = on;
+        return palette;
+    }
+
+    /// Blend between the off and on colours, `intensity` ranging from 0 (off) to 255 (on)
+    pub fn shade(&self, intensity: u8) -> Rgba {
+        let [off, on] = [self.colors[0], self.colors[1]];
+        let mut color = [0; 4];
+        for c in 0..4 {
+            let (off, on, t) = (off[c] as u32, on[c] as u32, intensity as u32);
+            color[c] = ((off * (255 - t) + on * t + 127) / 255) as u8;
+        }
+        return color;
+    }
+}
+impl Default for Palette {
+    fn default() -> Self {
+        return Self::MONOCHROME;
+    }
+}
+
+/// An RGBA image, row by row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Rgba>,
+}
+impl Image {
+    /// Render the screen with the palette's first two colours
+    pub fn from_framebuffer(frame: &Framebuffer, palette: &Palette) -> Self {
+        return Self::from_planes(&[frame], palette);
+    }
+
+    /// Render up to two bit planes, each pixel coloured by the palette entry its bits select
+    /// (plane 0 is the low bit)
+    pub fn from_planes(planes: &[&Framebuffer], palette: &Palette) -> Self {
+        let mut pixels = Vec::with_capacity(SCREEN_WIDTH * SCREEN_HEIGHT);
+        for y in 0..SCREEN_HEIGHT {
+            for x in 0..SCREEN_WIDTH {
+                let index = planes.iter().take(2).enumerate()
+                    .fold(0, |index, (bit, plane)| index | (plane.pixel(x, y) as usize) << bit);
+                pixels.push(palette.colors[index]);
+            }
+        }
+        return Self { width: SCREEN_WIDTH, height: SCREEN_HEIGHT, pixels };
+    }
+
+    /// Render per-pixel intensities (see `Phosphor`) blending between the palette's first two colours
+    pub fn from_intensity(intensity: &[u8], palette: &Palette) -> Self {
+        let pixels = intensity.iter().take(SCREEN_WIDTH * SCREEN_HEIGHT).map(|i| palette.shade(*i)).collect();
+        return Self { width: SCREEN_WIDTH, height: SCREEN_HEIGHT, pixels };
+    }
+
+    /// The pixels as RGBA8 bytes, ready to upload as a texture
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        return self.pixels.iter().flatten().copied().collect();
+    }
+
+    fn get(&self, x: isize, y: isize) -> Rgba {
+        // Clamp to the edges
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        return self.pixels[y * self.width + x];
+    }
+
+    // ============= //
+    // == SCALING == //
+    // ============= //
+
+    /// Nearest neighbour upscaling by a whole number
+    pub fn scale(&self, factor: usize) -> Self {
+        let (width, height) = (self.width * factor, self.height * factor);
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.push(self.pixels[(y / factor) * self.width + x / factor]);
+            }
+        }
+        return Self { width, height, pixels };
+    }
+
+    /// Double the size with the Scale2x (EPX) pixel-art filter, which smooths diagonals
+    pub fn scale2x(&self) -> Self {
+        let mut out = Self::blank(self.width * 2, self.height * 2);
+        for y in 0..self.height as isize {
+            for x in 0..self.width as isize {
+                let [b, d, e, f, h] = [self.get(x, y - 1), self.get(x - 1, y), self.get(x, y), self.get(x + 1, y), self.get(x, y + 1)];
+                let block = if b != h && d != f {
+                    [
+                        if d == b { d } else { e }, if b == f { f } else { e },
+                        if d == h { d } else { e }, if h == f { f } else { e },
+                    ]
+                } else {
+                    [e; 4]
+                };
+                out.put_block(x as usize, y as usize, 2, &block);
+            }
+        }
+        return out;
+    }
+
+    /// Triple the size with the Scale3x pixel-art filter
+    pub fn scale3x(&self) -> Self {
+        let mut out = Self::blank(self.width * 3, self.height * 3);
+        for y in 0..self.height as isize {
+            for x in 0..self.width as isize {
+                let [a, b, c] = [self.get(x - 1, y - 1), self.get(x, y - 1), self.get(x + 1, y - 1)];
+                let [d, e, f] = [self.get(x - 1, y), self.get(x, y), self.get(x + 1, y)];
+                let [g, h, i] = [self.get(x - 1, y + 1), self.get(x, y + 1), self.get(x + 1, y + 1)];
+                let block = if b != h && d != f {
+                    [
+                        if d == b { d } else { e },
+                        if (d == b && e != c) || (b == f && e != a) { b } else { e },
+                        if b == f { f } else { e },
+                        if (d == b && e != g) || (d == h && e != a) { d } else { e },
+                        e,
+                        if (b == f && e != i) || (h == f && e != c) { f } else { e },
+                        if d == h { d } else { e },
+                        if (d == h && e != i) || (h == f && e != g) { h } else { e },
+                        if h == f { f } else { e },
+                    ]
+                } else {
+                    [e; 9]
+                };
+                out.put_block(x as usize, y as usize, 3, &block);
+            }
+        }
+        return out;
+    }
+
+    fn blank(width: usize, height: usize) -> Self {
+        return Self { width, height, pixels: vec![[0; 4]; width * height] };
+    }
+
+    /// Write a `size` x `size` block for source pixel `(x, y)`
+    fn put_block(&mut self, x: usize, y: usize, size: usize, block: &[Rgba]) {
+        for (i, color) in block.iter().enumerate() {
+            let (px, py) = (x * size + i % size, y * size + i / size);
+            self.pixels[py * self.width + px] = *color;
+        }
+    }
+
+    // ============= //
+    // == EFFECTS == //
+    // ============= //
+
+    /// CRT-style scanlines: darken every other row by `strength` (0 = none, 255 = black)
+    /// Best applied after upscaling
+    pub fn scanlines(&mut self, strength: u8) {
+        // `chunks_mut` can't split an empty image (e.g. scaled by 0) into rows
+        if self.pixels.is_empty() { return; }
+        let keep = 255 - strength as u32;
+        for row in self.pixels.chunks_mut(self.width).skip(1).step_by(2) {
+            for pixel in row {
+                for channel in &mut pixel[..3] {
+                    *channel = (*channel as u32 * keep / 255) as u8;
+                }
+            }
+        }
+    }
+}
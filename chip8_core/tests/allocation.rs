@@ -0,0 +1,59 @@
+//! Running frames mustn't touch the heap, so the core can run on microcontrollers without
+//! an allocator fast (or deterministic) enough for the hot path
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use chip8_core::backend::Interpreter;
+use chip8_core::{Emu, Quirks, Silent};
+
+/// The system allocator, counting allocations
+struct Counting;
+
+thread_local! {
+    // Per thread, so the test harness's own threads aren't counted
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+#[test]
+fn frames_do_not_allocate() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../roms");
+    let mut entries: Vec<_> = std::fs::read_dir(dir).unwrap().map(|e| e.unwrap().path()).collect();
+    entries.sort();
+    for path in entries {
+        let rom = std::fs::read(&path).unwrap();
+        for (preset, quirks) in Quirks::PRESETS {
+            let mut emu = Emu::new();
+            emu.seed_rng(0);
+            // The default beeper prints, which may allocate
+            emu.set_beeper(Box::new(Silent));
+            emu.set_quirks(quirks);
+            emu.load_rom(&rom).unwrap();
+
+            let before = ALLOCATIONS.get();
+            for frame in 0..300 {
+                for key in 0..16 {
+                    emu.keypress(key, key == frame / 8 % 16);
+                }
+                emu.run_frame_with(&mut Interpreter).unwrap();
+                emu.take_dirty();
+            }
+            let allocations = ALLOCATIONS.get() - before;
+            assert_eq!(allocations, 0, "{} ({}) allocated", path.display(), preset);
+        }
+    }
+}
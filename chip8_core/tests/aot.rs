@@ -0,0 +1,85 @@
+use std::path::Path;
+use std::process::Command;
+
+use chip8_core::analysis::Rom;
+use chip8_core::backend::Interpreter;
+use chip8_core::constants::{RAM_SIZE, SCREEN_WIDTH};
+use chip8_core::{aot, Emu};
+
+#[path = "aot/maze.rs"]
+mod maze;
+
+const FRAMES: usize = 600;
+
+/// Regenerate with `cargo run translate ../roms/MAZE ../chip8_core/tests/aot/maze.rs` from `desktop`
+#[test]
+fn translated_module_is_up_to_date() {
+    let rom = include_bytes!("../../roms/MAZE");
+    let module = aot::translate(&Rom::new(rom), "MAZE");
+    assert_eq!(module, include_str!("aot/maze.rs"));
+}
+
+#[test]
+fn translated_rom_matches_interpreter() {
+    for display_wait in [false, true] {
+        let mut translated = Emu::new();
+        let mut interpreted = Emu::new();
+        for emu in [&mut translated, &mut interpreted] {
+            emu.seed_rng(0xC8);
+            emu.set_display_wait(display_wait);
+            emu.load_rom(&maze::ROM).unwrap();
+        }
+
+        for frame in 0..FRAMES {
+            translated.run_frame_with(&mut maze::Translated).unwrap();
+            interpreted.run_frame_with(&mut Interpreter).unwrap();
+            let context = format!("frame {}, display wait: {}", frame, display_wait);
+            assert_eq!(translated.pc(), interpreted.pc(), "pc, {}", context);
+            assert_eq!(translated.registers(), interpreted.registers(), "registers, {}", context);
+            assert_eq!(translated.index(), interpreted.index(), "I, {}", context);
+            assert_eq!(translated.memory(0..RAM_SIZE), interpreted.memory(0..RAM_SIZE), "memory, {}", context);
+            assert_eq!(translated.framebuffer(), interpreted.framebuffer(), "display, {}", context);
+        }
+    }
+}
+
+#[test]
+fn modified_code_falls_back_to_the_interpreter() {
+    let mut emu = Emu::new();
+    emu.load_rom(&maze::ROM).unwrap();
+    // 210: LD V0, 0x00 -> LD V0, 0x07, so the translated block no longer applies
+    emu.poke(0x211, 0x07);
+    emu.set_pc(0x210);
+    chip8_core::backend::Backend::run(&mut maze::Translated, &mut emu, 1).unwrap();
+    assert_eq!(emu.registers()[0], 0x07);
+    assert_eq!(emu.pc(), 0x212);
+}
+
+#[test]
+fn translated_crate_builds_and_runs() {
+    let rom = include_bytes!("../../roms/MAZE");
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("aot_maze");
+    let core = Path::new(env!("CARGO_MANIFEST_DIR"));
+    aot::translate_crate(&Rom::new(rom), "MAZE", core).write(&dir).unwrap();
+
+    // Offline, as the only dependency is this crate, whose own dependencies are already fetched
+    let output = Command::new(env!("CARGO"))
+        .current_dir(&dir)
+        .args(["run", "--quiet", "--offline", "--", "120", "-", "200"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "translated crate failed:\n{}", String::from_utf8_lossy(&output.stderr));
+
+    let mut emu = Emu::new();
+    emu.seed_rng(200);
+    emu.load_rom(rom).unwrap();
+    for _ in 0..120 {
+        emu.run_frame().unwrap();
+    }
+    let display: Vec<bool> = emu.get_display().collect();
+    let expected: String = display.chunks(SCREEN_WIDTH)
+        .map(|row| row.iter().map(|lit| if *lit { '#' } else { '.' }).collect::<String>() + "\n")
+        .collect();
+    assert!(expected.contains('#'));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+}
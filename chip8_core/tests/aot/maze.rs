@@ -0,0 +1,112 @@
+//! `MAZE`, translated ahead of time by `chip8_core::aot`
+//! Generated code, do not edit
+
+use chip8_core::backend::{Backend, Interpreter};
+use chip8_core::{Emu, Fault, Opcode};
+
+/// The original ROM, to be loaded with `Emu::load_rom`
+pub const ROM: [u8; 34] = [
+    0xA2, 0x1E, 0xC2, 0x01, 0x32, 0x01, 0xA2, 0x1A, 0xD0, 0x14, 0x70, 0x04, 0x30, 0x40, 0x12, 0x00,
+    0x60, 0x00, 0x71, 0x04, 0x31, 0x20, 0x12, 0x00, 0x12, 0x18, 0x80, 0x40, 0x20, 0x10, 0x20, 0x40,
+    0x80, 0x10,
+];
+
+/// Runs the translated blocks, interpreting any code that wasn't translated or has been modified
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Translated;
+impl Backend for Translated {
+    fn run(&mut self, emu: &mut Emu, budget: usize) -> Result<usize, Fault> {
+        if emu.profiler().is_some() || emu.coverage().is_some() {
+            return Interpreter.run(emu, budget);
+        }
+        let mut executed = 0;
+        while executed < budget && !emu.is_waiting_vblank() {
+            let left = budget - executed;
+            let ran = match emu.pc() {
+                0x200 => block_200(emu, left)?,
+                0x206 => block_206(emu, left)?,
+                0x208 => block_208(emu, left)?,
+                0x20E => block_20e(emu, left)?,
+                0x210 => block_210(emu, left)?,
+                0x216 => block_216(emu, left)?,
+                0x218 => block_218(emu, left)?,
+                _ => 0,
+            };
+            executed += if ran == 0 { Interpreter.run(emu, 1)? } else { ran };
+        }
+        Ok(executed)
+    }
+}
+
+/// `0x200..0x206`
+fn block_200(emu: &mut Emu, budget: usize) -> Result<usize, Fault> {
+    if budget < 3 || emu.memory(0x200..0x206) != [0xA2, 0x1E, 0xC2, 0x01, 0x32, 0x01] {
+        return Ok(0);
+    }
+    emu.set_index(0x21E); // 200: LD I, 0x21E
+    emu.try_execute(0x202, Opcode::Rand(2, 1))?; // 202: RND V2, 0x01
+    emu.try_execute(0x204, Opcode::SkipIfValEQ(2, 1))?; // 204: SE V2, 0x01
+    Ok(3)
+}
+
+/// `0x206..0x208`
+fn block_206(emu: &mut Emu, budget: usize) -> Result<usize, Fault> {
+    if budget < 1 || emu.memory(0x206..0x208) != [0xA2, 0x1A] {
+        return Ok(0);
+    }
+    emu.set_index(0x21A); // 206: LD I, 0x21A
+    emu.set_pc(0x208);
+    Ok(1)
+}
+
+/// `0x208..0x20E`
+fn block_208(emu: &mut Emu, budget: usize) -> Result<usize, Fault> {
+    if budget < 3 || emu.memory(0x208..0x20E) != [0xD0, 0x14, 0x70, 0x04, 0x30, 0x40] {
+        return Ok(0);
+    }
+    emu.try_execute(0x208, Opcode::DrawSprite(0, 1, 4))?; // 208: DRW V0, V1, 4
+    if emu.pc() != 0x20A {
+        return Ok(1);
+    }
+    { let v = emu.registers_mut(); v[0x0] = v[0x0].wrapping_add(0x04); } // 20A: ADD V0, 0x04
+    emu.try_execute(0x20C, Opcode::SkipIfValEQ(0, 64))?; // 20C: SE V0, 0x40
+    Ok(3)
+}
+
+/// `0x20E..0x210`
+fn block_20e(emu: &mut Emu, budget: usize) -> Result<usize, Fault> {
+    if budget < 1 || emu.memory(0x20E..0x210) != [0x12, 0x00] {
+        return Ok(0);
+    }
+    emu.try_execute(0x20E, Opcode::Jump(512))?; // 20E: JP 0x200
+    Ok(1)
+}
+
+/// `0x210..0x216`
+fn block_210(emu: &mut Emu, budget: usize) -> Result<usize, Fault> {
+    if budget < 3 || emu.memory(0x210..0x216) != [0x60, 0x00, 0x71, 0x04, 0x31, 0x20] {
+        return Ok(0);
+    }
+    emu.registers_mut()[0x0] = 0x00; // 210: LD V0, 0x00
+    { let v = emu.registers_mut(); v[0x1] = v[0x1].wrapping_add(0x04); } // 212: ADD V1, 0x04
+    emu.try_execute(0x214, Opcode::SkipIfValEQ(1, 32))?; // 214: SE V1, 0x20
+    Ok(3)
+}
+
+/// `0x216..0x218`
+fn block_216(emu: &mut Emu, budget: usize) -> Result<usize, Fault> {
+    if budget < 1 || emu.memory(0x216..0x218) != [0x12, 0x00] {
+        return Ok(0);
+    }
+    emu.try_execute(0x216, Opcode::Jump(512))?; // 216: JP 0x200
+    Ok(1)
+}
+
+/// `0x218..0x21A`
+fn block_218(emu: &mut Emu, budget: usize) -> Result<usize, Fault> {
+    if budget < 1 || emu.memory(0x218..0x21A) != [0x12, 0x18] {
+        return Ok(0);
+    }
+    emu.try_execute(0x218, Opcode::Jump(536))?; // 218: JP 0x218
+    Ok(1)
+}
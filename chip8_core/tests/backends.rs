@@ -0,0 +1,86 @@
+use chip8_core::backend::{Backend, BlockJit, Interpreter};
+use chip8_core::constants::RAM_SIZE;
+use chip8_core::{Emu, Quirks};
+
+const FRAMES: usize = 600;
+
+fn new_emu(rom: &[u8], quirks: Quirks) -> Emu {
+    let mut emu = Emu::new();
+    emu.seed_rng(0xC8);
+    emu.set_quirks(quirks);
+    emu.load_rom(rom).unwrap();
+    emu
+}
+
+/// Presses a different key every 8 frames so input-driven code paths get exercised
+fn press_keys(emu: &mut Emu, frame: usize) {
+    let key = (frame / 8) % 16;
+    for idx in 0..16 {
+        emu.keypress(idx, idx == key);
+    }
+}
+
+fn assert_same_state(jit: &Emu, interp: &Emu, context: &str) {
+    assert_eq!(jit.pc(), interp.pc(), "pc, {}", context);
+    assert_eq!(jit.registers(), interp.registers(), "registers, {}", context);
+    assert_eq!(jit.index(), interp.index(), "I, {}", context);
+    assert_eq!(jit.stack(), interp.stack(), "stack, {}", context);
+    assert_eq!(jit.timers(), interp.timers(), "timers, {}", context);
+    assert_eq!(jit.memory(0..RAM_SIZE), interp.memory(0..RAM_SIZE), "memory, {}", context);
+    assert_eq!(jit.framebuffer(), interp.framebuffer(), "display, {}", context);
+}
+
+/// Runs `rom` on both backends frame by frame, comparing the whole machine state after each frame
+fn lockstep(name: &str, rom: &[u8], quirks: Quirks) {
+    let mut jit_emu = new_emu(rom, quirks);
+    let mut interp_emu = new_emu(rom, quirks);
+    let mut jit = BlockJit::new();
+
+    for frame in 0..FRAMES {
+        press_keys(&mut jit_emu, frame);
+        press_keys(&mut interp_emu, frame);
+        jit_emu.run_frame_with(&mut jit).unwrap();
+        interp_emu.run_frame_with(&mut Interpreter).unwrap();
+        assert_same_state(&jit_emu, &interp_emu, &format!("{} frame {}", name, frame));
+    }
+}
+
+#[test]
+fn block_jit_matches_interpreter_on_roms() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../roms");
+    let mut entries: Vec<_> = std::fs::read_dir(dir).unwrap().map(|e| e.unwrap().path()).collect();
+    entries.sort();
+    for path in entries {
+        let rom = std::fs::read(&path).unwrap();
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        for (preset, quirks) in Quirks::PRESETS {
+            lockstep(&format!("{} ({})", name, preset), &rom, quirks);
+        }
+    }
+}
+
+/// Rewrites the instruction at 0x20A inside the same block it is executed from
+const SELF_MODIFYING_ROM: [u8; 16] = [
+    0x60, 0x01, // 200: LD V0, 0x01
+    0xA2, 0x0B, // 202: LD I, 0x20B
+    0x70, 0x01, // 204: ADD V0, 0x01
+    0xF0, 0x55, // 206: LD [I], V0
+    0x12, 0x0A, // 208: JP 0x20A
+    0x61, 0x01, // 20A: LD V1, 0x01 (rewritten with V0)
+    0x71, 0x10, // 20C: ADD V1, 0x10
+    0x12, 0x04, // 20E: JP 0x204
+];
+
+#[test]
+fn block_jit_retranslates_modified_code() {
+    let mut jit_emu = new_emu(&SELF_MODIFYING_ROM, Quirks::default());
+    let mut interp_emu = new_emu(&SELF_MODIFYING_ROM, Quirks::default());
+    let mut jit = BlockJit::new();
+
+    for step in 0..50 {
+        let executed = jit.run(&mut jit_emu, 7).unwrap();
+        assert_eq!(Interpreter.run(&mut interp_emu, executed), Ok(executed));
+        assert_same_state(&jit_emu, &interp_emu, &format!("step {}", step));
+    }
+    assert_eq!(jit_emu.registers()[1], interp_emu.registers()[0].wrapping_add(0x10));
+}
@@ -0,0 +1,108 @@
+use chip8_core::analysis::cfg::Terminator;
+use chip8_core::analysis::{Cfg, Rom};
+use chip8_core::Opcode;
+
+/// Counts V0 up in a subroutine until it reaches 5, then jumps through a table
+const ROM: [u8; 16] = [
+    0x60, 0x00, // 200: LD V0, 0
+    0x22, 0x0C, // 202: CALL 0x20C
+    0x30, 0x05, // 204: SE V0, 5
+    0x12, 0x02, // 206: JP 0x202
+    0xB3, 0x00, // 208: JP V0, 0x300
+    0x00, 0xE0, // 20A: CLS, never reached
+    0x70, 0x01, // 20C: ADD V0, 1
+    0x00, 0xEE, // 20E: RET
+];
+
+#[test]
+fn basic_blocks() {
+    let cfg = Cfg::build(&Rom::new(&ROM));
+    let terminators: Vec<(u16, Terminator)> = cfg.blocks.values().map(|b| (b.start, b.terminator)).collect();
+    assert_eq!(terminators, [
+        (0x200, Terminator::Fallthrough(0x202)),
+        (0x202, Terminator::Call { target: 0x20C, ret: 0x204 }),
+        (0x204, Terminator::Skip { next: 0x206, skip: 0x208 }),
+        (0x206, Terminator::Jump(0x202)),
+        (0x208, Terminator::Indirect(0x300)),
+        (0x20C, Terminator::Return),
+    ]);
+    assert_eq!(cfg.blocks[&0x20C].instructions, [(0x20C, Opcode::AddVal(0, 1)), (0x20E, Opcode::Return)]);
+}
+
+#[test]
+fn successors() {
+    let cfg = Cfg::build(&Rom::new(&ROM));
+    let successors = |start: u16| cfg.blocks[&start].successors();
+    assert_eq!(successors(0x200), [0x202]);
+    // A call continues at its return address within the caller
+    assert_eq!(successors(0x202), [0x204]);
+    assert_eq!(successors(0x204), [0x206, 0x208]);
+    assert_eq!(successors(0x206), [0x202]);
+    // The target of an indirect jump isn't known statically
+    assert!(successors(0x208).is_empty());
+    assert!(successors(0x20C).is_empty());
+}
+
+#[test]
+fn subroutines() {
+    let cfg = Cfg::build(&Rom::new(&ROM));
+    assert_eq!(cfg.subroutines.keys().copied().collect::<Vec<_>>(), [0x200, 0x20C]);
+    let main = &cfg.subroutines[&0x200];
+    assert_eq!(main.blocks.iter().copied().collect::<Vec<_>>(), [0x200, 0x202, 0x204, 0x206, 0x208]);
+    assert_eq!(main.calls.iter().copied().collect::<Vec<_>>(), [0x20C]);
+    let sub = &cfg.subroutines[&0x20C];
+    assert_eq!(sub.blocks.iter().copied().collect::<Vec<_>>(), [0x20C]);
+    assert!(sub.calls.is_empty());
+    assert_eq!(cfg.subroutine_name(0x200), "main");
+    assert_eq!(cfg.subroutine_name(0x20C), "sub_20C");
+}
+
+#[test]
+fn dot_export() {
+    let cfg = Cfg::build(&Rom::new(&ROM));
+    let dot = cfg.to_dot();
+    assert!(dot.starts_with("digraph cfg {\n"));
+    assert!(dot.ends_with("}\n"));
+    for line in [
+        "  subgraph cluster_200 {",
+        "    label=\"main\";",
+        "  subgraph cluster_20C {",
+        "    label=\"sub_20C\";",
+        "  \"b200\" -> \"b202\";",
+        "  \"b202\" -> \"b204\" [label=\"return\", style=dotted];",
+        "  \"b202\" -> \"b20C\" [label=\"call\", color=blue];",
+        "  \"b204\" -> \"b206\";",
+        "  \"b204\" -> \"b208\" [label=\"skip\", style=dashed];",
+        "  \"b206\" -> \"b202\" [label=\"jump\"];",
+        "  \"i208\" [label=\"V0 + 0x300\", shape=diamond];",
+        "  \"b208\" -> \"i208\" [label=\"indirect\", style=dashed, color=red];",
+    ] {
+        assert!(dot.lines().any(|l| l == line), "missing {:?} in\n{}", line, dot);
+    }
+    assert!(!dot.contains("b20A"));
+}
+
+#[test]
+fn subroutine_dot_leaves_out_calls() {
+    let cfg = Cfg::build(&Rom::new(&ROM));
+    let dot = cfg.subroutine_dot(0x200).unwrap();
+    assert!(dot.starts_with("digraph main {\n"));
+    assert!(dot.contains("\"b202\" -> \"b204\" [label=\"return\", style=dotted];"));
+    assert!(!dot.contains("call"));
+    assert!(!dot.contains("b20C"));
+    assert!(cfg.subroutine_dot(0x202).is_none());
+}
+
+#[test]
+fn call_graph() {
+    let cfg = Cfg::build(&Rom::new(&ROM));
+    assert_eq!(cfg.call_graph_dot(), concat!(
+        "digraph calls {\n",
+        "  node [shape=ellipse, fontname=\"monospace\"];\n",
+        // Subroutines with an indirect jump are drawn with a double outline
+        "  \"main\" [label=\"main\", peripheries=2];\n",
+        "  \"sub_20C\" [label=\"sub_20C\"];\n",
+        "  \"main\" -> \"sub_20C\";\n",
+        "}\n",
+    ));
+}
@@ -0,0 +1,107 @@
+use chip8_core::cheats::{CheatTarget, SearchFilter};
+use chip8_core::constants::{NUM_REGS, RAM_SIZE};
+use chip8_core::{Cheat, CheatSearch, Emu};
+
+/// An emulator looping on the spot, so only the test changes its state
+fn idle() -> Emu {
+    let mut emu = Emu::new();
+    emu.load_rom(&[0x12, 0x00]).unwrap(); // 200: JP 0x200
+    emu
+}
+
+#[test]
+fn parse_file() {
+    let cheats = Cheat::parse_file("# BLITZ\nVE = 9  # Infinite lives\n\n0x2F0 = 0x05\n  2f1=255\nvf = 0\n").unwrap();
+    assert_eq!(cheats, [
+        Cheat { target: CheatTarget::Register(0xE), value: 9, description: "Infinite lives".to_string() },
+        Cheat::new(CheatTarget::Ram(0x2F0), 5),
+        Cheat::new(CheatTarget::Ram(0x2F1), 255),
+        Cheat::new(CheatTarget::Register(0xF), 0),
+    ]);
+    assert_eq!(cheats[0].to_string(), "VE = 0x09  # Infinite lives");
+    assert_eq!(cheats[1].to_string(), "0x2F0 = 0x05");
+}
+
+#[test]
+fn parse_errors() {
+    let error = |text: &str| {
+        let error = Cheat::parse_file(text).unwrap_err();
+        (error.line, error.reason)
+    };
+    assert_eq!(error("V0 = 1\n\nVG = 1"), (3, "invalid target".to_string()));
+    assert_eq!(error("V10 = 1"), (1, "invalid target".to_string()));
+    assert_eq!(error("0x1000 = 1"), (1, "invalid target".to_string()));
+    assert_eq!(error("V0 = 256"), (1, "invalid value".to_string()));
+    assert_eq!(error("V0 = -1"), (1, "invalid value".to_string()));
+    assert_eq!(error("V0 1  # = 1"), (1, "expected `TARGET = VALUE`".to_string()));
+    assert_eq!(Cheat::parse_file("V0 = 1\nVX = 1").unwrap_err().to_string(), "line 2: invalid target");
+}
+
+#[test]
+fn cheats_are_applied_every_frame() {
+    let mut emu = idle();
+    for cheat in Cheat::parse_file("VE = 9\n0x300 = 0x42").unwrap() {
+        emu.add_cheat(cheat);
+    }
+    emu.run_frame().unwrap();
+    assert_eq!(emu.registers()[0xE], 9);
+    assert_eq!(emu.memory(0x300..0x301), [0x42]);
+
+    emu.set_register(0xE, 1);
+    emu.run_frame().unwrap();
+    assert_eq!(emu.registers()[0xE], 9);
+}
+
+#[test]
+fn out_of_range_targets() {
+    let mut emu = idle();
+    assert_eq!(CheatTarget::Ram(RAM_SIZE as u16).read(&emu), None);
+    assert_eq!(CheatTarget::Register(NUM_REGS).read(&emu), None);
+    assert_eq!(CheatTarget::Ram(0x200).read(&emu), Some(0x12));
+
+    // Cheats on them do nothing
+    emu.add_cheat(Cheat::new(CheatTarget::Ram(0xFFFF), 1));
+    emu.add_cheat(Cheat::new(CheatTarget::Register(99), 1));
+    let before = emu.snapshot();
+    emu.apply_cheats();
+    assert_eq!(emu.snapshot(), before);
+}
+
+#[test]
+fn search_narrows_down_candidates() {
+    let mut emu = idle();
+    emu.poke(0x300, 3);
+    emu.set_register(5, 3);
+
+    let mut search = CheatSearch::new(&emu);
+    assert_eq!(search.candidates().len(), RAM_SIZE + NUM_REGS);
+    search.filter(&emu, SearchFilter::Equal(3));
+    assert_eq!(search.candidates(), [(CheatTarget::Ram(0x300), 3), (CheatTarget::Register(5), 3)]);
+
+    // Nothing changed
+    search.filter(&emu, SearchFilter::Unchanged);
+    assert_eq!(search.candidates().len(), 2);
+
+    let mut increased = search.clone();
+    emu.poke(0x300, 4);
+    increased.filter(&emu, SearchFilter::Increased);
+    assert_eq!(increased.candidates(), [(CheatTarget::Ram(0x300), 4)]);
+
+    let mut changed = search.clone();
+    changed.filter(&emu, SearchFilter::Changed);
+    assert_eq!(changed.candidates(), [(CheatTarget::Ram(0x300), 4)]);
+
+    let mut unchanged = search.clone();
+    unchanged.filter(&emu, SearchFilter::Unchanged);
+    assert_eq!(unchanged.candidates(), [(CheatTarget::Register(5), 3)]);
+
+    // Each filter compares against the values as of the one before
+    emu.set_register(5, 2);
+    search.filter(&emu, SearchFilter::Decreased);
+    assert_eq!(search.candidates(), [(CheatTarget::Register(5), 2)]);
+    emu.set_register(5, 1);
+    search.filter(&emu, SearchFilter::Decreased);
+    assert_eq!(search.candidates(), [(CheatTarget::Register(5), 1)]);
+    search.filter(&emu, SearchFilter::Decreased);
+    assert!(search.candidates().is_empty());
+}
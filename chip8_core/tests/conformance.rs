@@ -0,0 +1,168 @@
+//! Runs test ROMs headlessly under every quirk preset and compares the final screen with
+//! reference images
+//!
+//! `flags.8o`'s reference is worked out from the quirks' definitions in `flags_reference`. The
+//! community test suite ROMs go in `tests/conformance/roms` with their reference images in
+//! `tests/conformance/expected`, see `tests/conformance/roms/README.md`. Those tests are
+//! ignored by default and fail if a ROM or image is missing:
+//! `cargo test --test conformance -- --ignored`.
+
+use chip8_core::constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8_core::{octo, Emu, Quirks};
+use std::path::PathBuf;
+
+/// Frames each key press is held for
+const PRESS_FRAMES: usize = 4;
+
+fn dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/conformance")
+}
+
+/// The screen as text, `#` for lit pixels
+fn render(emu: &Emu) -> String {
+    let mut out = String::new();
+    for y in 0..SCREEN_HEIGHT {
+        out.extend((0..SCREEN_WIDTH).map(|x| if emu.framebuffer().pixel(x, y) { '#' } else { '.' }));
+        out.push('\n');
+    }
+    out
+}
+
+/// The screen after running `rom` for `frames` frames, pressing `presses` as `(frame, key)`
+fn run(rom: &[u8], quirks: Quirks, frames: usize, presses: &[(usize, usize)]) -> String {
+    let mut emu = Emu::new();
+    emu.seed_rng(0);
+    emu.set_quirks(quirks);
+    emu.load_rom(rom).unwrap();
+    for frame in 0..frames {
+        for key in 0..16 {
+            let held = presses.iter().any(|(at, k)| *k == key && (*at..*at + PRESS_FRAMES).contains(&frame));
+            emu.keypress(key, held);
+        }
+        emu.run_frame().unwrap();
+    }
+    render(&emu)
+}
+
+/// Run `rom` under each preset and compare with `expected(preset, quirks)`
+fn check(name: &str, rom: &[u8], frames: usize, keys: fn(&str) -> Vec<(usize, usize)>, expected: impl Fn(&str, Quirks) -> String) {
+    let mut failures = Vec::new();
+    for (preset, quirks) in Quirks::PRESETS {
+        let actual = run(rom, quirks, frames, &keys(preset));
+        let expected = expected(preset, quirks);
+        if actual != expected {
+            failures.push(format!("{} ({}):\nexpected\n{}\nfound\n{}", name, preset, expected, actual));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+/// Check a ROM from the community test suite against its images in `tests/conformance/expected`
+fn check_suite_rom(file: &str, frames: usize, keys: fn(&str) -> Vec<(usize, usize)>) {
+    let path = dir().join("roms").join(file);
+    let rom = std::fs::read(&path).unwrap_or_else(|_| panic!("{} is missing, see tests/conformance/roms/README.md", path.display()));
+    let name = file.trim_end_matches(".ch8");
+    check(name, &rom, frames, keys, |preset, _| {
+        let path = dir().join("expected").join(format!("{}.{}.txt", name, preset));
+        std::fs::read_to_string(&path).unwrap_or_else(|_| panic!("no reference image at {}", path.display()))
+    });
+}
+
+/// Rows of the standard font's glyphs for the digits `flags.8o` shows
+fn glyph(digit: u8) -> [u8; 5] {
+    match digit {
+        0 => [0xF0, 0x90, 0x90, 0x90, 0xF0],
+        1 => [0x20, 0x60, 0x20, 0x20, 0x70],
+        2 => [0xF0, 0x10, 0xF0, 0x80, 0xF0],
+        4 => [0x90, 0x90, 0xF0, 0x10, 0x10],
+        8 => [0xF0, 0x90, 0xF0, 0x90, 0xF0],
+        _ => unreachable!(),
+    }
+}
+
+/// What `flags.8o` should show under `quirks`, worked out from what each quirk means rather
+/// than by running the program
+fn flags_reference(quirks: Quirks) -> String {
+    let mut screen = [[false; SCREEN_WIDTH]; SCREEN_HEIGHT];
+    let mut draw = |x: usize, y: usize, digit: u8| {
+        for (row, bits) in glyph(digit).iter().enumerate() {
+            for col in (0..8).filter(|col| bits << col & 0x80 != 0) {
+                let (x, y) = (x + col, y + row);
+                if quirks.clip && (x >= SCREEN_WIDTH || y >= SCREEN_HEIGHT) { continue; }
+                screen[y % SCREEN_HEIGHT][x % SCREEN_WIDTH] = true;
+            }
+        }
+    };
+
+    // The first digit, then collision, carry and no borrow
+    for (i, digit) in [8, 1, 1, 1].into_iter().enumerate() {
+        draw(1 + 6 * i, 1, digit);
+    }
+    let quirk_results = [
+        if quirks.shift_vy { 4 } else { 1 }, // 8 >> 1, or 3 >> 1
+        if quirks.vf_reset { 0 } else { 1 },
+        if quirks.memory_increment { 2 } else { 1 },
+        if quirks.jump_vx { 2 } else { 1 },
+    ];
+    for (i, digit) in quirk_results.into_iter().enumerate() {
+        draw(1 + 6 * i, 9, digit);
+    }
+    // Across the right edge, then the bottom
+    draw(62, 17, 8);
+    draw(30, 29, 8);
+
+    screen.iter().map(|row| row.iter().map(|lit| if *lit { '#' } else { '.' }).chain(['\n']).collect::<String>()).collect()
+}
+
+fn no_keys(_: &str) -> Vec<(usize, usize)> {
+    Vec::new()
+}
+
+#[test]
+fn flags() {
+    let source = std::fs::read_to_string(dir().join("flags.8o")).unwrap();
+    let program = octo::compile(&source).unwrap();
+    check("flags", &program.rom, 60, no_keys, |_, quirks| flags_reference(quirks));
+}
+
+#[test]
+#[ignore = "needs the test suite ROMs in tests/conformance/roms"]
+fn chip8_logo() {
+    check_suite_rom("1-chip8-logo.ch8", 60, no_keys);
+}
+
+#[test]
+#[ignore = "needs the test suite ROMs in tests/conformance/roms"]
+fn ibm_logo() {
+    check_suite_rom("2-ibm-logo.ch8", 60, no_keys);
+}
+
+#[test]
+#[ignore = "needs the test suite ROMs in tests/conformance/roms"]
+fn corax_opcodes() {
+    check_suite_rom("3-corax+.ch8", 60, no_keys);
+}
+
+#[test]
+#[ignore = "needs the test suite ROMs in tests/conformance/roms"]
+fn flags_suite() {
+    check_suite_rom("4-flags.ch8", 120, no_keys);
+}
+
+#[test]
+#[ignore = "needs the test suite ROMs in tests/conformance/roms"]
+fn quirks() {
+    // Pick the platform matching the preset from the ROM's menu
+    check_suite_rom("5-quirks.ch8", 600, |preset| match preset {
+        "schip" => vec![(30, 2)],
+        "xochip" => vec![(30, 3)],
+        _ => vec![(30, 1)],
+    });
+}
+
+#[test]
+#[ignore = "needs the test suite ROMs in tests/conformance/roms"]
+fn keypad() {
+    // Choose the `EX9E`/`EXA1` test, then hold a few keys
+    check_suite_rom("6-keypad.ch8", 120, |_| vec![(10, 1), (40, 5), (40, 0xA), (60, 0xF)]);
+}
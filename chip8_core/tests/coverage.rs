@@ -0,0 +1,114 @@
+use chip8_core::coverage::{READ, WRITTEN};
+use chip8_core::constants::MAX_ROM_SIZE;
+use chip8_core::Emu;
+
+/// Loops three times, then takes a skip that always goes the same way
+const ROM: [u8; 19] = [
+    0xA2, 0x12, // 200: LD I, 0x212
+    0xD0, 0x11, // 202: DRW V0, V1, 1
+    0x70, 0x01, // 204: ADD V0, 1
+    0x30, 0x03, // 206: SE V0, 3
+    0x12, 0x02, // 208: JP 0x202
+    0x40, 0x09, // 20A: SNE V0, 9
+    0x00, 0xE0, // 20C: CLS, never runs
+    0xF0, 0x55, // 20E: LD [I], V0
+    0x12, 0x10, // 210: JP 0x210
+    0x80,       // 212: sprite data
+];
+
+fn covered(rom: &[u8], display_wait: bool) -> Emu {
+    let mut emu = Emu::new();
+    emu.set_display_wait(display_wait);
+    emu.load_rom(rom).unwrap();
+    emu.enable_coverage();
+    emu
+}
+
+fn run(ticks: usize) -> Emu {
+    let mut emu = covered(&ROM, false);
+    for _ in 0..ticks {
+        emu.tick();
+    }
+    emu
+}
+
+#[test]
+fn hits_and_accesses() {
+    let emu = run(30);
+    let coverage = emu.coverage().unwrap();
+    let hits: Vec<u64> = (0x200..0x210).step_by(2).map(|addr| coverage.hits(addr)).collect();
+    assert_eq!(hits, [1, 3, 3, 3, 2, 1, 0, 1]);
+    assert_eq!(coverage.hits(0x210), 30 - 14);
+    assert_eq!(coverage.flags(0x212), READ | WRITTEN);
+    assert_eq!(coverage.flags(0x20C), 0);
+}
+
+#[test]
+fn branches() {
+    let emu = run(30);
+    let coverage = emu.coverage().unwrap();
+    let stats = coverage.branch_stats(0x206).unwrap();
+    assert_eq!((stats.taken, stats.not_taken), (1, 2));
+    let stats = coverage.branch_stats(0x20A).unwrap();
+    assert_eq!((stats.taken, stats.not_taken), (1, 0));
+    assert!(coverage.branch_stats(0x204).is_none());
+}
+
+#[test]
+fn listing() {
+    let listing = run(30).coverage_listing().unwrap();
+    let line = |addr: &str| listing.lines().find(|line| line[1..].starts_with(addr)).unwrap().to_string();
+    assert!(line("202").starts_with(" 202  X--         3  D011"), "{}", line("202"));
+    // Never executed, and a skip that only went one way
+    assert!(line("20C").starts_with("!20C  ---         0  00E0"), "{}", line("20C"));
+    assert!(line("20A").starts_with("?20A"), "{}", line("20A"));
+    assert!(line("20A").ends_with("; skipped 1 / fell through 0"), "{}", line("20A"));
+    assert!(line("206").starts_with(" 206"), "{}", line("206"));
+    // Overwritten with V0 after being drawn
+    assert_eq!(line("212"), " 212  -RW            03    .byte 0x03");
+}
+
+#[test]
+fn lcov() {
+    let lcov = run(30).coverage_lcov("test.ch8").unwrap();
+    let lines: Vec<&str> = lcov.lines().collect();
+    assert_eq!(lines[..2], ["TN:", "SF:test.ch8"]);
+    for expected in ["DA:514,3", "DA:524,0", "BRDA:518,0,0,2", "BRDA:518,0,1,1", "BRDA:522,0,0,0", "BRDA:522,0,1,1"] {
+        assert!(lines.contains(&expected), "{} missing from\n{}", expected, lcov);
+    }
+    assert_eq!(lines[lines.len() - 5..], ["BRF:4", "BRH:3", "LF:9", "LH:8", "end_of_record"]);
+}
+
+#[test]
+fn reports_reach_the_end_of_ram() {
+    // Jumps to the last byte of RAM, leaving half an instruction there
+    let mut rom = vec![0; MAX_ROM_SIZE];
+    rom[..2].copy_from_slice(&[0x1F, 0xFF]); // 200: JP 0xFFF
+    let mut emu = covered(&rom, false);
+    emu.tick();
+    assert!(emu.try_tick().is_err());
+    let lcov = emu.coverage_lcov("end.ch8").unwrap();
+    assert!(lcov.lines().any(|line| line == "DA:512,1"), "{}", lcov);
+    let listing = emu.coverage_listing().unwrap();
+    assert!(listing.lines().last().unwrap()[1..].starts_with("FFF"), "{}", listing);
+}
+
+#[test]
+fn retries_count_once() {
+    // 200: LD V0, K, JP 0x200
+    let mut emu = covered(&[0xF0, 0x0A, 0x12, 0x00], false);
+    for _ in 0..50 {
+        emu.tick();
+    }
+    assert_eq!(emu.coverage().unwrap().hits(0x200), 0);
+    emu.keypress(1, true);
+    emu.tick();
+    assert_eq!(emu.coverage().unwrap().hits(0x200), 1);
+
+    // 200: DRW V0, V1, 1, JP 0x200, one draw per frame after the first frame's vblank
+    let mut emu = covered(&[0xD0, 0x11, 0x12, 0x00], true);
+    for _ in 0..3 {
+        emu.run_frame().unwrap();
+    }
+    assert_eq!(emu.coverage().unwrap().hits(0x200), 2);
+}
@@ -0,0 +1,85 @@
+use chip8_core::analysis::{decompile, Rom};
+
+#[test]
+fn if_else_calls_and_arrays() {
+    let rom = [
+        0xA3, 0x00, // 200: LD I, 0x300
+        0xF2, 0x55, // 202: LD [I], V2
+        0x30, 0x01, // 204: SE V0, 1
+        0x12, 0x0C, // 206: JP 0x20C
+        0x61, 0x02, // 208: LD V1, 2
+        0x12, 0x0E, // 20A: JP 0x20E
+        0x61, 0x03, // 20C: LD V1, 3
+        0x22, 0x12, // 20E: CALL 0x212
+        0x12, 0x10, // 210: JP 0x210
+        0xF1, 0x65, // 212: LD V1, [I]
+        0x00, 0xEE, // 214: RET
+    ];
+    assert_eq!(decompile(&Rom::new(&rom)), concat!(
+        "fn main() {\n",
+        "    i = 0x300;\n",
+        "    data_300[0..=2] = [v0, v1, v2];\n",
+        "    if v0 == 0x01 {\n",
+        "        v1 = 0x02;\n",
+        "    } else {\n",
+        "        v1 = 0x03;\n",
+        "    }\n",
+        "    sub_212();\n",
+        "    loop {\n",
+        "    }\n",
+        "}\n",
+        "\n",
+        "fn sub_212() {\n",
+        "    [v0, v1] = mem[i..][0..=1];\n",
+        "    return;\n",
+        "}\n",
+    ));
+}
+
+#[test]
+fn loops_and_guarded_instructions() {
+    let rom = [
+        0x60, 0x00, // 200: LD V0, 0
+        0x70, 0x01, // 202: ADD V0, 1
+        0x40, 0x0A, // 204: SNE V0, 10
+        0x12, 0x0A, // 206: JP 0x20A
+        0x12, 0x02, // 208: JP 0x202
+        0xE1, 0x9E, // 20A: SKP V1
+        0x00, 0xE0, // 20C: CLS
+        0x12, 0x0E, // 20E: JP 0x20E
+    ];
+    assert_eq!(decompile(&Rom::new(&rom)), concat!(
+        "fn main() {\n",
+        "    v0 = 0x00;\n",
+        "    loop {\n",
+        "        v0 += 0x01;\n",
+        "        if v0 == 0x0A {\n",
+        "            break;\n",
+        "        }\n",
+        "    }\n",
+        "    if !key(v1) {\n",
+        "        clear();\n",
+        "    }\n",
+        "    loop {\n",
+        "    }\n",
+        "}\n",
+    ));
+}
+
+#[test]
+fn unstructured_jumps_fall_back_to_goto() {
+    let rom = [
+        0x12, 0x04, // 200: JP 0x204
+        0x00, 0xE0, // 202: CLS, never reached
+        0x60, 0x01, // 204: LD V0, 1
+        0xB2, 0x00, // 206: JP V0, 0x200
+    ];
+    assert_eq!(decompile(&Rom::new(&rom)), concat!(
+        "fn main() {\n",
+        "    goto label_204;\n",
+        "label_204:\n",
+        "    v0 = 0x01;\n",
+        "    goto *(v0 + 0x200);\n",
+        "}\n",
+    ));
+}
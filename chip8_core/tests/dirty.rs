@@ -0,0 +1,93 @@
+use chip8_core::constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8_core::{Emu, Framebuffer};
+
+#[test]
+fn drawing_marks_its_rows() {
+    let mut screen = Framebuffer::new();
+    assert!(screen.take_dirty().is_empty());
+    screen.draw_sprite(10, 4, &[0xFF, 0x00, 0x81], false);
+    let dirty = screen.take_dirty();
+    // The blank row in the middle of the sprite doesn't change anything
+    assert_eq!(dirty.rows().collect::<Vec<_>>(), [4, 6]);
+    assert!(dirty.contains(4) && !dirty.contains(5));
+    assert!(!dirty.contains(SCREEN_HEIGHT));
+    assert_eq!(dirty.spans(), [4..5, 6..7]);
+    // Taking them resets the tracking
+    assert!(screen.take_dirty().is_empty());
+}
+
+#[test]
+fn wrapped_rows_are_marked() {
+    let mut screen = Framebuffer::new();
+    screen.draw_sprite(SCREEN_WIDTH - 4, SCREEN_HEIGHT - 2, &[0xFF; 4], false);
+    assert_eq!(screen.take_dirty().spans(), [0..2, SCREEN_HEIGHT - 2..SCREEN_HEIGHT]);
+
+    // Clipped rows aren't drawn, so they aren't marked
+    screen.draw_sprite(SCREEN_WIDTH - 4, SCREEN_HEIGHT - 2, &[0xFF; 4], true);
+    assert_eq!(screen.take_dirty().rows().collect::<Vec<_>>(), (SCREEN_HEIGHT - 2..SCREEN_HEIGHT).collect::<Vec<_>>());
+}
+
+#[test]
+fn clearing_marks_only_lit_rows() {
+    let mut screen = Framebuffer::new();
+    screen.clear();
+    assert!(screen.take_dirty().is_empty());
+    screen.draw_sprite(0, 3, &[0x80, 0x80], false);
+    screen.draw_sprite(0, 20, &[0x80], false);
+    screen.take_dirty();
+    screen.clear();
+    assert_eq!(screen.take_dirty().spans(), [3..5, 20..21]);
+}
+
+#[test]
+fn version_changes_only_with_the_pixels() {
+    let mut screen = Framebuffer::new();
+    let start = screen.version();
+    screen.clear();
+    screen.draw_sprite(0, 0, &[0x00, 0x00], false);
+    assert_eq!(screen.version(), start);
+
+    screen.draw_sprite(0, 0, &[0x80], false);
+    let drawn = screen.version();
+    assert!(drawn > start);
+    // Taking the dirty rows isn't a change
+    screen.take_dirty();
+    assert_eq!(screen.version(), drawn);
+    screen.draw_sprite(0, 0, &[0x80], false);
+    assert!(screen.version() > drawn);
+}
+
+#[test]
+fn emu_tracks_its_screen() {
+    let mut emu = Emu::new();
+    emu.set_display_wait(false);
+    // 200: DRW V0, V1, 5, CLS, LD V2, 1
+    emu.load_rom(&[0xD0, 0x15, 0x00, 0xE0, 0x62, 0x01]).unwrap();
+    emu.set_register(1, 8);
+    emu.take_dirty();
+    let start = emu.display_version();
+
+    emu.tick();
+    assert_eq!(emu.take_dirty().rows().collect::<Vec<_>>(), (8..13).collect::<Vec<_>>());
+    let drawn = emu.display_version();
+    assert!(drawn > start);
+    emu.tick();
+    assert_eq!(emu.take_dirty().rows().collect::<Vec<_>>(), (8..13).collect::<Vec<_>>());
+    let cleared = emu.display_version();
+    assert!(cleared > drawn);
+    emu.tick();
+    assert!(emu.take_dirty().is_empty());
+    assert_eq!(emu.display_version(), cleared);
+}
+
+#[test]
+fn restoring_marks_every_row() {
+    let mut emu = Emu::new();
+    let snapshot = emu.snapshot();
+    emu.take_dirty();
+    let version = emu.display_version();
+    // Even a restore to the same pixels, as the frontend can't tell what changed
+    emu.restore(&snapshot);
+    assert_eq!(emu.take_dirty().rows().collect::<Vec<_>>(), (0..SCREEN_HEIGHT).collect::<Vec<_>>());
+    assert!(emu.display_version() > version);
+}
@@ -0,0 +1,99 @@
+use chip8_core::constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8_core::{Emu, Framebuffer};
+
+/// Coordinates of the lit pixels, row by row
+fn lit(screen: &Framebuffer) -> Vec<(usize, usize)> {
+    (0..SCREEN_HEIGHT).flat_map(|y| (0..SCREEN_WIDTH).map(move |x| (x, y))).filter(|(x, y)| screen.pixel(*x, *y)).collect()
+}
+
+#[test]
+fn sprites_wrap_around_the_edges() {
+    let mut screen = Framebuffer::new();
+    assert!(!screen.draw_sprite(SCREEN_WIDTH - 2, SCREEN_HEIGHT - 1, &[0b1100_0011, 0b1000_0001], false));
+    // The sprite's last six columns wrap round to columns 0 to 5
+    assert_eq!(lit(&screen), [
+        (5, 0), (SCREEN_WIDTH - 2, 0),
+        (4, SCREEN_HEIGHT - 1), (5, SCREEN_HEIGHT - 1), (SCREEN_WIDTH - 2, SCREEN_HEIGHT - 1), (SCREEN_WIDTH - 1, SCREEN_HEIGHT - 1),
+    ]);
+}
+
+#[test]
+fn sprites_are_clipped_at_the_edges() {
+    let mut screen = Framebuffer::new();
+    assert!(!screen.draw_sprite(SCREEN_WIDTH - 2, SCREEN_HEIGHT - 1, &[0b1100_0011, 0b1000_0001], true));
+    assert_eq!(lit(&screen), [(SCREEN_WIDTH - 2, SCREEN_HEIGHT - 1), (SCREEN_WIDTH - 1, SCREEN_HEIGHT - 1)]);
+}
+
+#[test]
+fn start_position_wraps_even_when_clipping() {
+    for clip in [false, true] {
+        let mut screen = Framebuffer::new();
+        screen.draw_sprite(SCREEN_WIDTH + 3, SCREEN_HEIGHT + 2, &[0x80], clip);
+        assert_eq!(lit(&screen), [(3, 2)], "clip: {}", clip);
+    }
+}
+
+#[test]
+fn collisions() {
+    let mut screen = Framebuffer::new();
+    assert!(!screen.draw_sprite(0, 0, &[0xF0], false));
+    // Touching but not overlapping
+    assert!(!screen.draw_sprite(4, 0, &[0xF0], false));
+    assert_eq!(screen.rows()[0] >> (128 - SCREEN_WIDTH), 0xFF << (SCREEN_WIDTH - 8));
+    // Overlapping turns the shared pixels off
+    assert!(screen.draw_sprite(2, 0, &[0xC0], false));
+    assert_eq!(lit(&screen).len(), 6);
+    // A collision in the wrapped part counts too
+    assert!(screen.draw_sprite(SCREEN_WIDTH - 4, 0, &[0x0F], false));
+    assert_eq!(lit(&screen), (2..8).map(|x| (x, 0)).collect::<Vec<_>>());
+}
+
+#[test]
+fn display_matches_pixels() {
+    let mut emu = Emu::new();
+    emu.load_rom(&[0xD0, 0x15, 0x12, 0x02]).unwrap(); // 200: DRW V0, V1, 5, JP 0x202
+    emu.set_register(0, 62);
+    emu.set_register(1, 30);
+    emu.run_frame().unwrap();
+    let display: Vec<bool> = emu.get_display().collect();
+    assert_eq!(display.len(), SCREEN_WIDTH * SCREEN_HEIGHT);
+    for y in 0..SCREEN_HEIGHT {
+        for x in 0..SCREEN_WIDTH {
+            assert_eq!(display[y * SCREEN_WIDTH + x], emu.framebuffer().pixel(x, y), "({}, {})", x, y);
+        }
+    }
+    assert!(display.iter().any(|pixel| *pixel));
+}
+
+#[test]
+fn draw_sets_and_clears_vf() {
+    let mut emu = Emu::new();
+    emu.set_display_wait(false);
+    // 200: DRW V0, V1, 5 three times, I pointing at the font's "0"
+    emu.load_rom(&[0xD0, 0x15, 0xD0, 0x15, 0xD0, 0x15]).unwrap();
+    emu.set_register(0xF, 7);
+    emu.tick();
+    assert_eq!(emu.registers()[0xF], 0);
+    emu.tick();
+    assert_eq!(emu.registers()[0xF], 1);
+    assert!(emu.get_display().all(|pixel| !pixel));
+    emu.tick();
+    assert_eq!(emu.registers()[0xF], 0);
+}
+
+#[test]
+fn sprite_data_wraps_at_the_end_of_ram() {
+    let mut emu = Emu::new();
+    emu.set_display_wait(false);
+    emu.load_rom(&[0xAF, 0xFF, 0xD0, 0x12]).unwrap(); // 200: LD I, 0xFFF, DRW V0, V1, 2
+    emu.poke(0xFFF, 0x81);
+    emu.tick();
+    emu.tick();
+    // The second row is the first byte of RAM, the top of the font's "0"
+    let expected = [0x81, emu.memory(0..1)[0]];
+    for (y, byte) in expected.iter().enumerate() {
+        for x in 0..8 {
+            assert_eq!(emu.framebuffer().pixel(x, y), byte << x & 0x80 != 0, "({}, {})", x, y);
+        }
+    }
+}
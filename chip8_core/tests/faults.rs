@@ -0,0 +1,169 @@
+use chip8_core::backend::{Backend, BlockJit, Interpreter};
+use chip8_core::constants::{MAX_ROM_SIZE, RAM_SIZE, STACK_SIZE, START_ADDR};
+use chip8_core::{Emu, Fault, Opcode, RomTooLarge, UnknownOpcode, Xorshift};
+
+fn emu(rom: &[u8]) -> Emu {
+    let mut emu = Emu::new();
+    emu.seed_rng(0);
+    emu.set_display_wait(false);
+    emu.load_rom(rom).unwrap();
+    emu
+}
+
+/// Runs `rom` until it faults, checking the fault left the state untouched
+fn fault(rom: &[u8]) -> Fault {
+    let mut emu = emu(rom);
+    for _ in 0..1000 {
+        let before = emu.snapshot();
+        if let Err(fault) = emu.try_tick() {
+            assert_eq!(emu.snapshot(), before, "{}", fault);
+            return fault;
+        }
+    }
+    panic!("no fault");
+}
+
+#[test]
+fn decode_errors() {
+    assert_eq!(Opcode::try_new(0x00E0), Ok(Opcode::ClearScreen));
+    assert_eq!(Opcode::try_new(0x5121), Err(UnknownOpcode(0x5121)));
+    assert_eq!(UnknownOpcode(0xF0FF).to_string(), "unknown opcode F0FF");
+}
+
+#[test]
+fn unknown_opcode() {
+    assert_eq!(fault(&[0x60, 0x01, 0xFF, 0xFF]), Fault::UnknownOpcode { addr: 0x202, opcode: 0xFFFF });
+    assert_eq!(Fault::UnknownOpcode { addr: 0x202, opcode: 0xFFFF }.to_string(), "unknown opcode FFFF at 202");
+}
+
+#[test]
+fn stack() {
+    assert_eq!(fault(&[0x00, 0xEE]), Fault::StackUnderflow);
+    // 200: CALL 0x200, forever
+    assert_eq!(fault(&[0x22, 0x00]), Fault::StackOverflow);
+    let mut emu = emu(&[0x22, 0x00]);
+    while emu.try_tick().is_ok() {}
+    assert_eq!(emu.stack().len(), STACK_SIZE - 1);
+}
+
+#[test]
+fn memory_out_of_range() {
+    // LD I, 0xFFE then BCD, LD [I], V1 and LD V1, [I]
+    assert_eq!(fault(&[0xAF, 0xFE, 0xF0, 0x33]), Fault::MemoryOutOfRange { addr: 0xFFE, len: 3 });
+    assert_eq!(fault(&[0xAF, 0xFE, 0xF2, 0x55]), Fault::MemoryOutOfRange { addr: 0xFFE, len: 3 });
+    assert_eq!(fault(&[0xAF, 0xFE, 0xF2, 0x65]), Fault::MemoryOutOfRange { addr: 0xFFE, len: 3 });
+    // Up to the last byte is fine
+    let mut emu = emu(&[0xAF, 0xFE, 0xF1, 0x55]);
+    emu.set_register(1, 7);
+    emu.try_tick().unwrap();
+    emu.try_tick().unwrap();
+    assert_eq!(emu.memory(RAM_SIZE - 1..RAM_SIZE), [7]);
+}
+
+#[test]
+fn pc_out_of_range() {
+    // JP V0, 0xFFF with V0 = 1
+    assert_eq!(fault(&[0x60, 0x01, 0xBF, 0xFF]), Fault::PcOutOfRange(0x1000));
+    // The last instruction in RAM runs, then there's nowhere to go
+    let mut emu = emu(&[0x1F, 0xFE]);
+    emu.poke(0xFFE, 0x60);
+    assert_eq!((emu.try_tick(), emu.try_tick(), emu.try_tick()), (Ok(()), Ok(()), Err(Fault::PcOutOfRange(0x1000))));
+}
+
+#[test]
+fn invalid_key() {
+    // LD V0, 0x10 then SKP V0
+    assert_eq!(fault(&[0x60, 0x10, 0xE0, 0x9E]), Fault::InvalidKey(0x10));
+    assert_eq!(fault(&[0x60, 0xFF, 0xE0, 0xA1]), Fault::InvalidKey(0xFF));
+}
+
+/// One ROM for each kind of fault, with the fault it ends in
+const FAULTING: [(&[u8], Fault); 6] = [
+    // LD V0, 1, then an unknown opcode
+    (&[0x60, 0x01, 0xFF, 0xFF], Fault::UnknownOpcode { addr: 0x202, opcode: 0xFFFF }),
+    // LD V0, 1, ADD V0, 1, RET, faulting part way through a translated block
+    (&[0x60, 0x01, 0x70, 0x01, 0x00, 0xEE], Fault::StackUnderflow),
+    (&[0x22, 0x00], Fault::StackOverflow),
+    (&[0xAF, 0xFE, 0xF0, 0x33], Fault::MemoryOutOfRange { addr: 0xFFE, len: 3 }),
+    (&[0x60, 0x01, 0xBF, 0xFF], Fault::PcOutOfRange(0x1000)),
+    (&[0x60, 0x10, 0xE0, 0x9E], Fault::InvalidKey(0x10)),
+];
+
+#[test]
+fn backends_return_faults() {
+    for (rom, expected) in FAULTING {
+        // Where the interpreter stops, one instruction at a time
+        let mut stepped = emu(rom);
+        while stepped.try_tick().is_ok() {}
+
+        let backends: [Box<dyn Backend>; 2] = [Box::new(Interpreter), Box::new(BlockJit::new())];
+        for mut backend in backends {
+            let mut emu = emu(rom);
+            let mut result = Ok(0);
+            for _ in 0..100 {
+                result = backend.run(&mut emu, 7);
+                if result.is_err() { break; }
+            }
+            assert_eq!(result, Err(expected));
+            assert_eq!(emu.snapshot(), stepped.snapshot(), "{}", expected);
+            // Running again faults again without going anywhere
+            assert_eq!(backend.run(&mut emu, 7), Err(expected));
+            assert_eq!(emu.snapshot(), stepped.snapshot(), "{}", expected);
+        }
+    }
+}
+
+#[test]
+fn run_frame_stops_at_a_fault() {
+    // LD V0, 5, LD DT, V0, RET
+    let mut emu = emu(&[0x60, 0x05, 0xF0, 0x15, 0x00, 0xEE]);
+    assert_eq!(emu.run_frame(), Err(Fault::StackUnderflow));
+    assert_eq!(emu.pc(), 0x204);
+    // The frame didn't finish, so the timers weren't ticked
+    assert_eq!(emu.timers(), (5, 0));
+    assert_eq!(emu.run_frame_with(&mut BlockJit::new()), Err(Fault::StackUnderflow));
+    assert_eq!(emu.timers(), (5, 0));
+}
+
+#[test]
+fn oversized_roms_are_rejected() {
+    let mut emu = Emu::new();
+    let before = emu.snapshot();
+    assert_eq!(emu.load_rom(&[0x12; MAX_ROM_SIZE + 1]), Err(RomTooLarge { len: MAX_ROM_SIZE + 1 }));
+    assert_eq!(emu.snapshot(), before);
+    assert_eq!(RomTooLarge { len: 4000 }.to_string(), "ROM is 4000 bytes, the most that fits is 3584");
+    // Filling memory to the end is fine
+    emu.load_rom(&[0x12; MAX_ROM_SIZE]).unwrap();
+    assert_eq!(emu.memory(RAM_SIZE - 1..RAM_SIZE), [0x12]);
+}
+
+#[test]
+#[should_panic(expected = "stack pointer underflow")]
+fn tick_panics_on_a_fault() {
+    emu(&[0x00, 0xEE]).tick();
+}
+
+#[test]
+fn random_roms_never_panic_and_backends_agree() {
+    let mut rng = Xorshift::new(1);
+    for _ in 0..500 {
+        let rom: Vec<u8> = (0..RAM_SIZE - START_ADDR as usize).map(|_| rng.next_u64() as u8).collect();
+        let display_wait = rng.next_u64() & 1 != 0;
+        let seed = rng.next_u64();
+        let backends: [Box<dyn Backend>; 2] = [Box::new(Interpreter), Box::new(BlockJit::new())];
+        let ends: Vec<_> = backends.into_iter().map(|mut backend| {
+            let mut emu = emu(&rom);
+            emu.set_display_wait(display_wait);
+            let mut keys = Xorshift::new(seed);
+            let mut fault = None;
+            for frame in 0..60 {
+                emu.keypress(frame % 16, keys.next_u64() & 1 != 0);
+                fault = emu.run_frame_with(&mut *backend).err();
+                if fault.is_some() { break; }
+            }
+            (fault, emu.snapshot())
+        }).collect();
+        // Both backends stop at the same fault, in the same state
+        assert_eq!(ends[0], ends[1]);
+    }
+}
@@ -0,0 +1,141 @@
+use chip8_core::gym::{Env, GameSpec, Step, VecEnv, BUILTIN};
+use chip8_core::{Fault, Xorshift};
+
+fn rom(name: &str) -> Vec<u8> {
+    std::fs::read(format!("{}/../roms/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap()
+}
+
+/// Actions from a fixed sequence of random numbers
+fn actions(seed: u64, num_actions: usize) -> impl FnMut() -> usize {
+    let mut rng = Xorshift::new(seed);
+    move || (rng.next_u64() % num_actions as u64) as usize
+}
+
+/// Play an episode to the end, returning every step
+fn episode(env: &mut Env, seed: u64) -> Vec<Step> {
+    env.reset(seed).unwrap();
+    let mut action = actions(seed, env.num_actions());
+    let mut steps = Vec::new();
+    while !env.is_done() {
+        steps.push(env.step(action()).unwrap());
+    }
+    steps
+}
+
+#[test]
+fn builtin_specs_parse() {
+    for (name, text) in BUILTIN {
+        GameSpec::parse(text).unwrap_or_else(|e| panic!("{}: {}", name, e));
+        assert!(GameSpec::builtin(name).is_some());
+    }
+}
+
+#[test]
+fn parse_errors() {
+    let error = GameSpec::parse("actions = -, 4\nreward = VG").unwrap_err();
+    assert_eq!(error.line, 2);
+    assert!(GameSpec::parse("done = VE = 0").is_err());
+    assert!(GameSpec::parse("actions = 4, 10").is_err());
+    assert!(GameSpec::parse("reward = VE / 0").is_err());
+    assert!(GameSpec::parse("frame_skip = 0").is_err());
+    assert!(GameSpec::parse("lives = VE").is_err());
+    // Only `target / div % modulo`, in that order
+    assert!(GameSpec::parse("reward = VE % 10 / 2").is_err());
+    assert!(GameSpec::parse("reward = VE / 2 / 5").is_err());
+    assert_eq!(GameSpec::parse("reward = VE / 2 % 10").unwrap().rewards[0].value.to_string(), "VE / 2 % 10");
+
+    let spec = GameSpec::parse("actions = -, 4+6  # Both\nreward = 0x2F0 % 10 * -0.5").unwrap();
+    assert_eq!(spec.actions, vec![vec![], vec![4, 6]]);
+    assert_eq!(spec.rewards[0].value.to_string(), "0x2F0 % 10");
+    assert_eq!(spec.rewards[0].scale, -0.5);
+}
+
+#[test]
+fn brix_rewards_bricks_and_lives() {
+    let mut env = Env::new(&rom("BRIX"), GameSpec::builtin("BRIX").unwrap()).unwrap();
+    let steps = episode(&mut env, 1);
+    let last = steps.last().unwrap();
+    assert!(!last.info.truncated, "ran out of frames");
+    let (bricks, lives) = (env.emu().registers()[5], env.emu().registers()[0xE]);
+    assert_eq!(lives, 0);
+    let total: f32 = steps.iter().map(|step| step.reward).sum();
+    assert_eq!(total, bricks as f32 - 5.0);
+}
+
+#[test]
+fn pong_rewards_points() {
+    let mut env = Env::new(&rom("PONG"), GameSpec::builtin("PONG").unwrap()).unwrap();
+    let steps = episode(&mut env, 2);
+    let score = env.emu().registers()[0xE] as i32;
+    let total: f32 = steps.iter().map(|step| step.reward).sum();
+    assert_eq!(total, (score / 10 - score % 10) as f32);
+    assert!(score / 10 == 9 || score % 10 == 9);
+}
+
+#[test]
+fn episodes_are_reproducible() {
+    let mut env = Env::new(&rom("BRIX"), GameSpec::builtin("BRIX").unwrap()).unwrap();
+    let first = episode(&mut env, 3);
+    let second = episode(&mut env, 3);
+    assert_eq!(first, second);
+}
+
+#[test]
+fn vec_env_matches_single_envs() {
+    const COUNT: usize = 6;
+    const STEPS: usize = 1500;
+    let rom = rom("BRIX");
+    let mut spec = GameSpec::builtin("BRIX").unwrap();
+    // Short episodes, to check the automatic resets
+    spec.max_frames = Some(800);
+
+    let run = |threads: usize| {
+        let mut vec_env = VecEnv::new(&rom, spec.clone(), COUNT, 10).unwrap();
+        vec_env.set_threads(threads);
+        let mut policies: Vec<_> = (0..COUNT).map(|i| actions(i as u64, spec.actions.len())).collect();
+        (0..STEPS).map(|_| {
+            let actions: Vec<_> = policies.iter_mut().map(|policy| policy()).collect();
+            vec_env.step(&actions).unwrap()
+        }).collect::<Vec<_>>()
+    };
+    let serial = run(1);
+    assert_eq!(serial, run(4));
+
+    // Environment 0 on its own: seeds 10, 10 + COUNT, ...
+    let mut env = Env::new(&rom, spec.clone()).unwrap();
+    let mut policy = actions(0, spec.actions.len());
+    let mut seed = 10;
+    env.reset(seed).unwrap();
+    for steps in &serial {
+        let mut step = env.step(policy()).unwrap();
+        if step.done {
+            seed += COUNT as u64;
+            step.observation = env.reset(seed).unwrap();
+        }
+        assert_eq!(steps[0], step);
+    }
+    assert!(seed > 10, "no episode finished");
+}
+
+#[test]
+fn seeds_wrap_around() {
+    let mut spec = GameSpec::builtin("PONG").unwrap();
+    spec.max_frames = Some(4);
+    let mut vec_env = VecEnv::new(&rom("PONG"), spec, 3, u64::MAX - 1).unwrap();
+    for _ in 0..4 {
+        vec_env.step(&[0, 0, 0]).unwrap();
+    }
+}
+
+#[test]
+fn faults_name_the_environment() {
+    // Returns with an empty stack while key 0 is held
+    let rom = [0xE0, 0xA1, 0x00, 0xEE, 0x12, 0x00];
+    let spec = GameSpec::parse("actions = -, 0").unwrap();
+    let mut vec_env = VecEnv::new(&rom, spec, 3, 0).unwrap();
+    vec_env.step(&[0, 0, 0]).unwrap();
+    let error = vec_env.step(&[0, 1, 0]).unwrap_err();
+    assert_eq!(error.env, 1);
+    assert_eq!(error.fault, Fault::StackUnderflow);
+    assert_eq!(vec_env.envs()[1].emu().pc(), 0x202);
+}
@@ -0,0 +1,78 @@
+use chip8_core::constants::{NUM_REGS, RAM_SIZE};
+use chip8_core::Emu;
+
+fn emu(rom: &[u8]) -> Emu {
+    let mut emu = Emu::new();
+    emu.load_rom(rom).unwrap();
+    emu
+}
+
+#[test]
+fn reading_state() {
+    // 200: LD V3, 0x42, LD I, 0x300, CALL 0x20A, ..., 20A: LD DT, V3, LD ST, V3
+    let mut emu = emu(&[0x63, 0x42, 0xA3, 0x00, 0x22, 0x0A, 0, 0, 0, 0, 0xF3, 0x15, 0xF3, 0x18]);
+    for _ in 0..5 {
+        emu.tick();
+    }
+    assert_eq!(emu.registers().len(), NUM_REGS);
+    assert_eq!(emu.registers()[3], 0x42);
+    assert_eq!(emu.index(), 0x300);
+    assert_eq!(emu.stack(), [0x206]);
+    assert_eq!(emu.pc(), 0x20E);
+    assert_eq!(emu.timers(), (0x42, 0x42));
+    assert_eq!(emu.memory(0x200..0x202), [0x63, 0x42]);
+}
+
+#[test]
+fn memory_is_clamped_to_ram() {
+    let emu = emu(&[]);
+    assert_eq!(emu.memory(RAM_SIZE - 2..RAM_SIZE + 10).len(), 2);
+    assert!(emu.memory(RAM_SIZE + 1..RAM_SIZE + 10).is_empty());
+}
+
+#[test]
+fn poke_and_set() {
+    let mut emu = emu(&[]);
+    emu.poke(0x300, 0xAB);
+    emu.poke(RAM_SIZE as u16, 0xCD); // Ignored
+    assert_eq!(emu.memory(0x300..0x301), [0xAB]);
+
+    emu.set_register(0xF, 9);
+    emu.set_register(NUM_REGS, 9); // Ignored
+    emu.registers_mut()[1] = 7;
+    assert_eq!((emu.registers()[1], emu.registers()[0xF]), (7, 9));
+
+    emu.set_index(0x123);
+    emu.set_pc(0x456);
+    assert_eq!((emu.index(), emu.pc()), (0x123, 0x456));
+}
+
+#[test]
+fn poked_code_runs() {
+    // 200: LD V0, 1, JP 0x200
+    let mut emu = emu(&[0x60, 0x01, 0x12, 0x00]);
+    emu.tick();
+    emu.tick();
+    // The decoded instruction is cached, poking it must replace it
+    emu.poke(0x201, 0x02);
+    emu.tick();
+    assert_eq!(emu.registers()[0], 2);
+}
+
+#[test]
+fn hex_dump() {
+    let emu = emu(b"Hello, CHIP-8\x00\xFF");
+    let dump = emu.hex_dump(0x1F8..0x212);
+    let lines: Vec<&str> = dump.lines().collect();
+    // Addresses before the range are left blank
+    let first = format!("1F0:{}{}  |        ........|", " ".repeat(3 * 8), " 00".repeat(8));
+    assert_eq!(lines, [
+        first.as_str(),
+        "200: 48 65 6C 6C 6F 2C 20 43 48 49 50 2D 38 00 FF 00  |Hello, CHIP-8...|  ; program",
+        "210: 00 00                                            |..              |",
+    ]);
+
+    assert_eq!(emu.hex_dump(0..16), "000: F0 90 90 90 F0 20 60 20 20 70 F0 10 F0 80 F0 F0  |..... `  p......|  ; font\n");
+    // Past the end of RAM is left out
+    assert_eq!(emu.hex_dump(RAM_SIZE - 1..RAM_SIZE + 16).lines().count(), 1);
+}
@@ -0,0 +1,121 @@
+use chip8_core::analysis::lint::{Extension, Severity};
+use chip8_core::analysis::{lint, LintReport, Rom};
+use chip8_core::constants::{RAM_SIZE, STACK_SIZE};
+use chip8_core::Opcode;
+
+fn errors(report: &LintReport) -> Vec<(u16, &str)> {
+    report.findings.iter()
+        .filter(|f| f.severity == Severity::Error)
+        .map(|f| (f.addr, f.message.as_str()))
+        .collect()
+}
+
+/// Subroutines nested `depth` (at least 1) deep: the entry calls the first, which calls the second, ...
+fn call_chain(depth: usize) -> Vec<u8> {
+    let mut rom = vec![0x22, 0x04, 0x12, 0x02]; // CALL 0x204; JP 0x202
+    for i in 0..depth {
+        let next = 0x204 + 4 * (i as u16 + 1);
+        match i + 1 < depth {
+            true => rom.extend([0x20 | (next >> 8) as u8, next as u8]), // CALL next
+            false => rom.extend([0x00, 0xE0]), // CLS
+        }
+        rom.extend([0x00, 0xEE]); // RET
+    }
+    rom
+}
+
+#[test]
+fn clean_rom() {
+    let report = lint(&[
+        0x60, 0x01, // 200: LD V0, 1
+        0x70, 0x01, // 202: ADD V0, 1
+        0x12, 0x02, // 204: JP 0x202
+    ]);
+    assert!(report.is_clean(), "{}", report);
+    assert_eq!(report.size, 6);
+    assert_eq!(report.instructions, 3);
+    assert!(report.extensions.is_empty());
+    assert_eq!(report.max_call_depth, Some(0));
+}
+
+#[test]
+fn reports_bad_control_flow() {
+    let report = lint(&[
+        0x30, 0x00, // 200: SE V0, 0
+        0x1F, 0x00, // 202: JP 0xF00
+        0x50, 0x01, // 204: 5XY1, unknown
+    ]);
+    assert_eq!(errors(&report), [
+        (0x202, "jump to 0xF00 outside the ROM"),
+        (0x204, "unknown instruction 5001"),
+        (0xF00, "execution runs off the end of the ROM"),
+    ]);
+    assert!(!report.is_clean());
+
+    let report = lint(&[0x60, 0x01]);
+    assert_eq!(errors(&report), [(0x202, "execution runs off the end of the ROM")]);
+}
+
+#[test]
+fn reports_extensions_and_quirks() {
+    let report = lint(&[
+        0x00, 0xFF, // 200: HIGH (SCHIP)
+        0xF0, 0x01, // 202: PLANE 0 (XO-CHIP)
+        0x80, 0x16, // 204: SHR V0, V1
+        0xF1, 0x55, // 206: LD [I], V1
+        0x12, 0x08, // 208: JP 0x208
+    ]);
+    assert_eq!(report.extensions.iter().copied().collect::<Vec<_>>(), [Extension::SuperChip, Extension::XoChip]);
+    let warnings: Vec<u16> = report.findings.iter().filter(|f| f.severity == Severity::Warning).map(|f| f.addr).collect();
+    assert_eq!(warnings, [0x200, 0x202, 0x204, 0x206]);
+    assert!(errors(&report).is_empty());
+}
+
+#[test]
+fn call_depth() {
+    for depth in [1, 2, STACK_SIZE - 1] {
+        let report = lint(&call_chain(depth));
+        assert_eq!(report.max_call_depth, Some(depth));
+        assert!(report.is_clean(), "depth {}: {}", depth, report);
+    }
+
+    // Pushing the last return address overflows the stack
+    let report = lint(&call_chain(STACK_SIZE));
+    assert_eq!(report.max_call_depth, Some(STACK_SIZE));
+    assert_eq!(errors(&report).len(), 1);
+    assert!(errors(&report)[0].1.starts_with("call depth 16 overflows the stack"));
+}
+
+#[test]
+fn recursion_is_unbounded() {
+    let report = lint(&[
+        0x22, 0x04, // 200: CALL 0x204
+        0x12, 0x02, // 202: JP 0x202
+        0x22, 0x04, // 204: CALL 0x204
+        0x00, 0xEE, // 206: RET
+    ]);
+    assert_eq!(report.max_call_depth, None);
+    assert!(report.findings.iter().any(|f| f.message.contains("recursive")));
+}
+
+#[test]
+fn oversized_rom() {
+    let bytes = vec![0x12; 0xFF00];
+    let report = lint(&bytes);
+    assert_eq!(report.size, 0xFF00);
+    assert_eq!(errors(&report), [(0x200, "ROM is 65280 bytes, only 3584 fit in RAM")]);
+
+    let rom = Rom::new(&bytes);
+    assert_eq!(rom.end() as usize, RAM_SIZE);
+    assert_eq!(rom.fetch(RAM_SIZE as u16 - 2), Some(0x1212));
+    assert_eq!(rom.fetch(RAM_SIZE as u16 - 1), None);
+    assert_eq!(Rom::with_base(&bytes, 0xFFFF).end(), 0xFFFF);
+}
+
+#[test]
+fn successors_at_the_top_of_memory() {
+    assert_eq!(Rom::successors(0xFFFD, Opcode::ClearScreen), [0xFFFF]);
+    assert!(Rom::successors(0xFFFE, Opcode::ClearScreen).is_empty());
+    assert_eq!(Rom::successors(0xFFFC, Opcode::SkipIfValEQ(0, 0)), [0xFFFE]);
+    assert_eq!(Rom::successors(0xFFFE, Opcode::Call(0x300)), [0x300]);
+}
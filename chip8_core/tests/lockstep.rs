@@ -0,0 +1,131 @@
+use chip8_core::backend::{Backend, BlockJit, Interpreter};
+use chip8_core::constants::TICKS_PER_FRAME;
+use chip8_core::lockstep::{lockstep, Side};
+use chip8_core::{Emu, Opcode, Quirks};
+
+/// Shifts V1 into V0, which only differs between presets that disagree on `shift_vy`
+const SHIFT: [u8; 8] = [
+    0x60, 0x03, // 200: LD V0, 3
+    0x61, 0x10, // 202: LD V1, 0x10
+    0x80, 0x16, // 204: SHR V0, V1
+    0x12, 0x06, // 206: JP 0x206
+];
+
+fn side(name: &str, rom: &[u8], quirks: Quirks, backend: Box<dyn Backend>) -> Side {
+    let mut emu = Emu::new();
+    emu.seed_rng(0);
+    emu.set_quirks(quirks);
+    emu.load_rom(rom).unwrap();
+    Side { name: name.to_string(), emu, backend }
+}
+
+fn preset(name: &str) -> Quirks {
+    Quirks::preset(name).unwrap()
+}
+
+#[test]
+fn reports_the_first_divergent_instruction() {
+    let mut left = side("default", &SHIFT, preset("default"), Box::new(Interpreter));
+    let mut right = side("xochip", &SHIFT, preset("xochip"), Box::new(Interpreter));
+    let divergence = lockstep(&mut left, &mut right, 10, |_| 0).unwrap();
+    assert_eq!(divergence.frame, 0);
+    assert_eq!(divergence.instruction, 2);
+    assert_eq!(divergence.addrs, (0x204, 0x204));
+    assert_eq!(divergence.differences, ["V0: 0x01 vs 0x08", "VF: 0x01 vs 0x00"]);
+
+    let report = divergence.to_string();
+    assert!(report.starts_with("Diverged on frame 0, after 2 matching instructions\n"));
+    let shift = format!("> 204: {}", Opcode::ShiftRight(0, 1));
+    assert!(report.lines().any(|l| l.starts_with(&shift) && l.ends_with(&shift)), "{}", report);
+    // Registers that differ are marked
+    assert!(report.lines().any(|l| l.starts_with("* V0    0x01") && l.ends_with("0x08")), "{}", report);
+    assert!(report.lines().any(|l| l.starts_with("  V1    0x10") && l.ends_with("0x10")), "{}", report);
+    assert!(report.ends_with("V0: 0x01 vs 0x08\nVF: 0x01 vs 0x00\n"));
+}
+
+#[test]
+fn counts_frames_and_instructions_before_diverging() {
+    let rom = [
+        0x60, 0x03, // 200: LD V0, 3
+        0x61, 0x10, // 202: LD V1, 0x10
+        0x72, 0x01, // 204: ADD V2, 1
+        0x32, 0x00, // 206: SE V2, 0
+        0x12, 0x04, // 208: JP 0x204
+        0x80, 0x16, // 20A: SHR V0, V1
+        0x12, 0x0C, // 20C: JP 0x20C
+    ];
+    let mut left = side("default", &rom, preset("default"), Box::new(Interpreter));
+    let mut right = side("xochip:jit", &rom, preset("xochip"), Box::new(BlockJit::new()));
+    let divergence = lockstep(&mut left, &mut right, 100, |_| 0).unwrap();
+    // Two loads, 255 trips round the loop, then the last ADD and SE
+    let instruction = 2 + 255 * 3 + 2;
+    assert_eq!(divergence.instruction, instruction);
+    assert_eq!(divergence.frame, instruction as usize / TICKS_PER_FRAME);
+    assert_eq!(divergence.addrs, (0x20A, 0x20A));
+
+    // Not enough frames to get there
+    let mut left = side("default", &rom, preset("default"), Box::new(Interpreter));
+    let mut right = side("xochip", &rom, preset("xochip"), Box::new(Interpreter));
+    assert!(lockstep(&mut left, &mut right, divergence.frame, |_| 0).is_none());
+}
+
+#[test]
+fn matching_configurations_never_diverge() {
+    // Random sprites, skipping on keys that are held
+    let rom = [
+        0xC0, 0x3F, // 200: RND V0, 0x3F
+        0xD0, 0x15, // 202: DRW V0, V1, 5
+        0xC2, 0x0F, // 204: RND V2, 0x0F
+        0xE2, 0xA1, // 206: SKNP V2
+        0x71, 0x01, // 208: ADD V1, 1
+        0x12, 0x00, // 20A: JP 0x200
+    ];
+    for (name, quirks) in Quirks::PRESETS {
+        let mut left = side(name, &rom, quirks, Box::new(Interpreter));
+        let mut right = side(name, &rom, quirks, Box::new(BlockJit::new()));
+        let divergence = lockstep(&mut left, &mut right, 120, |frame| 1 << (frame / 8 % 16));
+        assert!(divergence.is_none(), "{}: {}", name, divergence.unwrap());
+    }
+}
+
+#[test]
+fn different_seeds_diverge_on_the_first_random_number() {
+    let rom = [0xC0, 0xFF, 0x12, 0x00]; // 200: RND V0, 0xFF, JP 0x200
+    let mut left = side("left", &rom, Quirks::default(), Box::new(Interpreter));
+    let mut right = side("right", &rom, Quirks::default(), Box::new(Interpreter));
+    right.emu.seed_rng(1);
+    let divergence = lockstep(&mut left, &mut right, 10, |_| 0).unwrap();
+    assert_eq!(divergence.addrs, (0x200, 0x200));
+    assert_eq!(divergence.differences.len(), 1);
+    assert!(divergence.differences[0].starts_with("V0: "));
+}
+
+#[test]
+fn a_fault_on_one_side_diverges() {
+    let rom = [0xE0, 0xA1, 0x12, 0x00]; // 200: SKNP V0, JP 0x200
+    let mut left = side("left", &rom, Quirks::default(), Box::new(Interpreter));
+    let mut right = side("right", &rom, Quirks::default(), Box::new(BlockJit::new()));
+    right.emu.set_register(0, 0x10);
+    let divergence = lockstep(&mut left, &mut right, 10, |_| 0).unwrap();
+    assert_eq!(divergence.instruction, 0);
+    assert_eq!(divergence.differences[0], "right faulted at PC 0x200 (key 0x10 doesn't exist), left did not");
+    assert!(divergence.differences.iter().any(|d| d.starts_with("PC: ")));
+
+    // The same fault on both sides isn't a divergence
+    let rom = [0x00, 0xEE]; // 200: RET
+    let mut left = side("left", &rom, Quirks::default(), Box::new(Interpreter));
+    let mut right = side("right", &rom, Quirks::default(), Box::new(BlockJit::new()));
+    assert!(lockstep(&mut left, &mut right, 10, |_| 0).is_none());
+}
+
+#[test]
+fn waiting_for_vblank_is_compared() {
+    let rom = [0xD0, 0x15, 0x12, 0x02]; // 200: DRW V0, V1, 5, JP 0x202
+    let mut left = side("left", &rom, Quirks::default(), Box::new(Interpreter));
+    let mut right = side("right", &rom, Quirks::default(), Box::new(Interpreter));
+    left.emu.set_display_wait(true);
+    // The draw waits for the first vblank on the left only
+    let divergence = lockstep(&mut left, &mut right, 10, |_| 0).unwrap();
+    assert_eq!(divergence.instruction, 0);
+    assert!(divergence.differences.iter().any(|d| d == "waiting for vblank: true vs false"), "{:?}", divergence.differences);
+}
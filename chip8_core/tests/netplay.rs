@@ -0,0 +1,160 @@
+//! Two netplay sessions on loopback UDP, with latency and packet loss simulated on top
+
+use std::net::UdpSocket;
+use std::ops::Range;
+
+use chip8_core::netplay::{NetplayConfig, Session, Transport, UdpTransport};
+use chip8_core::{Emu, Random, Silent, Xorshift};
+
+/// Holds packets back for a random number of ticks before sending them, and drops some
+struct Lossy {
+    inner: UdpTransport,
+    queue: Vec<(usize, Vec<u8>)>,
+    now: usize,
+    latency: Range<usize>,
+    loss_percent: u8,
+    rng: Xorshift,
+}
+impl Lossy {
+    /// Send the packets that are due
+    fn tick(&mut self) {
+        self.now += 1;
+        let (due, waiting) = std::mem::take(&mut self.queue).into_iter().partition(|(at, _)| *at <= self.now);
+        self.queue = waiting;
+        for (_, packet) in due {
+            self.inner.send(&packet);
+        }
+    }
+}
+impl Transport for Lossy {
+    fn send(&mut self, packet: &[u8]) {
+        if self.rng.next_u8() % 100 < self.loss_percent { return; }
+        let spread = (self.latency.end - self.latency.start).max(1) as u64;
+        let delay = self.latency.start + (self.rng.next_u64() % spread) as usize;
+        self.queue.push((self.now + delay, packet.to_vec()));
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> Option<usize> {
+        self.inner.recv(buf)
+    }
+}
+
+fn rom(name: &str) -> Vec<u8> {
+    std::fs::read(format!("{}/../roms/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap()
+}
+
+fn new_emu(rom: &[u8], seed: u64) -> Emu {
+    let mut emu = Emu::new();
+    emu.seed_rng(seed);
+    emu.set_beeper(Box::new(Silent));
+    emu.load_rom(rom).unwrap();
+    emu
+}
+
+/// Two sessions talking to each other, with `seeds` for their emulators
+fn pair(rom: &[u8], seeds: [u64; 2], config: NetplayConfig, latency: Range<usize>, loss_percent: u8) -> [Session<Lossy>; 2] {
+    let sockets = [(); 2].map(|_| UdpSocket::bind("127.0.0.1:0").unwrap());
+    let addrs = [0, 1].map(|i| sockets[i].local_addr().unwrap());
+    let mut sockets = sockets.into_iter();
+    [0, 1].map(|i| {
+        let transport = Lossy {
+            inner: UdpTransport::from_socket(sockets.next().unwrap(), addrs[1 - i]).unwrap(),
+            queue: Vec::new(),
+            now: 0,
+            latency: latency.clone(),
+            loss_percent,
+            rng: Xorshift::new(i as u64),
+        };
+        Session::new(new_emu(rom, seeds[i]), transport, config)
+    })
+}
+
+/// Each player's keys on `frame`: the first player on 1 and 4, the second on C and D,
+/// switching between up, down and nothing at their own pace
+fn keys(player: usize, frame: usize) -> u16 {
+    let (up, down, pace) = [(0x1, 0x4, 20), (0xC, 0xD, 17)][player];
+    match frame / pace % 3 {
+        0 => 1 << up,
+        1 => 1 << down,
+        _ => 0,
+    }
+}
+
+/// Play both sessions for `frames` frames, one frame per tick, until both have settled
+fn play(sessions: &mut [Session<Lossy>; 2], frames: usize) {
+    for _ in 0..frames * 10 {
+        for (player, session) in sessions.iter_mut().enumerate() {
+            match session.frame() < frames {
+                true => { session.advance(keys(player, session.frame())); },
+                false => { session.poll(); },
+            }
+        }
+        for session in sessions.iter_mut() {
+            session.transport_mut().tick();
+        }
+        if sessions.iter().all(|session| session.settled_frame() == frames) { return; }
+    }
+    panic!("sessions didn't settle");
+}
+
+/// The same game on one machine, each frame getting the keys pressed `delay` frames earlier
+fn reference(rom: &[u8], frames: usize, delay: usize) -> Emu {
+    let mut emu = new_emu(rom, 1);
+    for frame in 0..frames {
+        let keys = match frame.checked_sub(delay) {
+            Some(pressed) => keys(0, pressed) | keys(1, pressed),
+            None => 0,
+        };
+        for key in 0..16 {
+            emu.keypress(key, keys >> key & 1 != 0);
+        }
+        emu.run_frame().unwrap();
+    }
+    emu
+}
+
+#[test]
+fn rollback_matches_local_play() {
+    const FRAMES: usize = 600;
+    for name in ["PONG", "PONG2", "TANK"] {
+        let rom = rom(name);
+        let config = NetplayConfig { input_delay: 2, ..NetplayConfig::default() };
+        let mut sessions = pair(&rom, [1, 1], config, 1..7, 20);
+        play(&mut sessions, FRAMES);
+
+        let expected = reference(&rom, FRAMES, config.input_delay).snapshot().to_bytes();
+        for session in &sessions {
+            assert!(session.emu().snapshot().to_bytes() == expected, "{} diverged from local play", name);
+            assert!(session.rolled_back() > 0, "{} never mispredicted", name);
+            assert_eq!(session.desync(), None);
+        }
+    }
+}
+
+#[test]
+fn input_delay_covering_latency_needs_no_rollback() {
+    const FRAMES: usize = 300;
+    let rom = rom("PONG");
+    let config = NetplayConfig { input_delay: 4, ..NetplayConfig::default() };
+    let mut sessions = pair(&rom, [1, 1], config, 2..2, 0);
+    play(&mut sessions, FRAMES);
+
+    let expected = reference(&rom, FRAMES, config.input_delay).snapshot().to_bytes();
+    for session in &sessions {
+        assert!(session.emu().snapshot().to_bytes() == expected, "diverged from local play");
+        assert_eq!(session.rolled_back(), 0);
+    }
+}
+
+#[test]
+fn detects_desync() {
+    // Differently seeded random number generators
+    let config = NetplayConfig { hash_interval: 30, ..NetplayConfig::default() };
+    let mut sessions = pair(&rom("PONG"), [1, 2], config, 1..3, 0);
+    play(&mut sessions, 120);
+    for session in &sessions {
+        let desync = session.desync().expect("desync not detected");
+        assert_eq!(desync.frame, 30);
+        assert_ne!(desync.local, desync.remote);
+    }
+}
@@ -0,0 +1,159 @@
+use chip8_core::octo::{self, CompileError};
+
+fn compile(source: &str) -> Vec<u8> {
+    octo::compile(source).unwrap_or_else(|e| panic!("{}", e)).rom
+}
+
+fn error(source: &str) -> (usize, String) {
+    let CompileError { line, message } = octo::compile(source).unwrap_err();
+    (line, message)
+}
+
+/// The value of `:calc x { expr }`
+fn calc(expr: &str) -> f64 {
+    let program = octo::compile(&format!(": main\n:calc x {{ {} }}", expr)).unwrap_or_else(|e| panic!("{}: {}", expr, e));
+    program.constants["x"]
+}
+
+#[test]
+fn tokens() {
+    // Comments, and braces without spaces around them
+    let rom = compile("# Comment\n: main   # Entry\n  v0 := {1 + 2}\n\tv1 := 0x10 # v1 := 1");
+    assert_eq!(rom, [0x12, 0x02, 0x60, 0x03, 0x61, 0x10]);
+    assert_eq!(error(": main\n\n  v0 := 1 # ok\n  v0 := {1 +}"), (4, "unexpected end of expression".to_string()));
+}
+
+#[test]
+fn labels() {
+    let program = octo::compile("
+        : main
+            loop
+                sprite v0 v1 5
+                if v0 == 3 then jump done
+            again
+        : done
+            sub
+        : sub
+            i := data
+            return
+        : data 0xF0 0x90
+    ").unwrap();
+    assert_eq!(program.rom, [
+        0x12, 0x02, // 200: jump main
+        0xD0, 0x15, // 202: sprite v0 v1 5
+        0x40, 0x03, // 204: if v0 == 3 then
+        0x12, 0x0A, // 206: jump done
+        0x12, 0x02, // 208: again
+        0x22, 0x0C, // 20A: sub
+        0xA2, 0x10, // 20C: i := data
+        0x00, 0xEE, // 20E: return
+        0xF0, 0x90, // 210: data
+    ]);
+    assert_eq!(program.labels["done"], 0x20A);
+    assert_eq!(program.label_at(0x210), Some("data"));
+    assert_eq!(program.symbol_table(), "202 main\n20A done\n20C sub\n210 data\n");
+
+    assert_eq!(error(": main\n  jump nowhere"), (2, "undefined label `nowhere`".to_string()));
+    assert_eq!(error(": main\n: main"), (2, "label `main` is already defined".to_string()));
+    assert_eq!(error(": start\n  clear"), (2, "missing `main` label".to_string()));
+}
+
+#[test]
+fn calc_expressions() {
+    // No precedence, operators associate to the right
+    assert_eq!(calc("2 * 3 + 1"), 8.0);
+    assert_eq!(calc("( 2 * 3 ) + 1"), 7.0);
+    assert_eq!(calc("10 - 2 - 3"), 11.0);
+    assert_eq!(calc("1 << 4 | 1"), 32.0);
+    assert_eq!(calc("0xFF & ~ 0x0F"), 240.0);
+    assert_eq!(calc("7 % 4 min 2"), 1.0);
+    assert_eq!(calc("floor 7 / 2"), 3.5);
+    assert_eq!(calc("floor ( 7 / 2 )"), 3.0);
+    assert_eq!(calc("HERE"), 0x202 as f64);
+    assert_eq!(calc("main + 2"), 0x204 as f64);
+
+    let program = octo::compile(": main 0x12 0x34\n:calc peek { @ ( main + 1 ) }\n:const five 5\n:calc ten { five * 2 }").unwrap();
+    assert_eq!(program.constants["peek"], 0x34 as f64);
+    assert_eq!(program.constants["ten"], 10.0);
+
+    let error = |expr: &str| error(&format!(": main\n:calc x {{ {} }}", expr));
+    assert_eq!(error("1 +"), (2, "unexpected end of expression".to_string()));
+    assert_eq!(error("( 1 + 2"), (2, "unexpected end of expression".to_string()));
+    assert_eq!(error("1 )"), (2, "unexpected `)` in expression".to_string()));
+    assert_eq!(error("1 ?? 2"), (2, "unknown operator `??`".to_string()));
+    assert_eq!(error("nothing"), (2, "undefined name `nothing` in expression".to_string()));
+}
+
+#[test]
+fn calc_shifts_out_of_range() {
+    assert_eq!(calc("1 << 63"), i64::MIN as f64);
+    assert_eq!(calc("-8 >> 1"), -4.0);
+    for expr in ["1 << 64", "1 << 100", "1 >> 64", "1 << -1", "1 >> -3"] {
+        let (line, message) = error(&format!(": main\n:calc x {{ {} }}", expr));
+        assert_eq!(line, 2);
+        assert!(message.starts_with("shift by") && message.ends_with("is out of range"), "{}: {}", expr, message);
+    }
+}
+
+#[test]
+fn macros() {
+    let rom = compile("
+        :macro set-both a b { v0 := a v1 := b }
+        :macro clear-both { set-both 0 0 }
+        : main
+            set-both 1 2
+            clear-both
+    ");
+    assert_eq!(rom, [0x12, 0x02, 0x60, 0x01, 0x61, 0x02, 0x60, 0x00, 0x61, 0x00]);
+
+    // Errors inside a macro are reported where it's used
+    assert_eq!(error(":macro bad { v0 := }\n: main\n  bad").0, 3);
+}
+
+#[test]
+fn recursive_macros() {
+    let (line, message) = error(":macro forever { v0 += 1 forever }\n: main\n  forever");
+    assert_eq!(line, 3);
+    assert_eq!(message, "macro `forever` nested more than 64 deep, is it recursive?");
+
+    let (_, message) = error(":macro ping { pong }\n:macro pong { ping }\n: main ping");
+    assert!(message.contains("is it recursive?"), "{}", message);
+}
+
+#[test]
+fn org() {
+    let program = octo::compile(": main\n  jump data\n:org 0x208\n: data 1 2").unwrap();
+    assert_eq!(program.rom, [0x12, 0x02, 0x12, 0x08, 0, 0, 0, 0, 1, 2]);
+    assert_eq!(program.labels["data"], 0x208);
+
+    // Can go back and overwrite
+    assert_eq!(compile(": main 1 2 3\n:org 0x203\n 9"), [0x12, 0x02, 1, 9, 3]);
+
+    assert_eq!(error(": main\n:org 0x100\n  clear"), (3, "can't emit code below 0x200".to_string()));
+    assert_eq!(error(": main\n:org 0x10000"), (2, "`:org` address 65536 out of range".to_string()));
+    assert_eq!(error(": main\n:org -1"), (2, "`:org` address -1 out of range".to_string()));
+}
+
+#[test]
+fn org_at_the_top_of_memory() {
+    let program = octo::compile(": main\n:org 0xFFFE\n: last 7").unwrap();
+    assert_eq!(program.labels["last"], 0xFFFE);
+    assert_eq!(program.rom.len(), 0xFFFF - 0x200);
+    assert_eq!(program.rom.last(), Some(&7));
+
+    assert_eq!(error(": main\n:org 0xFFFF\n 7"), (3, "program runs past 0xFFFF".to_string()));
+    assert_eq!(error(": main\n:org 0xFFFE\n clear"), (3, "program runs past 0xFFFF".to_string()));
+    assert_eq!(error(": main\n:org 0xFFFF\n:next x v0 := 1"), (3, "program runs past 0xFFFF".to_string()));
+    assert_eq!(error(": main\n:org 0xFFFD\n:unpack 0 main"), (3, "program runs past 0xFFFF".to_string()));
+}
+
+#[test]
+fn error_lines() {
+    assert_eq!(error(": main\n  clear\n  :bogus"), (3, "unknown directive `:bogus`".to_string()));
+    assert_eq!(error(": main\n  v0 := 300"), (2, "value 300 doesn't fit in a byte".to_string()));
+    assert_eq!(error(": main\n  sprite v0 v1 16"), (2, "value 16 doesn't fit in a nibble".to_string()));
+    assert_eq!(error(": main\n  loop\n  clear"), (3, "`loop` without a matching `again`".to_string()));
+    assert_eq!(error(": main\n\n  end"), (3, "`end` without `if ... begin`".to_string()));
+    assert_eq!(error(": main\n  while v0 == 1"), (2, "`while` outside of a loop".to_string()));
+    assert_eq!(error(": main\n  v0 :="), (2, "unexpected end of file".to_string()));
+}
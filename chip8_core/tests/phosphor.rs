@@ -0,0 +1,82 @@
+use std::num::NonZeroU8;
+
+use chip8_core::constants::SCREEN_WIDTH;
+use chip8_core::phosphor::FULL;
+use chip8_core::{Framebuffer, Phosphor, PhosphorMode};
+
+/// A frame with only the top left pixel lit, or none
+fn frame(lit: bool) -> Framebuffer {
+    let mut frame = Framebuffer::new();
+    if lit { frame.draw_sprite(0, 0, &[0x80], false); }
+    frame
+}
+
+fn decay(frames: u8) -> PhosphorMode {
+    PhosphorMode::Decay(NonZeroU8::new(frames).unwrap())
+}
+
+/// The top left pixel's intensity after each frame, lit as in `pattern`
+fn intensities(mode: PhosphorMode, pattern: &[bool]) -> Vec<u8> {
+    let mut phosphor = Phosphor::new(mode);
+    pattern.iter().map(|lit| {
+        phosphor.push(&frame(*lit));
+        phosphor.intensity()[0]
+    }).collect()
+}
+
+#[test]
+fn parse() {
+    for (text, mode) in [("off", PhosphorMode::Off), ("blend", PhosphorMode::Blend), (" decay  6 ", decay(6))] {
+        assert_eq!(PhosphorMode::parse(text), Some(mode), "{}", text);
+        assert_eq!(PhosphorMode::parse(&mode.to_string()), Some(mode));
+    }
+    for text in ["", "on", "decay", "decay 0", "decay 256", "blend 2", "decay -1"] {
+        assert_eq!(PhosphorMode::parse(text), None, "{}", text);
+    }
+}
+
+#[test]
+fn off_follows_the_frame() {
+    assert_eq!(intensities(PhosphorMode::Off, &[true, false, true, false]), [FULL, 0, FULL, 0]);
+}
+
+#[test]
+fn blend_keeps_pixels_lit_for_a_frame() {
+    assert_eq!(intensities(PhosphorMode::Blend, &[true, false, true, false, false]), [FULL, FULL, FULL, FULL, 0]);
+    assert_eq!(intensities(PhosphorMode::Blend, &[false, true, false]), [0, FULL, FULL]);
+}
+
+#[test]
+fn decay_fades_out_over_n_frames() {
+    let faded = intensities(decay(4), &[true, false, false, false, false, false]);
+    assert_eq!(faded, [FULL, 191, 127, 63, 0, 0]);
+    // Relit at full intensity
+    assert_eq!(intensities(decay(4), &[true, false, true, false]), [FULL, 191, FULL, 191]);
+    // Gone the frame after with a single frame of decay
+    assert_eq!(intensities(decay(1), &[true, false]), [FULL, 0]);
+    assert_eq!(intensities(decay(255), &[true, false, false]), [FULL, 254, 253]);
+    // Exactly n frames, however many there are
+    for frames in [3, 10, 100, 255] {
+        let mut pattern = vec![false; frames as usize + 1];
+        pattern[0] = true;
+        let faded = intensities(decay(frames), &pattern);
+        assert!(faded[frames as usize - 1] > 0, "decay {}", frames);
+        assert_eq!(faded[frames as usize], 0, "decay {}", frames);
+    }
+}
+
+#[test]
+fn intensity_covers_every_pixel() {
+    let mut phosphor = Phosphor::new(decay(2));
+    let mut lit = Framebuffer::new();
+    lit.draw_sprite(SCREEN_WIDTH - 8, 31, &[0xFF], false);
+    phosphor.push(&lit);
+    phosphor.push(&Framebuffer::new());
+    let intensity = phosphor.intensity();
+    assert_eq!(intensity.iter().filter(|value| **value == 127).count(), 8);
+    assert_eq!(intensity[31 * SCREEN_WIDTH + SCREEN_WIDTH - 1], 127);
+
+    // Switching modes forgets the afterglow
+    phosphor.set_mode(PhosphorMode::Blend);
+    assert!(phosphor.intensity().iter().all(|value| *value == 0));
+}
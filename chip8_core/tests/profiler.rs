@@ -0,0 +1,81 @@
+use chip8_core::{Emu, Opcode};
+
+/// Calls a subroutine twice, then waits for a key
+const ROM: [u8; 12] = [
+    0x22, 0x08, // 200: CALL 0x208
+    0x22, 0x08, // 202: CALL 0x208
+    0xF0, 0x0A, // 204: LD V0, K
+    0x12, 0x04, // 206: JP 0x204
+    0x61, 0x01, // 208: LD V1, 1
+    0x00, 0xEE, // 20A: RET
+];
+
+fn profiled(rom: &[u8], display_wait: bool) -> Emu {
+    let mut emu = Emu::new();
+    emu.set_display_wait(display_wait);
+    emu.load_rom(rom).unwrap();
+    emu.enable_profiler();
+    emu
+}
+
+#[test]
+fn counts_per_address_opcode_and_subroutine() {
+    let mut emu = profiled(&ROM, false);
+    for _ in 0..6 {
+        emu.tick();
+    }
+    let profiler = emu.profiler().unwrap();
+    assert_eq!(profiler.total(), 6);
+    assert_eq!(profiler.address_stats(0x200).unwrap().count, 1);
+    assert_eq!(profiler.address_stats(0x208).unwrap().count, 2);
+    assert_eq!(profiler.address_stats(0x20A).unwrap().opcode, Opcode::Return);
+    assert_eq!(profiler.hot_addresses(2).iter().map(|(addr, _)| *addr).collect::<Vec<_>>(), [0x208, 0x20A]);
+    assert_eq!(profiler.opcode_counts(), [("Call", 2), ("Return", 2), ("SetToVal", 2)]);
+
+    let sub = profiler.subroutine_stats(0x208).unwrap();
+    assert_eq!((sub.calls, sub.exclusive, sub.inclusive), (2, 4, 4));
+    let main = profiler.subroutine_stats(0x200).unwrap();
+    assert_eq!((main.calls, main.exclusive, main.inclusive), (0, 2, 6));
+    assert_eq!(profiler.folded_stacks(), "main 2\nmain;sub_208 4\n");
+    assert!(profiler.report(3).starts_with("Total instructions: 6\n"));
+}
+
+#[test]
+fn waiting_for_a_key_counts_once() {
+    let mut emu = profiled(&ROM, false);
+    for _ in 0..100 {
+        emu.tick();
+    }
+    let profiler = emu.profiler().unwrap();
+    assert_eq!(profiler.total(), 6);
+    assert!(profiler.address_stats(0x204).is_none());
+
+    emu.keypress(5, true);
+    emu.tick();
+    let profiler = emu.profiler().unwrap();
+    assert_eq!(profiler.total(), 7);
+    assert_eq!(profiler.address_stats(0x204).unwrap().count, 1);
+}
+
+#[test]
+fn draws_blocked_on_the_vblank_count_once() {
+    // 200: DRW V0, V1, 1, JP 0x200
+    let mut emu = profiled(&[0xD0, 0x11, 0x12, 0x00], true);
+    for _ in 0..3 {
+        emu.run_frame().unwrap();
+    }
+    // The first frame's draw waits for its vblank, then one draw per frame
+    let profiler = emu.profiler().unwrap();
+    assert_eq!(profiler.address_stats(0x200).unwrap().count, 2);
+    assert_eq!(profiler.address_stats(0x202).unwrap().count, 2);
+    assert_eq!(profiler.total(), 4);
+}
+
+#[test]
+fn take_profiler_stops_profiling() {
+    let mut emu = profiled(&ROM, false);
+    emu.tick();
+    assert_eq!(emu.take_profiler().unwrap().total(), 1);
+    emu.tick();
+    assert!(emu.profiler().is_none());
+}
@@ -0,0 +1,100 @@
+use chip8_core::constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8_core::render::Rgba;
+use chip8_core::{Framebuffer, Image, Palette};
+
+const OFF: Rgba = [0x00, 0x00, 0x00, 0xFF];
+const ON: Rgba = [0xFF, 0xFF, 0xFF, 0xFF];
+
+/// An image drawn with `#` for lit pixels and `.` for unlit ones
+fn image(rows: &[&str]) -> Image {
+    let pixels = rows.iter().flat_map(|row| row.chars()).map(|c| if c == '#' { ON } else { OFF }).collect();
+    Image { width: rows[0].len(), height: rows.len(), pixels }
+}
+
+fn text(image: &Image) -> Vec<String> {
+    image.pixels.chunks(image.width).map(|row| row.iter().map(|p| if *p == ON { '#' } else { '.' }).collect()).collect()
+}
+
+#[test]
+fn framebuffer_colours() {
+    let mut screen = Framebuffer::new();
+    screen.draw_sprite(1, 0, &[0x80], false);
+    let palette = Palette::two([1, 2, 3, 4], [5, 6, 7, 8]);
+    let image = Image::from_framebuffer(&screen, &palette);
+    assert_eq!((image.width, image.height), (SCREEN_WIDTH, SCREEN_HEIGHT));
+    assert_eq!(image.pixels[..3], [[1, 2, 3, 4], [5, 6, 7, 8], [1, 2, 3, 4]]);
+    assert_eq!(image.to_rgba8()[..8], [1, 2, 3, 4, 5, 6, 7, 8]);
+
+    // The second plane selects the upper two colours
+    let mut plane = Framebuffer::new();
+    plane.draw_sprite(0, 0, &[0xC0], false);
+    let image = Image::from_planes(&[&screen, &plane], &Palette::MONOCHROME);
+    assert_eq!(image.pixels[..3], [Palette::MONOCHROME.colors[2], Palette::MONOCHROME.colors[3], OFF]);
+}
+
+#[test]
+fn shade_blends_off_and_on() {
+    let palette = Palette::two([0, 0, 0, 255], [255, 100, 10, 255]);
+    assert_eq!(palette.shade(0), [0, 0, 0, 255]);
+    assert_eq!(palette.shade(255), [255, 100, 10, 255]);
+    assert_eq!(palette.shade(128), [128, 50, 5, 255]);
+}
+
+#[test]
+fn nearest_neighbour() {
+    let scaled = image(&["#.", ".#"]).scale(3);
+    assert_eq!(text(&scaled), ["###...", "###...", "###...", "...###", "...###", "...###"]);
+}
+
+#[test]
+fn scale2x_smooths_diagonals() {
+    let scaled = image(&["#.", ".#"]).scale2x();
+    assert_eq!((scaled.width, scaled.height), (4, 4));
+    assert_eq!(text(&scaled), [
+        "##..",
+        "#.#.",
+        ".#.#",
+        "..##",
+    ]);
+}
+
+#[test]
+fn scale3x_smooths_diagonals() {
+    let scaled = image(&["#.", ".#"]).scale3x();
+    assert_eq!((scaled.width, scaled.height), (6, 6));
+    assert_eq!(text(&scaled), [
+        "###...",
+        "##.#..",
+        "#..##.",
+        ".##..#",
+        "..#.##",
+        "...###",
+    ]);
+}
+
+#[test]
+fn filters_leave_lone_pixels_and_lines_square() {
+    for rows in [&["...", ".#.", "..."][..], &["...", "###", "..."][..]] {
+        let source = image(rows);
+        assert_eq!(source.scale2x(), source.scale(2), "{:?}", rows);
+        assert_eq!(source.scale3x(), source.scale(3), "{:?}", rows);
+    }
+}
+
+#[test]
+fn scanlines_darken_odd_rows() {
+    let mut lines = image(&["##", "##", "##"]);
+    lines.scanlines(255);
+    assert_eq!(text(&lines), ["##", "..", "##"]);
+    // Alpha is left alone
+    assert_eq!(lines.pixels[2], OFF);
+
+    let mut half = image(&["#", "#"]);
+    half.scanlines(128);
+    assert_eq!(half.pixels[1], [127, 127, 127, 0xFF]);
+
+    // Nothing to darken in an empty image
+    let mut empty = image(&["#"]).scale(0);
+    empty.scanlines(255);
+    assert_eq!((empty.width, empty.height, empty.pixels.len()), (0, 0, 0));
+}
@@ -0,0 +1,67 @@
+use chip8_core::{Emu, Quirks, Random, Snapshot, SnapshotError, Xorshift};
+
+fn brix() -> Emu {
+    let rom = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/../roms/BRIX")).unwrap();
+    let mut emu = Emu::new();
+    emu.seed_rng(7);
+    emu.set_quirks(Quirks::CHIP8);
+    emu.load_rom(&rom).unwrap();
+    for frame in 0..120 {
+        emu.keypress(4, frame % 40 < 20);
+        emu.run_frame().unwrap();
+    }
+    emu
+}
+
+#[test]
+fn save_state_round_trip() {
+    let mut emu = brix();
+    let bytes = emu.snapshot().to_bytes();
+    assert_eq!(bytes.len(), Snapshot::SIZE);
+    let loaded = Snapshot::from_bytes(&bytes).unwrap();
+    assert_eq!(loaded, emu.snapshot());
+    assert_eq!(loaded.to_bytes(), bytes);
+
+    // Restoring the loaded state replays the same frames, random numbers included
+    let mut other = Emu::new();
+    other.restore(&loaded);
+    for _ in 0..300 {
+        emu.run_frame().unwrap();
+        other.run_frame().unwrap();
+    }
+    assert_eq!(other.snapshot(), emu.snapshot());
+    assert!(other.get_display().eq(emu.get_display()));
+}
+
+#[test]
+fn save_state_errors() {
+    let bytes = brix().snapshot().to_bytes();
+    assert_eq!(Snapshot::from_bytes(&bytes[1..]).unwrap_err(), SnapshotError::WrongSize);
+
+    let mut wrong = bytes.clone();
+    wrong[0] = b'X';
+    assert_eq!(Snapshot::from_bytes(&wrong).unwrap_err(), SnapshotError::NotSnapshot);
+
+    let mut wrong = bytes.clone();
+    wrong[4] = 99;
+    assert_eq!(Snapshot::from_bytes(&wrong).unwrap_err(), SnapshotError::Version(99));
+
+    // Stack pointer past the end of the stack
+    let mut wrong = bytes.clone();
+    wrong[9..11].copy_from_slice(&100u16.to_le_bytes());
+    assert_eq!(Snapshot::from_bytes(&wrong).unwrap_err(), SnapshotError::Corrupt);
+
+    // A saved generator stuck at zero
+    let mut wrong = bytes;
+    wrong[67..75].fill(0);
+    assert_eq!(Snapshot::from_bytes(&wrong).unwrap_err(), SnapshotError::Corrupt);
+}
+
+#[test]
+fn xorshift_save_state() {
+    assert_eq!(Xorshift::from_state(0), None);
+    let mut rng = Xorshift::new(3);
+    rng.next_u64();
+    let mut copy = Xorshift::from_state(rng.save_state().unwrap()).unwrap();
+    assert_eq!(copy.next_u64(), rng.next_u64());
+}
@@ -0,0 +1,69 @@
+use chip8_core::Emu;
+
+/// Runs the code at 0x210 once, then rewrites it with `LD [I], VX` and runs it again
+const STORE_ROM: [u8; 20] = [
+    0x22, 0x10, // 200: CALL 0x210
+    0x60, 0x6B, // 202: LD V0, 0x6B
+    0x61, 0x42, // 204: LD V1, 0x42
+    0xA2, 0x10, // 206: LD I, 0x210
+    0xF1, 0x55, // 208: LD [I], V1
+    0x22, 0x10, // 20A: CALL 0x210
+    0x12, 0x0C, // 20C: JP 0x20C
+    0x00, 0x00, // 20E: NOP
+    0x6B, 0x01, // 210: LD VB, 0x01 (becomes LD VB, 0x42)
+    0x00, 0xEE, // 212: RET
+];
+
+/// Rewrites only the second byte of the instruction at 0x210
+const ODD_STORE_ROM: [u8; 20] = [
+    0x22, 0x10, // 200: CALL 0x210
+    0x60, 0x42, // 202: LD V0, 0x42
+    0xA2, 0x11, // 204: LD I, 0x211
+    0xF0, 0x55, // 206: LD [I], V0
+    0x22, 0x10, // 208: CALL 0x210
+    0x12, 0x0A, // 20A: JP 0x20A
+    0x00, 0x00, // 20C: NOP
+    0x00, 0x00, // 20E: NOP
+    0x6B, 0x01, // 210: LD VB, 0x01 (becomes LD VB, 0x42)
+    0x00, 0xEE, // 212: RET
+];
+
+fn run(rom: &[u8], predecode: bool) -> Emu {
+    let mut emu = Emu::new();
+    emu.set_predecode(predecode);
+    emu.load_rom(rom).unwrap();
+    for _ in 0..32 {
+        emu.tick();
+    }
+    emu
+}
+
+#[test]
+fn store_invalidates_predecoded_instruction() {
+    for predecode in [false, true] {
+        let emu = run(&STORE_ROM, predecode);
+        assert_eq!(emu.registers()[0xB], 0x42, "predecode: {}", predecode);
+    }
+}
+
+#[test]
+fn store_into_second_byte_invalidates_predecoded_instruction() {
+    for predecode in [false, true] {
+        let emu = run(&ODD_STORE_ROM, predecode);
+        assert_eq!(emu.registers()[0xB], 0x42, "predecode: {}", predecode);
+    }
+}
+
+#[test]
+fn poke_and_reload_invalidate_predecoded_instructions() {
+    let mut emu = run(&STORE_ROM, true);
+    emu.poke(0x211, 0x07);
+    emu.set_pc(0x210);
+    emu.tick();
+    assert_eq!(emu.registers()[0xB], 0x07);
+
+    emu.load_rom(&STORE_ROM).unwrap();
+    emu.set_pc(0x210);
+    emu.tick();
+    assert_eq!(emu.registers()[0xB], 0x01);
+}
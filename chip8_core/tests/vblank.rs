@@ -0,0 +1,91 @@
+use chip8_core::backend::{Backend, Interpreter};
+use chip8_core::constants::TICKS_PER_FRAME;
+use chip8_core::{Emu, Quirks};
+
+/// 200: DRW V0, V1, 1, ADD V2, 1, JP 0x200, counting draws in V2
+const DRAW_LOOP: [u8; 6] = [0xD0, 0x11, 0x72, 0x01, 0x12, 0x00];
+
+fn emu(rom: &[u8], display_wait: bool) -> Emu {
+    let mut emu = Emu::new();
+    emu.set_display_wait(display_wait);
+    emu.load_rom(rom).unwrap();
+    emu
+}
+
+#[test]
+fn draw_blocks_until_the_next_vblank() {
+    let mut emu = emu(&[0xD0, 0x11, 0xD0, 0x11], true);
+    // No vblank yet, so the draw waits and is retried afterwards
+    emu.tick();
+    assert!(emu.is_waiting_vblank());
+    assert_eq!(emu.pc(), 0x200);
+    assert!(emu.get_display().all(|pixel| !pixel));
+    // Ticking while blocked does nothing
+    emu.tick();
+    assert_eq!(emu.pc(), 0x200);
+
+    emu.vblank();
+    assert!(!emu.is_waiting_vblank());
+    emu.tick();
+    assert_eq!(emu.pc(), 0x202);
+    assert!(emu.framebuffer().pixel(0, 0));
+    // The vblank is used up, so the next draw waits for another
+    emu.tick();
+    assert!(emu.is_waiting_vblank());
+    assert_eq!(emu.pc(), 0x202);
+    emu.vblank();
+    emu.tick();
+    assert_eq!(emu.pc(), 0x204);
+    assert!(!emu.framebuffer().pixel(0, 0));
+}
+
+#[test]
+fn one_draw_per_frame() {
+    let mut emu = emu(&DRAW_LOOP, true);
+    for _ in 0..10 {
+        emu.run_frame().unwrap();
+    }
+    // The first frame's draw waits for its vblank
+    assert_eq!(emu.registers()[2], 9);
+
+    // Without the quirk every draw goes straight ahead
+    let mut emu = self::emu(&DRAW_LOOP, false);
+    for _ in 0..10 {
+        emu.run_frame().unwrap();
+    }
+    assert_eq!(emu.registers()[2] as usize, 10 * TICKS_PER_FRAME / 3);
+}
+
+#[test]
+fn backends_stop_early_while_blocked() {
+    let mut emu = emu(&DRAW_LOOP, true);
+    emu.vblank();
+    // Draw, add, jump, then the second draw, which blocks
+    assert_eq!(Interpreter.run(&mut emu, TICKS_PER_FRAME), Ok(4));
+    assert!(emu.is_waiting_vblank());
+    assert_eq!(Interpreter.run(&mut emu, TICKS_PER_FRAME), Ok(0));
+}
+
+#[test]
+fn disabling_the_quirk_releases_a_blocked_draw() {
+    let mut emu = emu(&DRAW_LOOP, true);
+    emu.tick();
+    assert!(emu.is_waiting_vblank());
+    emu.set_quirks(Quirks { display_wait: false, ..Quirks::CHIP8 });
+    assert!(!emu.is_waiting_vblank());
+    emu.tick();
+    assert_eq!(emu.pc(), 0x202);
+}
+
+#[test]
+fn vblank_wait_survives_save_states() {
+    let mut emu = emu(&DRAW_LOOP, true);
+    emu.tick();
+    let snapshot = emu.snapshot();
+    let mut restored = Emu::new();
+    restored.restore(&snapshot);
+    assert!(restored.is_waiting_vblank());
+    restored.vblank();
+    restored.tick();
+    assert_eq!(restored.pc(), 0x202);
+}
@@ -0,0 +1,236 @@
+/* Generated from src/lib.rs by cbindgen, regenerate with `CHIP8_BLESS=1 cargo test` */
+
+#ifndef CHIP8_H
+#define CHIP8_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Screen width in pixels
+ */
+#define CHIP8_SCREEN_WIDTH 64
+
+/**
+ * Screen height in pixels
+ */
+#define CHIP8_SCREEN_HEIGHT 32
+
+/**
+ * Number of keys on the keypad, `0x0` to `0xF`
+ */
+#define CHIP8_NUM_KEYS 16
+
+/**
+ * Largest ROM that fits in memory
+ */
+#define CHIP8_MAX_ROM_SIZE 3584
+
+/**
+ * Result of an API call
+ */
+typedef enum Chip8Status {
+  CHIP8_STATUS_OK = 0,
+  /**
+   * A required pointer was null
+   */
+  CHIP8_STATUS_NULL_POINTER,
+  /**
+   * The ROM is larger than `CHIP8_MAX_ROM_SIZE`
+   */
+  CHIP8_STATUS_ROM_TOO_LARGE,
+  /**
+   * Key index outside `0..CHIP8_NUM_KEYS`
+   */
+  CHIP8_STATUS_INVALID_KEY,
+  /**
+   * Unknown quirk preset
+   */
+  CHIP8_STATUS_INVALID_PRESET,
+  /**
+   * The output buffer is too small
+   */
+  CHIP8_STATUS_BUFFER_TOO_SMALL,
+  /**
+   * The emulator panicked, its state is unspecified until restored from a snapshot
+   */
+  CHIP8_STATUS_PANIC,
+  /**
+   * The ROM hit an instruction that isn't part of the instruction set
+   */
+  CHIP8_STATUS_UNKNOWN_OPCODE,
+  /**
+   * The program counter ran past the end of RAM
+   */
+  CHIP8_STATUS_PC_OUT_OF_RANGE,
+  /**
+   * The ROM called a subroutine with every return address in use
+   */
+  CHIP8_STATUS_STACK_OVERFLOW,
+  /**
+   * The ROM returned with nothing on the stack
+   */
+  CHIP8_STATUS_STACK_UNDERFLOW,
+  /**
+   * The ROM read or wrote past the end of RAM
+   */
+  CHIP8_STATUS_MEMORY_OUT_OF_RANGE,
+  /**
+   * The ROM checked a key register holding more than `0xF`
+   */
+  CHIP8_STATUS_KEY_OUT_OF_RANGE,
+} Chip8Status;
+
+/**
+ * An emulator
+ */
+typedef struct Chip8 Chip8;
+
+/**
+ * A saved emulator state
+ */
+typedef struct Chip8Snapshot Chip8Snapshot;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Create an emulator, with no ROM loaded and the default quirks
+ * Free it with `chip8_free`
+ */
+struct Chip8 *chip8_new(void);
+
+/**
+ * Free an emulator, null is ignored
+ *
+ * # Safety
+ * `chip8` must be null or come from `chip8_new`, and not be used again
+ */
+void chip8_free(struct Chip8 *chip8);
+
+/**
+ * Copy `len` bytes of ROM into memory at 0x200
+ *
+ * # Safety
+ * `chip8` must be a live emulator, `rom` must point to `len` readable bytes
+ */
+enum Chip8Status chip8_load_rom(struct Chip8 *chip8, const uint8_t *rom, size_t len);
+
+/**
+ * Switch to a quirk preset: "default", "chip8", "schip" or "xochip"
+ *
+ * # Safety
+ * `chip8` must be a live emulator, `preset` a NUL-terminated string
+ */
+enum Chip8Status chip8_set_quirks(struct Chip8 *chip8, const char *preset);
+
+/**
+ * Reseed the random number generator, making `RND` deterministic
+ *
+ * # Safety
+ * `chip8` must be a live emulator
+ */
+enum Chip8Status chip8_seed_rng(struct Chip8 *chip8, uint64_t seed);
+
+/**
+ * Execute one instruction
+ *
+ * A fault leaves the emulator at the faulting instruction, nothing else changes.
+ *
+ * # Safety
+ * `chip8` must be a live emulator
+ */
+enum Chip8Status chip8_tick(struct Chip8 *chip8);
+
+/**
+ * Tick the timers and signal the vblank, call once per 60Hz frame when stepping with `chip8_tick`
+ *
+ * # Safety
+ * `chip8` must be a live emulator
+ */
+enum Chip8Status chip8_tick_timers(struct Chip8 *chip8);
+
+/**
+ * Run a whole 60Hz frame: the frame's instructions, then the timers
+ *
+ * A fault stops the frame at the faulting instruction without ticking the timers.
+ *
+ * # Safety
+ * `chip8` must be a live emulator
+ */
+enum Chip8Status chip8_run_frame(struct Chip8 *chip8);
+
+/**
+ * Press or release a key, `0x0` to `0xF`
+ *
+ * # Safety
+ * `chip8` must be a live emulator
+ */
+enum Chip8Status chip8_keypress(struct Chip8 *chip8, size_t key, bool pressed);
+
+/**
+ * Copy the screen into `pixels`, one byte per pixel (1 lit, 0 unlit), row by row
+ * `len` must be at least `CHIP8_SCREEN_WIDTH * CHIP8_SCREEN_HEIGHT`
+ *
+ * # Safety
+ * `chip8` must be a live emulator, `pixels` must point to `len` writable bytes
+ */
+enum Chip8Status chip8_framebuffer(const struct Chip8 *chip8, uint8_t *pixels, size_t len);
+
+/**
+ * Counter that changes whenever the screen does, to skip redrawing unchanged frames
+ *
+ * # Safety
+ * `chip8` must be a live emulator, `version` writable
+ */
+enum Chip8Status chip8_display_version(const struct Chip8 *chip8, uint64_t *version);
+
+/**
+ * Whether the buzzer should be sounding, i.e. the sound timer is running
+ *
+ * # Safety
+ * `chip8` must be a live emulator, `active` writable
+ */
+enum Chip8Status chip8_sound_active(const struct Chip8 *chip8, bool *active);
+
+/**
+ * Capture the emulator's state into `*snapshot`, freed with `chip8_snapshot_free`
+ *
+ * # Safety
+ * `chip8` must be a live emulator, `snapshot` writable
+ */
+enum Chip8Status chip8_snapshot_save(const struct Chip8 *chip8, struct Chip8Snapshot **snapshot);
+
+/**
+ * Return the emulator to a saved state, which can be restored any number of times
+ *
+ * # Safety
+ * `chip8` must be a live emulator, `snapshot` a live snapshot
+ */
+enum Chip8Status chip8_snapshot_restore(struct Chip8 *chip8, const struct Chip8Snapshot *snapshot);
+
+/**
+ * Free a snapshot, null is ignored
+ *
+ * # Safety
+ * `snapshot` must be null or come from `chip8_snapshot_save`, and not be used again
+ */
+void chip8_snapshot_free(struct Chip8Snapshot *snapshot);
+
+/**
+ * A description of `status`, as a static NUL-terminated string
+ *
+ * Takes any `int`, as a value outside `enum Chip8Status` can't be passed as one safely.
+ * Unknown values are described as "unknown status".
+ */
+const char *chip8_status_message(int status);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CHIP8_H */
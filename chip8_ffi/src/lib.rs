@@ -0,0 +1,329 @@
+//! C ABI for embedding the emulator, declared in `include/chip8.h`
+//!
+//! Emulators and snapshots are opaque handles, created and freed through this API.
+//! Functions report failure with a `Chip8Status` rather than panicking across the boundary.
+//! A ROM that faults (e.g. a stack underflow) gets the fault's own status, with the emulator
+//! left at the faulting instruction and still usable. Any other panic is caught as a last resort
+//! and returned as `CHIP8_STATUS_PANIC`, after which the emulator's state is unspecified and it
+//! should be reset with `chip8_snapshot_restore` or freed.
+
+use std::ffi::{c_char, c_int, CStr};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use chip8_core::constants::{MAX_ROM_SIZE, NUM_KEYS, SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8_core::{Emu, Fault, Quirks, Silent, Snapshot};
+
+/// Screen width in pixels
+pub const CHIP8_SCREEN_WIDTH: usize = 64;
+/// Screen height in pixels
+pub const CHIP8_SCREEN_HEIGHT: usize = 32;
+/// Number of keys on the keypad, `0x0` to `0xF`
+pub const CHIP8_NUM_KEYS: usize = 16;
+/// Largest ROM that fits in memory
+pub const CHIP8_MAX_ROM_SIZE: usize = 3584;
+
+// cbindgen can't see the core's constants, so they're repeated above
+const _: () = assert!(CHIP8_SCREEN_WIDTH == SCREEN_WIDTH && CHIP8_SCREEN_HEIGHT == SCREEN_HEIGHT);
+const _: () = assert!(CHIP8_NUM_KEYS == NUM_KEYS);
+const _: () = assert!(CHIP8_MAX_ROM_SIZE == MAX_ROM_SIZE);
+
+/// Result of an API call
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip8Status {
+    Ok = 0,
+    /// A required pointer was null
+    NullPointer,
+    /// The ROM is larger than `CHIP8_MAX_ROM_SIZE`
+    RomTooLarge,
+    /// Key index outside `0..CHIP8_NUM_KEYS`
+    InvalidKey,
+    /// Unknown quirk preset
+    InvalidPreset,
+    /// The output buffer is too small
+    BufferTooSmall,
+    /// The emulator panicked, its state is unspecified until restored from a snapshot
+    Panic,
+    /// The ROM hit an instruction that isn't part of the instruction set
+    UnknownOpcode,
+    /// The program counter ran past the end of RAM
+    PcOutOfRange,
+    /// The ROM called a subroutine with every return address in use
+    StackOverflow,
+    /// The ROM returned with nothing on the stack
+    StackUnderflow,
+    /// The ROM read or wrote past the end of RAM
+    MemoryOutOfRange,
+    /// The ROM checked a key register holding more than `0xF`
+    KeyOutOfRange,
+}
+
+impl From<Fault> for Chip8Status {
+    fn from(fault: Fault) -> Self {
+        match fault {
+            Fault::UnknownOpcode { .. } => Chip8Status::UnknownOpcode,
+            Fault::PcOutOfRange(_) => Chip8Status::PcOutOfRange,
+            Fault::StackOverflow => Chip8Status::StackOverflow,
+            Fault::StackUnderflow => Chip8Status::StackUnderflow,
+            Fault::MemoryOutOfRange { .. } => Chip8Status::MemoryOutOfRange,
+            Fault::InvalidKey(_) => Chip8Status::KeyOutOfRange,
+        }
+    }
+}
+
+/// An emulator
+pub struct Chip8 {
+    emu: Emu,
+}
+
+/// A saved emulator state
+pub struct Chip8Snapshot {
+    snapshot: Snapshot,
+}
+
+/// Run `f`, turning a panic into `Chip8Status::Panic`
+///
+/// Faults come back as their own status, this only catches bugs in the emulator.
+fn guard(f: impl FnOnce() -> Chip8Status) -> Chip8Status {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or(Chip8Status::Panic)
+}
+
+// ================ //
+// == LIFE CYCLE == //
+// ================ //
+
+/// Create an emulator, with no ROM loaded and the default quirks
+/// Free it with `chip8_free`
+#[no_mangle]
+pub extern "C" fn chip8_new() -> *mut Chip8 {
+    let mut emu = Emu::new();
+    // Hosts poll `chip8_sound_active` rather than having beeps printed
+    emu.set_beeper(Box::new(Silent));
+    Box::into_raw(Box::new(Chip8 { emu }))
+}
+
+/// Free an emulator, null is ignored
+///
+/// # Safety
+/// `chip8` must be null or come from `chip8_new`, and not be used again
+#[no_mangle]
+pub unsafe extern "C" fn chip8_free(chip8: *mut Chip8) {
+    if !chip8.is_null() {
+        drop(Box::from_raw(chip8));
+    }
+}
+
+/// Copy `len` bytes of ROM into memory at 0x200
+///
+/// # Safety
+/// `chip8` must be a live emulator, `rom` must point to `len` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_rom(chip8: *mut Chip8, rom: *const u8, len: usize) -> Chip8Status {
+    let (Some(chip8), false) = (chip8.as_mut(), rom.is_null()) else { return Chip8Status::NullPointer; };
+    match chip8.emu.load_rom(std::slice::from_raw_parts(rom, len)) {
+        Ok(()) => Chip8Status::Ok,
+        Err(_) => Chip8Status::RomTooLarge,
+    }
+}
+
+/// Switch to a quirk preset: "default", "chip8", "schip" or "xochip"
+///
+/// # Safety
+/// `chip8` must be a live emulator, `preset` a NUL-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn chip8_set_quirks(chip8: *mut Chip8, preset: *const c_char) -> Chip8Status {
+    let (Some(chip8), false) = (chip8.as_mut(), preset.is_null()) else { return Chip8Status::NullPointer; };
+    let quirks = CStr::from_ptr(preset).to_str().ok().and_then(Quirks::preset);
+    let Some(quirks) = quirks else { return Chip8Status::InvalidPreset; };
+    chip8.emu.set_quirks(quirks);
+    Chip8Status::Ok
+}
+
+/// Reseed the random number generator, making `RND` deterministic
+///
+/// # Safety
+/// `chip8` must be a live emulator
+#[no_mangle]
+pub unsafe extern "C" fn chip8_seed_rng(chip8: *mut Chip8, seed: u64) -> Chip8Status {
+    let Some(chip8) = chip8.as_mut() else { return Chip8Status::NullPointer; };
+    chip8.emu.seed_rng(seed);
+    Chip8Status::Ok
+}
+
+// ============= //
+// == RUNNING == //
+// ============= //
+
+/// Execute one instruction
+///
+/// A fault leaves the emulator at the faulting instruction, nothing else changes.
+///
+/// # Safety
+/// `chip8` must be a live emulator
+#[no_mangle]
+pub unsafe extern "C" fn chip8_tick(chip8: *mut Chip8) -> Chip8Status {
+    let Some(chip8) = chip8.as_mut() else { return Chip8Status::NullPointer; };
+    guard(|| {
+        match chip8.emu.try_tick() {
+            Ok(()) => Chip8Status::Ok,
+            Err(fault) => fault.into(),
+        }
+    })
+}
+
+/// Tick the timers and signal the vblank, call once per 60Hz frame when stepping with `chip8_tick`
+///
+/// # Safety
+/// `chip8` must be a live emulator
+#[no_mangle]
+pub unsafe extern "C" fn chip8_tick_timers(chip8: *mut Chip8) -> Chip8Status {
+    let Some(chip8) = chip8.as_mut() else { return Chip8Status::NullPointer; };
+    chip8.emu.tick_timers();
+    chip8.emu.vblank();
+    Chip8Status::Ok
+}
+
+/// Run a whole 60Hz frame: the frame's instructions, then the timers
+///
+/// A fault stops the frame at the faulting instruction without ticking the timers.
+///
+/// # Safety
+/// `chip8` must be a live emulator
+#[no_mangle]
+pub unsafe extern "C" fn chip8_run_frame(chip8: *mut Chip8) -> Chip8Status {
+    let Some(chip8) = chip8.as_mut() else { return Chip8Status::NullPointer; };
+    guard(|| {
+        match chip8.emu.run_frame() {
+            Ok(()) => Chip8Status::Ok,
+            Err(fault) => fault.into(),
+        }
+    })
+}
+
+// =========== //
+// == INPUT == //
+// =========== //
+
+/// Press or release a key, `0x0` to `0xF`
+///
+/// # Safety
+/// `chip8` must be a live emulator
+#[no_mangle]
+pub unsafe extern "C" fn chip8_keypress(chip8: *mut Chip8, key: usize, pressed: bool) -> Chip8Status {
+    let Some(chip8) = chip8.as_mut() else { return Chip8Status::NullPointer; };
+    if key >= CHIP8_NUM_KEYS { return Chip8Status::InvalidKey; }
+    chip8.emu.keypress(key, pressed);
+    Chip8Status::Ok
+}
+
+// ============ //
+// == OUTPUT == //
+// ============ //
+
+/// Copy the screen into `pixels`, one byte per pixel (1 lit, 0 unlit), row by row
+/// `len` must be at least `CHIP8_SCREEN_WIDTH * CHIP8_SCREEN_HEIGHT`
+///
+/// # Safety
+/// `chip8` must be a live emulator, `pixels` must point to `len` writable bytes
+#[no_mangle]
+pub unsafe extern "C" fn chip8_framebuffer(chip8: *const Chip8, pixels: *mut u8, len: usize) -> Chip8Status {
+    let (Some(chip8), false) = (chip8.as_ref(), pixels.is_null()) else { return Chip8Status::NullPointer; };
+    if len < CHIP8_SCREEN_WIDTH * CHIP8_SCREEN_HEIGHT { return Chip8Status::BufferTooSmall; }
+    let pixels = std::slice::from_raw_parts_mut(pixels, len);
+    let screen = chip8.emu.framebuffer();
+    for y in 0..CHIP8_SCREEN_HEIGHT {
+        for x in 0..CHIP8_SCREEN_WIDTH {
+            pixels[y * CHIP8_SCREEN_WIDTH + x] = screen.pixel(x, y) as u8;
+        }
+    }
+    Chip8Status::Ok
+}
+
+/// Counter that changes whenever the screen does, to skip redrawing unchanged frames
+///
+/// # Safety
+/// `chip8` must be a live emulator, `version` writable
+#[no_mangle]
+pub unsafe extern "C" fn chip8_display_version(chip8: *const Chip8, version: *mut u64) -> Chip8Status {
+    let (Some(chip8), Some(version)) = (chip8.as_ref(), version.as_mut()) else { return Chip8Status::NullPointer; };
+    *version = chip8.emu.display_version();
+    Chip8Status::Ok
+}
+
+/// Whether the buzzer should be sounding, i.e. the sound timer is running
+///
+/// # Safety
+/// `chip8` must be a live emulator, `active` writable
+#[no_mangle]
+pub unsafe extern "C" fn chip8_sound_active(chip8: *const Chip8, active: *mut bool) -> Chip8Status {
+    let (Some(chip8), Some(active)) = (chip8.as_ref(), active.as_mut()) else { return Chip8Status::NullPointer; };
+    *active = chip8.emu.timers().1 > 0;
+    Chip8Status::Ok
+}
+
+// =============== //
+// == SNAPSHOTS == //
+// =============== //
+
+/// Capture the emulator's state into `*snapshot`, freed with `chip8_snapshot_free`
+///
+/// # Safety
+/// `chip8` must be a live emulator, `snapshot` writable
+#[no_mangle]
+pub unsafe extern "C" fn chip8_snapshot_save(chip8: *const Chip8, snapshot: *mut *mut Chip8Snapshot) -> Chip8Status {
+    let (Some(chip8), Some(out)) = (chip8.as_ref(), snapshot.as_mut()) else { return Chip8Status::NullPointer; };
+    *out = Box::into_raw(Box::new(Chip8Snapshot { snapshot: chip8.emu.snapshot() }));
+    Chip8Status::Ok
+}
+
+/// Return the emulator to a saved state, which can be restored any number of times
+///
+/// # Safety
+/// `chip8` must be a live emulator, `snapshot` a live snapshot
+#[no_mangle]
+pub unsafe extern "C" fn chip8_snapshot_restore(chip8: *mut Chip8, snapshot: *const Chip8Snapshot) -> Chip8Status {
+    let (Some(chip8), Some(snapshot)) = (chip8.as_mut(), snapshot.as_ref()) else { return Chip8Status::NullPointer; };
+    chip8.emu.restore(&snapshot.snapshot);
+    Chip8Status::Ok
+}
+
+/// Free a snapshot, null is ignored
+///
+/// # Safety
+/// `snapshot` must be null or come from `chip8_snapshot_save`, and not be used again
+#[no_mangle]
+pub unsafe extern "C" fn chip8_snapshot_free(snapshot: *mut Chip8Snapshot) {
+    if !snapshot.is_null() {
+        drop(Box::from_raw(snapshot));
+    }
+}
+
+/// A description of `status`, as a static NUL-terminated string
+///
+/// Takes any `int`, as a value outside `enum Chip8Status` can't be passed as one safely.
+/// Unknown values are described as "unknown status".
+#[no_mangle]
+pub extern "C" fn chip8_status_message(status: c_int) -> *const c_char {
+    use Chip8Status::*;
+    let known = [
+        Ok, NullPointer, RomTooLarge, InvalidKey, InvalidPreset, BufferTooSmall, Panic,
+        UnknownOpcode, PcOutOfRange, StackOverflow, StackUnderflow, MemoryOutOfRange, KeyOutOfRange,
+    ];
+    let message: &'static CStr = match known.into_iter().find(|known| *known as c_int == status) {
+        Some(Ok) => c"ok",
+        Some(NullPointer) => c"null pointer",
+        Some(RomTooLarge) => c"ROM too large",
+        Some(InvalidKey) => c"invalid key",
+        Some(InvalidPreset) => c"unknown quirk preset",
+        Some(BufferTooSmall) => c"buffer too small",
+        Some(Panic) => c"emulator panicked",
+        Some(UnknownOpcode) => c"unknown opcode",
+        Some(PcOutOfRange) => c"program counter past the end of RAM",
+        Some(StackOverflow) => c"stack overflow",
+        Some(StackUnderflow) => c"stack underflow",
+        Some(MemoryOutOfRange) => c"memory access past the end of RAM",
+        Some(KeyOutOfRange) => c"key register out of range",
+        None => c"unknown status",
+    };
+    message.as_ptr()
+}
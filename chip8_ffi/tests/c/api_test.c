@@ -0,0 +1,125 @@
+/* Exercises the C API, built and run by tests/c_api.rs */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "chip8.h"
+
+#define CHECK(cond)                                                    \
+    do {                                                               \
+        if (!(cond)) {                                                 \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__,     \
+                    __LINE__, #cond);                                  \
+            return 1;                                                  \
+        }                                                              \
+    } while (0)
+
+#define CHECK_STATUS(call, expected)                                   \
+    do {                                                               \
+        Chip8Status status = (call);                                   \
+        if (status != (expected)) {                                    \
+            fprintf(stderr, "%s:%d: %s returned \"%s\"\n", __FILE__,   \
+                    __LINE__, #call, chip8_status_message(status));    \
+            return 1;                                                  \
+        }                                                              \
+    } while (0)
+
+#define CHECK_OK(call) CHECK_STATUS(call, CHIP8_STATUS_OK)
+
+/* Starts the sound timer and draws a "0" at (5, 0) */
+static const uint8_t ROM[] = {
+    0x60, 0x05, /* 200: LD V0, 5 */
+    0xF0, 0x18, /* 202: LD ST, V0 */
+    0x61, 0x00, /* 204: LD V1, 0 */
+    0xF1, 0x29, /* 206: LD F, V1 */
+    0xD0, 0x15, /* 208: DRW V0, V1, 5 */
+    0x12, 0x0A, /* 20A: JP 0x20A */
+};
+
+/* Returns from a subroutine that was never called */
+static const uint8_t BAD_ROM[] = {0x00, 0xEE};
+
+static uint8_t pixels[CHIP8_SCREEN_WIDTH * CHIP8_SCREEN_HEIGHT];
+
+static int pixel(int x, int y) {
+    return pixels[y * CHIP8_SCREEN_WIDTH + x];
+}
+
+int main(void) {
+    Chip8 *chip8 = chip8_new();
+    CHECK(chip8 != NULL);
+    CHECK_OK(chip8_seed_rng(chip8, 0));
+    CHECK_OK(chip8_set_quirks(chip8, "chip8"));
+    CHECK_STATUS(chip8_set_quirks(chip8, "nes"), CHIP8_STATUS_INVALID_PRESET);
+    CHECK_OK(chip8_load_rom(chip8, ROM, sizeof ROM));
+
+    /* Stepping */
+    bool sound;
+    CHECK_OK(chip8_tick(chip8));
+    CHECK_OK(chip8_tick(chip8));
+    CHECK_OK(chip8_sound_active(chip8, &sound));
+    CHECK(sound);
+    /* The chip8 preset waits for a vblank before drawing */
+    CHECK_OK(chip8_run_frame(chip8));
+    CHECK_OK(chip8_run_frame(chip8));
+
+    /* Screen */
+    uint64_t version;
+    CHECK_OK(chip8_display_version(chip8, &version));
+    CHECK_OK(chip8_framebuffer(chip8, pixels, sizeof pixels));
+    for (int x = 5; x < 9; x++) {
+        CHECK(pixel(x, 0) == 1 && pixel(x, 4) == 1);
+    }
+    CHECK(pixel(4, 0) == 0 && pixel(6, 1) == 0);
+    CHECK_STATUS(chip8_framebuffer(chip8, pixels, 10), CHIP8_STATUS_BUFFER_TOO_SMALL);
+
+    /* Snapshots: the sound timer runs out, then comes back on restore */
+    Chip8Snapshot *snapshot = NULL;
+    CHECK_OK(chip8_snapshot_save(chip8, &snapshot));
+    CHECK(snapshot != NULL);
+    for (int frame = 0; frame < 10; frame++) {
+        CHECK_OK(chip8_run_frame(chip8));
+    }
+    CHECK_OK(chip8_sound_active(chip8, &sound));
+    CHECK(!sound);
+    CHECK_OK(chip8_snapshot_restore(chip8, snapshot));
+    CHECK_OK(chip8_sound_active(chip8, &sound));
+    CHECK(sound);
+    memset(pixels, 0, sizeof pixels);
+    CHECK_OK(chip8_framebuffer(chip8, pixels, sizeof pixels));
+    CHECK(pixel(5, 0) == 1);
+
+    /* Input */
+    CHECK_OK(chip8_keypress(chip8, 0xF, true));
+    CHECK_OK(chip8_keypress(chip8, 0xF, false));
+    CHECK_STATUS(chip8_keypress(chip8, CHIP8_NUM_KEYS, true), CHIP8_STATUS_INVALID_KEY);
+
+    /* Errors */
+    static uint8_t too_large[CHIP8_MAX_ROM_SIZE + 1];
+    CHECK_STATUS(chip8_load_rom(chip8, too_large, sizeof too_large), CHIP8_STATUS_ROM_TOO_LARGE);
+    CHECK_STATUS(chip8_load_rom(chip8, NULL, 2), CHIP8_STATUS_NULL_POINTER);
+    CHECK_STATUS(chip8_tick(NULL), CHIP8_STATUS_NULL_POINTER);
+    CHECK_STATUS(chip8_sound_active(chip8, NULL), CHIP8_STATUS_NULL_POINTER);
+
+    /* A fault comes back as its own status, leaving the emulator usable at the faulting instruction */
+    Chip8 *bad = chip8_new();
+    CHECK_OK(chip8_load_rom(bad, BAD_ROM, sizeof BAD_ROM));
+    CHECK_STATUS(chip8_tick(bad), CHIP8_STATUS_STACK_UNDERFLOW);
+    CHECK_STATUS(chip8_run_frame(bad), CHIP8_STATUS_STACK_UNDERFLOW);
+    CHECK_OK(chip8_display_version(bad, &version));
+    CHECK_OK(chip8_snapshot_restore(bad, snapshot));
+    CHECK_OK(chip8_run_frame(bad));
+    chip8_free(bad);
+
+    chip8_snapshot_free(snapshot);
+    chip8_free(chip8);
+    chip8_free(NULL);
+
+    /* Any int can be described */
+    CHECK(strcmp(chip8_status_message(CHIP8_STATUS_PANIC), "emulator panicked") == 0);
+    CHECK(strcmp(chip8_status_message(CHIP8_STATUS_STACK_UNDERFLOW), "stack underflow") == 0);
+    CHECK(strcmp(chip8_status_message(-1), "unknown status") == 0);
+    CHECK(strcmp(chip8_status_message(1000), "unknown status") == 0);
+    printf("ok\n");
+    return 0;
+}
@@ -0,0 +1,36 @@
+//! Builds `tests/c/api_test.c` against the shared library and runs it
+//! Needs a C compiler (`cc`, or `$CC`).
+
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn c_program() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    // Test builds leave the library next to the test binaries, in `target/<profile>/deps`
+    let lib_dir = std::env::current_exe().unwrap().parent().unwrap().to_path_buf();
+    let exe = lib_dir.join("chip8_api_test");
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+
+    let compiled = Command::new(&cc)
+        .arg(dir.join("tests/c/api_test.c"))
+        .arg("-std=c99")
+        .args(["-Wall", "-Wextra", "-Werror"])
+        .arg("-I").arg(dir.join("include"))
+        .arg("-L").arg(&lib_dir)
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .arg("-lchip8_ffi")
+        .arg("-o").arg(&exe)
+        .status()
+        .unwrap_or_else(|err| panic!("running the C compiler ({}) failed: {}", cc, err));
+    assert!(compiled.success(), "compiling api_test.c failed");
+
+    // Cargo's library path can hold an older build of the library, which would win over the rpath
+    let output = Command::new(&exe).env("LD_LIBRARY_PATH", &lib_dir).output().unwrap();
+    assert!(
+        output.status.success(),
+        "api_test failed ({}):\n{}{}", output.status,
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr),
+    );
+}
@@ -0,0 +1,21 @@
+//! `include/chip8.h` must match what cbindgen generates from the source
+//! Set `CHIP8_BLESS=1` to regenerate it.
+
+use std::path::Path;
+
+#[test]
+fn header_is_up_to_date() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let config = cbindgen::Config::from_file(dir.join("cbindgen.toml")).unwrap();
+    let mut generated = Vec::new();
+    cbindgen::generate_with_config(dir, config).unwrap().write(&mut generated);
+    let generated = String::from_utf8(generated).unwrap();
+
+    let path = dir.join("include/chip8.h");
+    if std::env::var_os("CHIP8_BLESS").is_some() {
+        std::fs::write(&path, &generated).unwrap();
+        return;
+    }
+    let current = std::fs::read_to_string(&path).unwrap_or_default();
+    assert!(current == generated, "include/chip8.h is out of date, run with CHIP8_BLESS=1");
+}
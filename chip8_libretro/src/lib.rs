@@ -0,0 +1,521 @@
+//! libretro core, for running the emulator in RetroArch and other libretro frontends
+//!
+//! Frontends look for `chip8_libretro.so` (`.dll`, `.dylib`), so the built `libchip8_libretro.so`
+//! should be renamed when installing it.
+//!
+//! The joypad is mapped onto the keypad as below, the keyboard uses the usual
+//! `1234`/`QWER`/`ASDF`/`ZXCV` layout:
+//!
+//! ```text
+//!  Up/Down/Left/Right  2/8/4/6      L/R       7/9
+//!  A/B/X/Y             5/0/1/3      L2/R2     A/B
+//!  Select/Start        C/D          L3/R3     E/F
+//! ```
+//!
+//! The quirk preset is the `chip8_quirks` core option. Save states are `Snapshot::to_bytes`,
+//! and cheats use the cheat file format (`VE = 9`), several separated by `+`.
+//!
+//! Flicker reduction is the `chip8_phosphor` core option, overridden per ROM by a file next to it
+//! holding the mode (`path/to/rom.phosphor`, e.g. `decay 6`, see `PhosphorMode::parse`).
+
+pub mod libretro;
+
+use std::collections::BTreeMap;
+use std::ffi::{c_char, c_uint, c_void, CStr};
+use std::sync::{Mutex, MutexGuard};
+
+use chip8_core::constants::{MAX_ROM_SIZE, NUM_KEYS, SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8_core::{Cheat, Emu, Palette, Phosphor, PhosphorMode, Quirks, Silent, Snapshot};
+use libretro::*;
+
+/// Audio sample rate, in Hz
+pub const SAMPLE_RATE: usize = 44100;
+/// Stereo audio frames per video frame
+pub const SAMPLES_PER_FRAME: usize = SAMPLE_RATE / 60;
+/// Pitch of the beep, in Hz
+const BEEP_PITCH: usize = 440;
+const BEEP_VOLUME: i16 = 0x1000;
+
+/// The quirk preset core option, the first choice is the default
+pub const QUIRKS_OPTION: &CStr = c"chip8_quirks";
+const QUIRKS_CHOICES: &CStr = c"Quirk preset; default|chip8|schip|xochip";
+
+/// The flicker reduction core option, in `PhosphorMode::parse`'s format
+pub const PHOSPHOR_OPTION: &CStr = c"chip8_phosphor";
+const PHOSPHOR_CHOICES: &CStr = c"Flicker reduction; off|blend|decay 4|decay 8";
+
+/// Joypad buttons and the keys they press
+pub const JOYPAD: [(c_uint, usize, &CStr); NUM_KEYS] = [
+    (RETRO_DEVICE_ID_JOYPAD_UP, 0x2, c"Up (2)"),
+    (RETRO_DEVICE_ID_JOYPAD_DOWN, 0x8, c"Down (8)"),
+    (RETRO_DEVICE_ID_JOYPAD_LEFT, 0x4, c"Left (4)"),
+    (RETRO_DEVICE_ID_JOYPAD_RIGHT, 0x6, c"Right (6)"),
+    (RETRO_DEVICE_ID_JOYPAD_A, 0x5, c"5"),
+    (RETRO_DEVICE_ID_JOYPAD_B, 0x0, c"0"),
+    (RETRO_DEVICE_ID_JOYPAD_X, 0x1, c"1"),
+    (RETRO_DEVICE_ID_JOYPAD_Y, 0x3, c"3"),
+    (RETRO_DEVICE_ID_JOYPAD_L, 0x7, c"7"),
+    (RETRO_DEVICE_ID_JOYPAD_R, 0x9, c"9"),
+    (RETRO_DEVICE_ID_JOYPAD_L2, 0xA, c"A"),
+    (RETRO_DEVICE_ID_JOYPAD_R2, 0xB, c"B"),
+    (RETRO_DEVICE_ID_JOYPAD_SELECT, 0xC, c"C"),
+    (RETRO_DEVICE_ID_JOYPAD_START, 0xD, c"D"),
+    (RETRO_DEVICE_ID_JOYPAD_L3, 0xE, c"E"),
+    (RETRO_DEVICE_ID_JOYPAD_R3, 0xF, c"F"),
+];
+
+/// Keyboard keys (`retro_key`, lowercase ASCII) and the keys they press
+pub const KEYBOARD: [(c_uint, usize); NUM_KEYS] = [
+    (b'1' as c_uint, 0x1), (b'2' as c_uint, 0x2), (b'3' as c_uint, 0x3), (b'4' as c_uint, 0xC),
+    (b'q' as c_uint, 0x4), (b'w' as c_uint, 0x5), (b'e' as c_uint, 0x6), (b'r' as c_uint, 0xD),
+    (b'a' as c_uint, 0x7), (b's' as c_uint, 0x8), (b'd' as c_uint, 0x9), (b'f' as c_uint, 0xE),
+    (b'z' as c_uint, 0xA), (b'x' as c_uint, 0x0), (b'c' as c_uint, 0xB), (b'v' as c_uint, 0xF),
+];
+
+/// Callbacks set by the frontend
+#[derive(Default)]
+struct Callbacks {
+    environment: Option<retro_environment_t>,
+    video: Option<retro_video_refresh_t>,
+    audio_sample: Option<retro_audio_sample_t>,
+    audio_batch: Option<retro_audio_sample_batch_t>,
+    input_poll: Option<retro_input_poll_t>,
+    input_state: Option<retro_input_state_t>,
+}
+impl Callbacks {
+    /// Send an environment command, `false` if the frontend doesn't support it
+    fn environment(&self, cmd: c_uint, data: *mut c_void) -> bool {
+        match self.environment {
+            Some(environment) => unsafe { environment(cmd, data) },
+            None => false,
+        }
+    }
+
+    /// The value of core option `key`, `None` if the frontend has no options
+    fn variable(&self, key: &CStr) -> Option<String> {
+        let mut var = retro_variable { key: key.as_ptr(), value: std::ptr::null() };
+        let found = self.environment(RETRO_ENVIRONMENT_GET_VARIABLE, &mut var as *mut _ as *mut c_void);
+        if !found || var.value.is_null() { return None; }
+        unsafe { CStr::from_ptr(var.value) }.to_str().ok().map(str::to_string)
+    }
+
+    /// The chosen quirk preset, the default if the frontend has no options
+    fn quirks(&self) -> Quirks {
+        self.variable(QUIRKS_OPTION).as_deref().and_then(Quirks::preset).unwrap_or_default()
+    }
+
+    /// The chosen flicker reduction, off if the frontend has no options
+    fn phosphor(&self) -> PhosphorMode {
+        self.variable(PHOSPHOR_OPTION).as_deref().and_then(PhosphorMode::parse).unwrap_or_default()
+    }
+
+    fn options_changed(&self) -> bool {
+        let mut updated = false;
+        self.environment(RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE, &mut updated as *mut _ as *mut c_void) && updated
+    }
+}
+
+/// A loaded game
+struct Game {
+    emu: Emu,
+    rom: Vec<u8>,
+    /// Cheats by index, with whether they're enabled
+    cheats: BTreeMap<c_uint, (bool, Vec<Cheat>)>,
+    /// The ROM faulted (e.g. stack underflow), it stays frozen until reset or a state is loaded
+    faulted: bool,
+    phosphor: Phosphor,
+    /// Flicker reduction from the ROM's `.phosphor` file, which takes precedence over the core option
+    rom_phosphor: Option<PhosphorMode>,
+    video: Vec<u32>,
+    audio: Vec<i16>,
+    /// Position in the beep's square wave, in samples
+    phase: usize,
+}
+impl Game {
+    fn new(rom: &[u8], quirks: Quirks, phosphor: PhosphorMode, rom_phosphor: Option<PhosphorMode>) -> Self {
+        let mut game = Self {
+            emu: Emu::new(),
+            rom: rom.to_vec(),
+            cheats: BTreeMap::new(),
+            faulted: false,
+            phosphor: Phosphor::new(rom_phosphor.unwrap_or(phosphor)),
+            rom_phosphor,
+            video: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            audio: vec![0; SAMPLES_PER_FRAME * 2],
+            phase: 0,
+        };
+        game.reset(quirks);
+        game
+    }
+
+    fn reset(&mut self, quirks: Quirks) {
+        self.emu = Emu::new();
+        // The sound timer is turned into audio instead
+        self.emu.set_beeper(Box::new(Silent));
+        self.emu.set_quirks(quirks);
+        // `retro_load_game` only accepts ROMs that fit
+        self.faulted = self.emu.load_rom(&self.rom).is_err();
+        self.apply_cheats();
+        self.phosphor.reset();
+    }
+
+    /// Switch to the core option's flicker reduction, unless the ROM picked its own
+    fn set_phosphor(&mut self, mode: PhosphorMode) {
+        let mode = self.rom_phosphor.unwrap_or(mode);
+        if mode != self.phosphor.mode() { self.phosphor.set_mode(mode); }
+    }
+
+    fn apply_cheats(&mut self) {
+        self.emu.clear_cheats();
+        for cheat in self.cheats.values().filter(|(enabled, _)| *enabled).flat_map(|(_, cheats)| cheats) {
+            self.emu.add_cheat(cheat.clone());
+        }
+    }
+
+    fn run(&mut self, keys: [bool; NUM_KEYS]) {
+        if self.faulted { return; }
+        for (key, held) in keys.into_iter().enumerate() {
+            self.emu.keypress(key, held);
+        }
+        self.faulted = self.emu.run_frame().is_err();
+        self.phosphor.push(self.emu.framebuffer());
+    }
+
+    /// Render the screen as XRGB8888, fading between the colors by the pixels' intensity
+    fn render(&mut self) {
+        for (pixel, intensity) in self.video.iter_mut().zip(self.phosphor.intensity()) {
+            let [r, g, b, _] = Palette::MONOCHROME.shade(*intensity);
+            *pixel = u32::from_be_bytes([0, r, g, b]);
+        }
+    }
+
+    /// A square wave while the sound timer is running, silence otherwise
+    fn mix(&mut self) {
+        let beeping = self.emu.timers().1 > 0;
+        let period = SAMPLE_RATE / BEEP_PITCH;
+        for frame in self.audio.chunks_exact_mut(2) {
+            let sample = match beeping {
+                true if self.phase < period / 2 => BEEP_VOLUME,
+                true => -BEEP_VOLUME,
+                false => 0,
+            };
+            frame.fill(sample);
+            self.phase = (self.phase + 1) % period;
+        }
+    }
+}
+
+struct State {
+    callbacks: Callbacks,
+    game: Option<Game>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    callbacks: Callbacks {
+        environment: None,
+        video: None,
+        audio_sample: None,
+        audio_batch: None,
+        input_poll: None,
+        input_state: None,
+    },
+    game: None,
+});
+
+fn state() -> MutexGuard<'static, State> {
+    // A panic can't unwind out of the `extern "C"` callbacks, so a poisoned lock never outlives one
+    STATE.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// =============== //
+// == CALLBACKS == //
+// =============== //
+
+#[no_mangle]
+pub extern "C" fn retro_set_environment(callback: retro_environment_t) {
+    let mut state = state();
+    state.callbacks.environment = Some(callback);
+
+    let variables = [
+        retro_variable { key: QUIRKS_OPTION.as_ptr(), value: QUIRKS_CHOICES.as_ptr() },
+        retro_variable { key: PHOSPHOR_OPTION.as_ptr(), value: PHOSPHOR_CHOICES.as_ptr() },
+        retro_variable { key: std::ptr::null(), value: std::ptr::null() },
+    ];
+    state.callbacks.environment(RETRO_ENVIRONMENT_SET_VARIABLES, variables.as_ptr() as *mut c_void);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(callback: retro_video_refresh_t) {
+    state().callbacks.video = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(callback: retro_audio_sample_t) {
+    state().callbacks.audio_sample = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(callback: retro_audio_sample_batch_t) {
+    state().callbacks.audio_batch = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(callback: retro_input_poll_t) {
+    state().callbacks.input_poll = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(callback: retro_input_state_t) {
+    state().callbacks.input_state = Some(callback);
+}
+
+// ================ //
+// == LIFE CYCLE == //
+// ================ //
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    let mut state = state();
+    state.game = None;
+    state.callbacks = Callbacks::default();
+}
+
+/// # Safety
+/// `info` must point to a `retro_system_info`
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut retro_system_info) {
+    let Some(info) = info.as_mut() else { return; };
+    *info = retro_system_info {
+        library_name: c"CHIP-8".as_ptr(),
+        library_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char,
+        valid_extensions: c"ch8|c8".as_ptr(),
+        need_fullpath: false,
+        block_extract: false,
+    };
+}
+
+/// # Safety
+/// `info` must point to a `retro_system_av_info`
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut retro_system_av_info) {
+    let Some(info) = info.as_mut() else { return; };
+    *info = retro_system_av_info {
+        geometry: retro_game_geometry {
+            base_width: SCREEN_WIDTH as c_uint,
+            base_height: SCREEN_HEIGHT as c_uint,
+            max_width: SCREEN_WIDTH as c_uint,
+            max_height: SCREEN_HEIGHT as c_uint,
+            aspect_ratio: SCREEN_WIDTH as f32 / SCREEN_HEIGHT as f32,
+        },
+        timing: retro_system_timing { fps: 60.0, sample_rate: SAMPLE_RATE as f64 },
+    };
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    RETRO_REGION_NTSC
+}
+
+/// Load a ROM, which the frontend passes in memory
+///
+/// # Safety
+/// `game` must be null or point to a `retro_game_info` whose `data` holds `size` bytes
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const retro_game_info) -> bool {
+    let Some(game) = game.as_ref() else { return false; };
+    if game.data.is_null() || game.size > MAX_ROM_SIZE { return false; }
+    let rom = std::slice::from_raw_parts(game.data as *const u8, game.size);
+
+    let mut state = state();
+    let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+    if !state.callbacks.environment(RETRO_ENVIRONMENT_SET_PIXEL_FORMAT, &mut format as *mut _ as *mut c_void) {
+        return false;
+    }
+    let mut descriptors: Vec<_> = JOYPAD.iter().map(|(id, _, description)| retro_input_descriptor {
+        port: 0,
+        device: RETRO_DEVICE_JOYPAD,
+        index: 0,
+        id: *id,
+        description: description.as_ptr(),
+    }).collect();
+    descriptors.push(retro_input_descriptor { port: 0, device: 0, index: 0, id: 0, description: std::ptr::null() });
+    state.callbacks.environment(RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS, descriptors.as_mut_ptr() as *mut c_void);
+
+    // The ROM's own flicker reduction, if the frontend says where it was loaded from
+    let rom_phosphor = match game.path.is_null() {
+        true => None,
+        false => {
+            let path = format!("{}.phosphor", CStr::from_ptr(game.path).to_string_lossy());
+            std::fs::read_to_string(path).ok().and_then(|text| PhosphorMode::parse(&text))
+        },
+    };
+    let (quirks, phosphor) = (state.callbacks.quirks(), state.callbacks.phosphor());
+    state.game = Some(Game::new(rom, quirks, phosphor, rom_phosphor));
+    true
+}
+
+/// No special content types are supported
+///
+/// # Safety
+/// Never dereferences its arguments
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game_special(_type: c_uint, _info: *const retro_game_info, _num: usize) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    state().game = None;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    let mut state = state();
+    let quirks = state.callbacks.quirks();
+    if let Some(game) = &mut state.game { game.reset(quirks); }
+}
+
+// ========= //
+// == RUN == //
+// ========= //
+
+/// Run a frame: read input, emulate, then send the frame's video and audio
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    let mut state = state();
+    let State { callbacks, game } = &mut *state;
+    let Some(game) = game else { return; };
+
+    if callbacks.options_changed() {
+        game.emu.set_quirks(callbacks.quirks());
+        game.set_phosphor(callbacks.phosphor());
+    }
+
+    let mut keys = [false; NUM_KEYS];
+    if let (Some(poll), Some(input)) = (callbacks.input_poll, callbacks.input_state) {
+        unsafe {
+            poll();
+            for (id, key, _) in JOYPAD {
+                keys[key] |= input(0, RETRO_DEVICE_JOYPAD, 0, id) != 0;
+            }
+            for (id, key) in KEYBOARD {
+                keys[key] |= input(0, RETRO_DEVICE_KEYBOARD, 0, id) != 0;
+            }
+        }
+    }
+    game.run(keys);
+
+    game.render();
+    if let Some(video) = callbacks.video {
+        let pitch = SCREEN_WIDTH * size_of::<u32>();
+        unsafe { video(game.video.as_ptr() as *const c_void, SCREEN_WIDTH as c_uint, SCREEN_HEIGHT as c_uint, pitch) };
+    }
+    game.mix();
+    match (callbacks.audio_batch, callbacks.audio_sample) {
+        (Some(batch), _) => {
+            let mut sent = 0;
+            while sent < SAMPLES_PER_FRAME {
+                let taken = unsafe { batch(game.audio[sent * 2..].as_ptr(), SAMPLES_PER_FRAME - sent) };
+                if taken == 0 { break; }
+                sent += taken;
+            }
+        },
+        (None, Some(sample)) => {
+            for frame in game.audio.chunks_exact(2) {
+                unsafe { sample(frame[0], frame[1]) };
+            }
+        },
+        (None, None) => {},
+    }
+}
+
+// ================= //
+// == SAVE STATES == //
+// ================= //
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    Snapshot::SIZE
+}
+
+/// # Safety
+/// `data` must be valid for writing `size` bytes
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    let state = state();
+    let (Some(game), false) = (&state.game, data.is_null()) else { return false; };
+    if size < Snapshot::SIZE { return false; }
+    let bytes = game.emu.snapshot().to_bytes();
+    std::ptr::copy_nonoverlapping(bytes.as_ptr(), data as *mut u8, bytes.len());
+    true
+}
+
+/// # Safety
+/// `data` must be valid for reading `size` bytes
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    let mut state = state();
+    let (Some(game), false) = (&mut state.game, data.is_null()) else { return false; };
+    // Frontends may pass a larger buffer than `retro_serialize_size`
+    if size < Snapshot::SIZE { return false; }
+    let bytes = std::slice::from_raw_parts(data as *const u8, Snapshot::SIZE);
+    let Ok(snapshot) = Snapshot::from_bytes(bytes) else { return false; };
+    game.emu.restore(&snapshot);
+    game.faulted = false;
+    game.phosphor.reset();
+    true
+}
+
+// ============ //
+// == CHEATS == //
+// ============ //
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {
+    if let Some(game) = &mut state().game {
+        game.cheats.clear();
+        game.apply_cheats();
+    }
+}
+
+/// Set cheat `index`, in the cheat file format with `+` between cheats, e.g. `VE = 9+V5 = 0`
+/// Codes that don't parse are ignored.
+///
+/// # Safety
+/// `code` must be null or a NUL-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn retro_cheat_set(index: c_uint, enabled: bool, code: *const c_char) {
+    if code.is_null() { return; }
+    let Ok(code) = CStr::from_ptr(code).to_str() else { return; };
+    let Ok(cheats) = Cheat::parse_file(&code.replace('+', "\n")) else { return; };
+    if let Some(game) = &mut state().game {
+        game.cheats.insert(index, (enabled, cheats));
+        game.apply_cheats();
+    }
+}
+
+// ============ //
+// == MEMORY == //
+// ============ //
+
+/// Memory isn't exposed: writes from the frontend would bypass the emulator's code caches
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(_id: c_uint) -> *mut c_void {
+    std::ptr::null_mut()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(_id: c_uint) -> usize {
+    0
+}
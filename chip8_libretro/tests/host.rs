@@ -0,0 +1,435 @@
+//! A minimal libretro frontend, loading the built core the way RetroArch would
+//!
+//! The core keeps global state, so the tests take turns through `Host::start`.
+
+use std::collections::HashMap;
+use std::ffi::{c_char, c_uint, c_void, CStr, CString};
+use std::sync::{Mutex, MutexGuard};
+
+use chip8_core::{Emu, PhosphorMode, Quirks, Snapshot};
+use chip8_libretro::libretro::*;
+use libloading::{Library, Symbol};
+
+/// What the core has sent to the frontend, and the input and options it will be given
+#[derive(Default)]
+struct Frontend {
+    pixel_format: Option<c_uint>,
+    descriptors: Vec<(c_uint, String)>,
+    /// Options declared by the core, as "Description; first|second|..."
+    variables: HashMap<String, String>,
+    /// Chosen option values, kept alive for `GET_VARIABLE`
+    options: HashMap<String, CString>,
+    options_updated: bool,
+    video: Vec<u32>,
+    frames: usize,
+    audio: Vec<i16>,
+    joypad: Vec<c_uint>,
+    keyboard: Vec<c_uint>,
+}
+
+static FRONTEND: Mutex<Option<Frontend>> = Mutex::new(None);
+static TURN: Mutex<()> = Mutex::new(());
+
+fn frontend() -> MutexGuard<'static, Option<Frontend>> {
+    FRONTEND.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn with_frontend<T>(f: impl FnOnce(&mut Frontend) -> T) -> T {
+    f(frontend().as_mut().unwrap())
+}
+
+unsafe extern "C" fn environment(cmd: c_uint, data: *mut c_void) -> bool {
+    with_frontend(|frontend| match cmd {
+        RETRO_ENVIRONMENT_SET_PIXEL_FORMAT => {
+            frontend.pixel_format = Some(*(data as *const c_uint));
+            true
+        },
+        RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS => {
+            let mut descriptor = data as *const retro_input_descriptor;
+            while !(*descriptor).description.is_null() {
+                let description = CStr::from_ptr((*descriptor).description).to_string_lossy().into_owned();
+                frontend.descriptors.push(((*descriptor).id, description));
+                descriptor = descriptor.add(1);
+            }
+            true
+        },
+        RETRO_ENVIRONMENT_SET_VARIABLES => {
+            let mut var = data as *const retro_variable;
+            while !(*var).key.is_null() {
+                let key = CStr::from_ptr((*var).key).to_string_lossy().into_owned();
+                let value = CStr::from_ptr((*var).value).to_string_lossy().into_owned();
+                frontend.variables.insert(key, value);
+                var = var.add(1);
+            }
+            true
+        },
+        RETRO_ENVIRONMENT_GET_VARIABLE => {
+            let var = &mut *(data as *mut retro_variable);
+            let key = CStr::from_ptr(var.key).to_string_lossy();
+            match frontend.options.get(&*key) {
+                Some(value) => {
+                    var.value = value.as_ptr();
+                    true
+                },
+                None => false,
+            }
+        },
+        RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE => {
+            *(data as *mut bool) = std::mem::take(&mut frontend.options_updated);
+            true
+        },
+        _ => false,
+    })
+}
+
+unsafe extern "C" fn video_refresh(data: *const c_void, width: c_uint, height: c_uint, pitch: usize) {
+    assert_eq!((width, height, pitch), (64, 32, 64 * 4));
+    let pixels = std::slice::from_raw_parts(data as *const u32, (width * height) as usize);
+    with_frontend(|frontend| {
+        frontend.video = pixels.to_vec();
+        frontend.frames += 1;
+    });
+}
+
+unsafe extern "C" fn audio_sample(left: i16, right: i16) {
+    with_frontend(|frontend| frontend.audio.extend([left, right]));
+}
+
+unsafe extern "C" fn audio_sample_batch(data: *const i16, frames: usize) -> usize {
+    let samples = std::slice::from_raw_parts(data, frames * 2);
+    with_frontend(|frontend| frontend.audio.extend_from_slice(samples));
+    frames
+}
+
+unsafe extern "C" fn input_poll() {}
+
+unsafe extern "C" fn input_state(port: c_uint, device: c_uint, _index: c_uint, id: c_uint) -> i16 {
+    if port != 0 { return 0; }
+    with_frontend(|frontend| match device {
+        RETRO_DEVICE_JOYPAD => frontend.joypad.contains(&id) as i16,
+        RETRO_DEVICE_KEYBOARD => frontend.keyboard.contains(&id) as i16,
+        _ => 0,
+    })
+}
+
+/// The loaded core
+struct Host {
+    lib: Library,
+    _turn: MutexGuard<'static, ()>,
+}
+impl Host {
+    /// Load the core and initialize it, with `options` chosen
+    fn start(options: &[(&str, &str)]) -> Self {
+        let turn = TURN.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut frontend = Frontend::default();
+        for (key, value) in options {
+            frontend.options.insert(key.to_string(), CString::new(*value).unwrap());
+        }
+        *self::frontend() = Some(frontend);
+
+        // Test builds leave the library next to the test binaries, in `target/<profile>/deps`
+        let dir = std::env::current_exe().unwrap().parent().unwrap().to_path_buf();
+        let name = format!("{}chip8_libretro{}", std::env::consts::DLL_PREFIX, std::env::consts::DLL_SUFFIX);
+        let lib = unsafe { Library::new(dir.join(name)).unwrap() };
+        let host = Self { lib, _turn: turn };
+        unsafe {
+            host.symbol::<unsafe extern "C" fn(retro_environment_t)>("retro_set_environment")(environment);
+            host.symbol::<unsafe extern "C" fn(retro_video_refresh_t)>("retro_set_video_refresh")(video_refresh);
+            host.symbol::<unsafe extern "C" fn(retro_audio_sample_t)>("retro_set_audio_sample")(audio_sample);
+            host.symbol::<unsafe extern "C" fn(retro_audio_sample_batch_t)>("retro_set_audio_sample_batch")(audio_sample_batch);
+            host.symbol::<unsafe extern "C" fn(retro_input_poll_t)>("retro_set_input_poll")(input_poll);
+            host.symbol::<unsafe extern "C" fn(retro_input_state_t)>("retro_set_input_state")(input_state);
+            host.call("retro_init");
+        }
+        host
+    }
+
+    unsafe fn symbol<T>(&self, name: &str) -> Symbol<'_, T> {
+        self.lib.get(name.as_bytes()).unwrap()
+    }
+
+    unsafe fn call(&self, name: &str) {
+        self.symbol::<unsafe extern "C" fn()>(name)();
+    }
+
+    fn load(&self, rom: &[u8]) -> bool {
+        self.load_from(rom, None)
+    }
+
+    /// Load `rom`, telling the core it came from `path`
+    fn load_from(&self, rom: &[u8], path: Option<&CStr>) -> bool {
+        let info = retro_game_info {
+            path: path.map_or(std::ptr::null(), CStr::as_ptr),
+            data: rom.as_ptr() as *const c_void,
+            size: rom.len(),
+            meta: std::ptr::null(),
+        };
+        unsafe { self.symbol::<unsafe extern "C" fn(*const retro_game_info) -> bool>("retro_load_game")(&info) }
+    }
+
+    fn run(&self, frames: usize) {
+        for _ in 0..frames {
+            unsafe { self.call("retro_run") };
+        }
+    }
+
+    fn save(&self) -> Vec<u8> {
+        unsafe {
+            let size = self.symbol::<unsafe extern "C" fn() -> usize>("retro_serialize_size")();
+            let mut data = vec![0; size];
+            let saved = self.symbol::<unsafe extern "C" fn(*mut c_void, usize) -> bool>("retro_serialize")(
+                data.as_mut_ptr() as *mut c_void,
+                size,
+            );
+            assert!(saved);
+            data
+        }
+    }
+
+    fn load_state(&self, data: &[u8]) -> bool {
+        unsafe {
+            self.symbol::<unsafe extern "C" fn(*const c_void, usize) -> bool>("retro_unserialize")(
+                data.as_ptr() as *const c_void,
+                data.len(),
+            )
+        }
+    }
+
+    /// The emulator's state, through a save state
+    fn emu(&self) -> Emu {
+        let mut emu = Emu::new();
+        emu.restore(&Snapshot::from_bytes(&self.save()).unwrap());
+        emu
+    }
+}
+impl Drop for Host {
+    fn drop(&mut self) {
+        unsafe {
+            self.call("retro_unload_game");
+            self.call("retro_deinit");
+        }
+    }
+}
+
+fn rom(name: &str) -> Vec<u8> {
+    std::fs::read(format!("{}/../roms/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap()
+}
+
+#[test]
+fn system_info() {
+    let host = Host::start(&[]);
+    unsafe {
+        assert_eq!(host.symbol::<unsafe extern "C" fn() -> c_uint>("retro_api_version")(), RETRO_API_VERSION);
+
+        let mut info: retro_system_info = std::mem::zeroed();
+        host.symbol::<unsafe extern "C" fn(*mut retro_system_info)>("retro_get_system_info")(&mut info);
+        assert_eq!(CStr::from_ptr(info.library_name), c"CHIP-8");
+        assert!(!info.need_fullpath);
+
+        let mut av: retro_system_av_info = std::mem::zeroed();
+        host.symbol::<unsafe extern "C" fn(*mut retro_system_av_info)>("retro_get_system_av_info")(&mut av);
+        assert_eq!((av.geometry.base_width, av.geometry.base_height), (64, 32));
+        assert_eq!((av.timing.fps, av.timing.sample_rate), (60.0, 44100.0));
+    }
+
+    // Every choice of the quirks option is a preset
+    let quirks = with_frontend(|frontend| frontend.variables["chip8_quirks"].clone());
+    let (_, choices) = quirks.split_once("; ").unwrap();
+    let names: Vec<_> = Quirks::PRESETS.iter().map(|(name, _)| *name).collect();
+    assert_eq!(choices.split('|').collect::<Vec<_>>(), names);
+
+    // And every choice of the phosphor option a mode
+    let phosphor = with_frontend(|frontend| frontend.variables["chip8_phosphor"].clone());
+    let (_, choices) = phosphor.split_once("; ").unwrap();
+    for choice in choices.split('|') {
+        assert_eq!(PhosphorMode::parse(choice).map(|mode| mode.to_string()).as_deref(), Some(choice));
+    }
+}
+
+#[test]
+fn runs_game() {
+    let host = Host::start(&[]);
+    assert!(!host.load(&vec![0; 4000]), "ROM too large to fit in memory");
+    assert!(host.load(&rom("BRIX")));
+    with_frontend(|frontend| {
+        assert_eq!(frontend.pixel_format, Some(RETRO_PIXEL_FORMAT_XRGB8888));
+        assert_eq!(frontend.descriptors.len(), 16);
+    });
+
+    host.run(60);
+    with_frontend(|frontend| {
+        assert_eq!(frontend.frames, 60);
+        assert_eq!(frontend.audio.len(), 60 * 735 * 2);
+        let lit = frontend.video.iter().filter(|pixel| **pixel == 0x00FF_FFFF).count();
+        let unlit = frontend.video.iter().filter(|pixel| **pixel == 0).count();
+        assert!(lit > 100, "bricks drawn");
+        assert_eq!(lit + unlit, 64 * 32);
+    });
+}
+
+#[test]
+fn beeps_while_sound_timer_runs() {
+    let host = Host::start(&[]);
+    // LD V0, 30; LD ST, V0; loop
+    assert!(host.load(&[0x60, 0x1E, 0xF0, 0x18, 0x12, 0x04]));
+    host.run(10);
+    let audio = with_frontend(|frontend| std::mem::take(&mut frontend.audio));
+    assert!(audio.iter().all(|sample| sample.abs() == 0x1000), "square wave");
+    assert!(audio.windows(2).any(|pair| pair[0] != pair[1]));
+
+    host.run(30);
+    let audio = with_frontend(|frontend| std::mem::take(&mut frontend.audio));
+    assert!(audio[audio.len() - 735 * 2..].iter().all(|sample| *sample == 0), "silent once the timer runs out");
+}
+
+#[test]
+fn maps_input() {
+    let host = Host::start(&[]);
+    // LD V0, K; LD V1, V0... loop: waits for a key, then stores it in V1 and waits again
+    assert!(host.load(&[0xF0, 0x0A, 0x81, 0x00, 0x12, 0x00]));
+    let press = |joypad: Vec<c_uint>, keyboard: Vec<c_uint>| {
+        with_frontend(|frontend| (frontend.joypad, frontend.keyboard) = (joypad, keyboard));
+        host.run(3);
+        with_frontend(|frontend| (frontend.joypad, frontend.keyboard) = (vec![], vec![]));
+        host.run(3);
+        host.emu().registers()[1]
+    };
+    assert_eq!(press(vec![RETRO_DEVICE_ID_JOYPAD_A], vec![]), 0x5);
+    assert_eq!(press(vec![RETRO_DEVICE_ID_JOYPAD_UP], vec![]), 0x2);
+    assert_eq!(press(vec![RETRO_DEVICE_ID_JOYPAD_R3], vec![]), 0xF);
+    assert_eq!(press(vec![], vec![b'x' as c_uint]), 0x0);
+    assert_eq!(press(vec![], vec![b'4' as c_uint]), 0xC);
+}
+
+#[test]
+fn save_states() {
+    let host = Host::start(&[]);
+    assert!(host.load(&rom("BRIX")));
+    host.run(60);
+    let state = host.save();
+    assert_eq!(state.len(), Snapshot::SIZE);
+
+    let run = || {
+        for frame in 0..120 {
+            let joypad = if frame % 30 < 15 { vec![RETRO_DEVICE_ID_JOYPAD_LEFT] } else { vec![] };
+            with_frontend(|frontend| frontend.joypad = joypad);
+            host.run(1);
+        }
+        with_frontend(|frontend| frontend.video.clone())
+    };
+    let first = run();
+    assert!(host.load_state(&state));
+    assert_eq!(run(), first);
+
+    assert!(!host.load_state(&state[..100]));
+    assert!(!host.load_state(&vec![0; state.len()]));
+}
+
+#[test]
+fn quirks_option() {
+    let host = Host::start(&[("chip8_quirks", "chip8")]);
+    assert!(host.load(&rom("PONG")));
+    host.run(1);
+    assert_eq!(host.emu().quirks(), Quirks::CHIP8);
+
+    with_frontend(|frontend| {
+        frontend.options.insert("chip8_quirks".to_string(), CString::new("schip").unwrap());
+        frontend.options_updated = true;
+    });
+    host.run(1);
+    assert_eq!(host.emu().quirks(), Quirks::SUPER_CHIP);
+
+    unsafe { host.call("retro_reset") };
+    let emu = host.emu();
+    assert_eq!((emu.pc(), emu.quirks()), (0x200, Quirks::SUPER_CHIP));
+}
+
+#[test]
+fn cheats() {
+    let host = Host::start(&[]);
+    // An endless loop
+    assert!(host.load(&[0x12, 0x00]));
+    let state = host.save();
+    unsafe {
+        let set = host.symbol::<unsafe extern "C" fn(c_uint, bool, *const c_char)>("retro_cheat_set");
+        set(0, true, c"V5 = 7+0x300 = 0x2A".as_ptr());
+        set(2, false, c"V6 = 1".as_ptr());
+        // Indices are only keys, a huge one doesn't allocate room for the ones before it
+        set(c_uint::MAX, true, c"V7 = 3".as_ptr());
+    }
+    host.run(1);
+    let emu = host.emu();
+    assert_eq!((emu.registers()[5], emu.registers()[6], emu.memory(0x300..0x301)[0]), (7, 0, 0x2A));
+    assert_eq!(emu.registers()[7], 3);
+
+    unsafe { host.call("retro_cheat_reset") };
+    assert!(host.load_state(&state));
+    host.run(1);
+    assert_eq!(host.emu().registers()[5], 0);
+}
+
+#[test]
+fn faults_freeze_the_game_until_reset() {
+    let host = Host::start(&[]);
+    // Sets V0 then returns with an empty stack
+    assert!(host.load(&[0x60, 0x01, 0x00, 0xEE]));
+    host.run(3);
+    let emu = host.emu();
+    assert_eq!((emu.pc(), emu.registers()[0]), (0x202, 1));
+    with_frontend(|frontend| assert_eq!(frontend.frames, 3));
+
+    unsafe { host.call("retro_reset") };
+    assert_eq!((host.emu().pc(), host.emu().registers()[0]), (0x200, 0));
+    host.run(1);
+    assert_eq!(host.emu().pc(), 0x202);
+}
+
+/// Draws the font's "0" at the top left every frame, so it's lit on every other frame
+const FLICKER: [u8; 4] = [0xD0, 0x15, 0x12, 0x00];
+
+/// The top left pixel over `frames` frames
+fn top_left(host: &Host, frames: usize) -> Vec<u32> {
+    (0..frames).map(|_| {
+        host.run(1);
+        with_frontend(|frontend| frontend.video[0])
+    }).collect()
+}
+
+#[test]
+fn phosphor_option() {
+    let host = Host::start(&[]);
+    assert!(host.load(&FLICKER));
+    assert_eq!(top_left(&host, 4), [0x00FF_FFFF, 0, 0x00FF_FFFF, 0]);
+
+    with_frontend(|frontend| {
+        frontend.options.insert("chip8_phosphor".to_string(), CString::new("blend").unwrap());
+        frontend.options_updated = true;
+    });
+    assert_eq!(top_left(&host, 4), [0x00FF_FFFF; 4]);
+
+    with_frontend(|frontend| {
+        frontend.options.insert("chip8_phosphor".to_string(), CString::new("decay 4").unwrap());
+        frontend.options_updated = true;
+    });
+    assert_eq!(top_left(&host, 4), [0x00FF_FFFF, 0x00BF_BFBF, 0x00FF_FFFF, 0x00BF_BFBF]);
+}
+
+#[test]
+fn phosphor_file_next_to_the_rom() {
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("phosphor");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("FLICKER.ch8");
+    std::fs::write(&path, FLICKER).unwrap();
+    std::fs::write(dir.join("FLICKER.ch8.phosphor"), "decay 4\n").unwrap();
+    let path = CString::new(path.to_str().unwrap()).unwrap();
+
+    // The file wins over the core option, even when it changes
+    let host = Host::start(&[("chip8_phosphor", "blend")]);
+    assert!(host.load_from(&FLICKER, Some(&path)));
+    assert_eq!(top_left(&host, 2), [0x00FF_FFFF, 0x00BF_BFBF]);
+    with_frontend(|frontend| {
+        frontend.options.insert("chip8_phosphor".to_string(), CString::new("off").unwrap());
+        frontend.options_updated = true;
+    });
+    assert_eq!(top_left(&host, 2), [0x00FF_FFFF, 0x00BF_BFBF]);
+}
@@ -0,0 +1,385 @@
+//! Python bindings, built with maturin:
+//!
+//! ```sh
+//! maturin develop
+//! python -m unittest discover tests
+//! ```
+
+use numpy::{PyArray1, PyArray2, PyArray3, PyArrayMethods};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+
+use chip8_core::constants::{MAX_ROM_SIZE, NUM_KEYS, NUM_REGS, RAM_SIZE, SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8_core::gym::{self, EnvFault, GameSpec};
+use chip8_core::{Fault, Framebuffer, Quirks, RomTooLarge, Silent};
+
+fn quirks(preset: &str) -> PyResult<Quirks> {
+    Quirks::preset(preset).ok_or_else(|| {
+        let names: Vec<_> = Quirks::PRESETS.iter().map(|(name, _)| *name).collect();
+        PyValueError::new_err(format!("unknown quirk preset {:?}, expected one of {}", preset, names.join(", ")))
+    })
+}
+
+fn check_rom(rom: &[u8]) -> PyResult<()> {
+    if rom.len() > MAX_ROM_SIZE {
+        return Err(PyValueError::new_err(RomTooLarge { len: rom.len() }.to_string()));
+    }
+    Ok(())
+}
+
+create_exception!(
+    chip8,
+    Chip8Fault,
+    PyException,
+    "The running ROM faulted, e.g. by returning with an empty stack\n\n\
+     `kind` names the fault, such as \"stack_underflow\", and `addr` is the address of the faulting\n\
+     instruction, or None if the emulator is gone. The emulator stays at that instruction."
+);
+
+/// `Chip8Fault` with `message`, for `fault` in the instruction at `addr`
+fn fault_error(py: Python<'_>, message: String, fault: Fault, addr: Option<u16>) -> PyErr {
+    let kind = match fault {
+        Fault::UnknownOpcode { .. } => "unknown_opcode",
+        Fault::PcOutOfRange(_) => "pc_out_of_range",
+        Fault::StackOverflow => "stack_overflow",
+        Fault::StackUnderflow => "stack_underflow",
+        Fault::MemoryOutOfRange { .. } => "memory_out_of_range",
+        Fault::InvalidKey(_) => "invalid_key",
+    };
+    let error = Chip8Fault::new_err(message);
+    let value = error.value(py);
+    if let Err(error) = value.setattr("kind", kind).and_then(|()| value.setattr("addr", addr)) {
+        return error;
+    }
+    error
+}
+
+/// One byte per pixel, 1 if lit, row by row
+fn pixels(screen: &Framebuffer) -> impl Iterator<Item = u8> + '_ {
+    (0..SCREEN_HEIGHT).flat_map(move |y| (0..SCREEN_WIDTH).map(move |x| screen.pixel(x, y) as u8))
+}
+
+fn screen_array<'py>(py: Python<'py>, screen: &Framebuffer) -> PyResult<Bound<'py, PyArray2<u8>>> {
+    PyArray1::from_vec(py, pixels(screen).collect()).reshape([SCREEN_HEIGHT, SCREEN_WIDTH])
+}
+
+/// A CHIP-8 emulator
+///
+/// Emu(rom=None, quirks="default", seed=None)
+///
+/// Faults in the running ROM, such as returning with an empty stack, raise `Chip8Fault`
+#[pyclass]
+struct Emu {
+    emu: chip8_core::Emu,
+}
+
+#[pymethods]
+impl Emu {
+    #[new]
+    #[pyo3(signature = (rom=None, quirks="default", seed=None))]
+    fn new(rom: Option<&[u8]>, quirks: &str, seed: Option<u64>) -> PyResult<Self> {
+        let mut emu = chip8_core::Emu::new();
+        // Sound is read from `timers` rather than printed
+        emu.set_beeper(Box::new(Silent));
+        emu.set_quirks(self::quirks(quirks)?);
+        if let Some(seed) = seed { emu.seed_rng(seed); }
+        let mut this = Self { emu };
+        if let Some(rom) = rom { this.load_rom(rom)?; }
+        Ok(this)
+    }
+
+    /// Copy a ROM into memory at 0x200
+    fn load_rom(&mut self, rom: &[u8]) -> PyResult<()> {
+        self.emu.load_rom(rom).map_err(|error| PyValueError::new_err(error.to_string()))
+    }
+
+    /// Switch to a quirk preset: "default", "chip8", "schip" or "xochip"
+    fn set_quirks(&mut self, preset: &str) -> PyResult<()> {
+        self.emu.set_quirks(quirks(preset)?);
+        Ok(())
+    }
+
+    /// Reseed the random number generator, making `RND` deterministic
+    fn seed(&mut self, seed: u64) {
+        self.emu.seed_rng(seed);
+    }
+
+    // ============= //
+    // == RUNNING == //
+    // ============= //
+
+    /// Execute `count` instructions, without ticking the timers
+    #[pyo3(signature = (count=1))]
+    fn step(&mut self, py: Python<'_>, count: usize) -> PyResult<()> {
+        for _ in 0..count {
+            self.emu.try_tick().map_err(|fault| self.fault(py, fault))?;
+        }
+        Ok(())
+    }
+
+    /// Run `count` 60Hz frames, releasing the GIL meanwhile
+    #[pyo3(signature = (count=1))]
+    fn run_frames(&mut self, py: Python<'_>, count: usize) -> PyResult<()> {
+        let emu = &mut self.emu;
+        let result = py.detach(|| (0..count).try_for_each(|_| emu.run_frame()));
+        result.map_err(|fault| self.fault(py, fault))
+    }
+
+    /// Press or release a key, 0x0 to 0xF
+    fn keypress(&mut self, key: usize, pressed: bool) -> PyResult<()> {
+        if key >= NUM_KEYS {
+            return Err(PyValueError::new_err(format!("key {} out of range, keys are 0 to {}", key, NUM_KEYS - 1)));
+        }
+        self.emu.keypress(key, pressed);
+        Ok(())
+    }
+
+    // ============= //
+    // == READING == //
+    // ============= //
+
+    /// `V0` to `VF`
+    #[getter]
+    fn registers<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.emu.registers()[..NUM_REGS])
+    }
+
+    #[getter]
+    fn pc(&self) -> u16 {
+        self.emu.pc()
+    }
+
+    /// The `I` register
+    #[getter]
+    fn index(&self) -> u16 {
+        self.emu.index()
+    }
+
+    /// Return addresses on the stack, oldest first
+    #[getter]
+    fn stack(&self) -> Vec<u16> {
+        self.emu.stack().to_vec()
+    }
+
+    /// `(delay, sound)`, the buzzer sounds while the sound timer is non-zero
+    #[getter]
+    fn timers(&self) -> (u8, u8) {
+        self.emu.timers()
+    }
+
+    /// RAM from `start` to `end`, clamped to the 4KB of memory
+    #[pyo3(signature = (start=0, end=RAM_SIZE))]
+    fn memory<'py>(&self, py: Python<'py>, start: usize, end: usize) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, self.emu.memory(start..end))
+    }
+
+    /// The screen as a `(32, 64)` uint8 array, 1 for lit pixels
+    fn framebuffer<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray2<u8>>> {
+        screen_array(py, self.emu.framebuffer())
+    }
+
+    // =========== //
+    // == STATE == //
+    // =========== //
+
+    /// Capture the whole machine state
+    fn snapshot(&self) -> Snapshot {
+        Snapshot { snapshot: self.emu.snapshot() }
+    }
+
+    /// Go back to a state from `snapshot`, which can be restored any number of times
+    fn restore(&mut self, snapshot: &Snapshot) {
+        self.emu.restore(&snapshot.snapshot);
+    }
+}
+
+impl Emu {
+    /// `Chip8Fault` for `fault`, which left the emulator at the faulting instruction
+    fn fault(&self, py: Python<'_>, fault: Fault) -> PyErr {
+        fault_error(py, fault.to_string(), fault, Some(self.emu.pc()))
+    }
+}
+
+/// A saved emulator state, from `Emu.snapshot`
+#[pyclass(frozen)]
+struct Snapshot {
+    snapshot: chip8_core::Snapshot,
+}
+
+#[pymethods]
+impl Snapshot {
+    fn __eq__(&self, other: &Self) -> bool {
+        self.snapshot == other.snapshot
+    }
+}
+
+// ========= //
+// == GYM == //
+// ========= //
+
+/// A spec file's text, or the name of a builtin spec such as "BRIX"
+fn game_spec(spec: &str, frame_skip: Option<usize>) -> PyResult<GameSpec> {
+    let mut spec = match GameSpec::builtin(spec) {
+        Some(spec) => spec,
+        None => GameSpec::parse(spec).map_err(|e| PyValueError::new_err(format!("invalid game spec, {}", e)))?,
+    };
+    if let Some(frame_skip) = frame_skip {
+        if frame_skip == 0 { return Err(PyValueError::new_err("frame_skip must be at least 1")); }
+        spec.frame_skip = frame_skip;
+    }
+    Ok(spec)
+}
+
+fn check_action(action: usize, num_actions: usize) -> PyResult<()> {
+    if action >= num_actions {
+        return Err(PyValueError::new_err(format!("action {} out of range, there are {}", action, num_actions)));
+    }
+    Ok(())
+}
+
+fn info<'py>(py: Python<'py>, info: &gym::Info) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("frame", info.frame)?;
+    dict.set_item("truncated", info.truncated)?;
+    Ok(dict)
+}
+
+/// Gym-style environment playing a ROM, see `chip8_core::gym` for the spec format
+///
+/// Env(rom, spec, frame_skip=None)
+///
+/// `spec` is a spec file's text or the name of a builtin one ("PONG", "BRIX").
+/// Observations are `(32, 64)` uint8 arrays like `Emu.framebuffer`.
+/// A fault in the ROM raises `Chip8Fault`, `reset` to play on.
+#[pyclass]
+struct Env {
+    env: gym::Env,
+}
+
+#[pymethods]
+impl Env {
+    #[new]
+    #[pyo3(signature = (rom, spec, frame_skip=None))]
+    fn new(py: Python<'_>, rom: &[u8], spec: &str, frame_skip: Option<usize>) -> PyResult<Self> {
+        check_rom(rom)?;
+        let env = gym::Env::new(rom, game_spec(spec, frame_skip)?);
+        Ok(Self { env: env.map_err(|fault| fault_error(py, fault.to_string(), fault, None))? })
+    }
+
+    /// Start a new episode, returning the first observation
+    #[pyo3(signature = (seed=0))]
+    fn reset<'py>(&mut self, py: Python<'py>, seed: u64) -> PyResult<Bound<'py, PyArray2<u8>>> {
+        let screen = self.env.reset(seed).map_err(|fault| self.fault(py, fault))?;
+        screen_array(py, &screen)
+    }
+
+    /// Take an action, returning `(observation, reward, done, info)`
+    #[allow(clippy::type_complexity)]
+    fn step<'py>(&mut self, py: Python<'py>, action: usize) -> PyResult<(Bound<'py, PyArray2<u8>>, f32, bool, Bound<'py, PyDict>)> {
+        check_action(action, self.env.num_actions())?;
+        if self.env.is_done() { return Err(PyRuntimeError::new_err("the episode is over, call reset")); }
+        let step = self.env.step(action).map_err(|fault| self.fault(py, fault))?;
+        Ok((screen_array(py, &step.observation)?, step.reward, step.done, info(py, &step.info)?))
+    }
+
+    #[getter]
+    fn num_actions(&self) -> usize {
+        self.env.num_actions()
+    }
+
+    /// The emulator's registers, `V0` to `VF`
+    #[getter]
+    fn registers<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, self.env.emu().registers())
+    }
+}
+
+impl Env {
+    /// `Chip8Fault` for `fault`, call `reset` to play on
+    fn fault(&self, py: Python<'_>, fault: Fault) -> PyErr {
+        fault_error(py, fault.to_string(), fault, Some(self.env.emu().pc()))
+    }
+}
+
+/// Many environments stepped together on several threads, see `chip8_core::gym::VecEnv`
+///
+/// VecEnv(rom, spec, count, seed=0, frame_skip=None, threads=None)
+///
+/// Finished episodes are reset automatically. Observations are stacked into `(count, 32, 64)` arrays.
+/// A fault in any environment raises `Chip8Fault` naming it, after every environment has stepped.
+#[pyclass]
+struct VecEnv {
+    envs: gym::VecEnv,
+}
+
+#[pymethods]
+impl VecEnv {
+    #[new]
+    #[pyo3(signature = (rom, spec, count, seed=0, frame_skip=None, threads=None))]
+    fn new(py: Python<'_>, rom: &[u8], spec: &str, count: usize, seed: u64, frame_skip: Option<usize>, threads: Option<usize>) -> PyResult<Self> {
+        check_rom(rom)?;
+        let envs = gym::VecEnv::new(rom, game_spec(spec, frame_skip)?, count, seed);
+        let mut envs = envs.map_err(|error| fault_error(py, error.to_string(), error.fault, None))?;
+        if let Some(threads) = threads { envs.set_threads(threads); }
+        Ok(Self { envs })
+    }
+
+    /// Reset environment `i` with seed `seed + i`, returning the first observations
+    #[pyo3(signature = (seed=0))]
+    fn reset<'py>(&mut self, py: Python<'py>, seed: u64) -> PyResult<Bound<'py, PyArray3<u8>>> {
+        let screens = self.envs.reset(seed).map_err(|error| self.fault(py, error))?;
+        let pixels = screens.iter().flat_map(pixels).collect();
+        PyArray1::from_vec(py, pixels).reshape([screens.len(), SCREEN_HEIGHT, SCREEN_WIDTH])
+    }
+
+    /// Take one action per environment, returning `(observations, rewards, dones, infos)`
+    #[allow(clippy::type_complexity)]
+    fn step<'py>(&mut self, py: Python<'py>, actions: Vec<usize>) -> PyResult<(Bound<'py, PyArray3<u8>>, Bound<'py, PyArray1<f32>>, Bound<'py, PyArray1<bool>>, Vec<Bound<'py, PyDict>>)> {
+        if actions.len() != self.envs.len() {
+            return Err(PyValueError::new_err(format!("expected {} actions, got {}", self.envs.len(), actions.len())));
+        }
+        for action in &actions {
+            check_action(*action, self.num_actions())?;
+        }
+        let envs = &mut self.envs;
+        let steps = py.detach(|| envs.step(&actions)).map_err(|error| self.fault(py, error))?;
+
+        let pixels = steps.iter().flat_map(|step| pixels(&step.observation)).collect();
+        let observations = PyArray1::from_vec(py, pixels).reshape([steps.len(), SCREEN_HEIGHT, SCREEN_WIDTH])?;
+        let rewards = PyArray1::from_vec(py, steps.iter().map(|step| step.reward).collect());
+        let dones = PyArray1::from_vec(py, steps.iter().map(|step| step.done).collect());
+        let infos = steps.iter().map(|step| info(py, &step.info)).collect::<PyResult<_>>()?;
+        Ok((observations, rewards, dones, infos))
+    }
+
+    #[getter]
+    fn num_actions(&self) -> usize {
+        self.envs.envs().first().map_or(0, |env| env.num_actions())
+    }
+
+    fn __len__(&self) -> usize {
+        self.envs.len()
+    }
+}
+
+impl VecEnv {
+    /// `Chip8Fault` naming the environment that faulted, call `reset` to play on
+    fn fault(&self, py: Python<'_>, error: EnvFault) -> PyErr {
+        fault_error(py, error.to_string(), error.fault, Some(self.envs.envs()[error.env].emu().pc()))
+    }
+}
+
+#[pymodule]
+fn chip8(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Emu>()?;
+    m.add_class::<Snapshot>()?;
+    m.add_class::<Env>()?;
+    m.add_class::<VecEnv>()?;
+    m.add("Chip8Fault", m.py().get_type::<Chip8Fault>())?;
+    m.add("SCREEN_WIDTH", SCREEN_WIDTH)?;
+    m.add("SCREEN_HEIGHT", SCREEN_HEIGHT)?;
+    m.add("PRESETS", Quirks::PRESETS.iter().map(|(name, _)| *name).collect::<Vec<_>>())?;
+    Ok(())
+}
@@ -0,0 +1,28 @@
+//! Runs the Python tests in this directory against the built extension module
+//! Ignored by default, as it needs Python 3 (`python3`, or `$PYTHON`) with numpy:
+//! run it with `cargo test --test python -- --ignored`.
+
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+#[ignore = "needs Python 3 with numpy installed"]
+fn python_tests() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    // Test builds leave the library next to the test binaries, in `target/<profile>/deps`
+    let lib_dir = std::env::current_exe().unwrap().parent().unwrap().to_path_buf();
+    // Python only imports the module under its own name
+    let module_dir = lib_dir.join("python");
+    std::fs::create_dir_all(&module_dir).unwrap();
+    let lib = format!("{}chip8{}", std::env::consts::DLL_PREFIX, std::env::consts::DLL_SUFFIX);
+    std::fs::copy(lib_dir.join(lib), module_dir.join("chip8.so")).unwrap();
+
+    let python = std::env::var("PYTHON").unwrap_or_else(|_| "python3".to_string());
+    let output = Command::new(&python)
+        .args(["-m", "unittest", "discover", "-v", "-s"])
+        .arg(dir.join("tests"))
+        .env("PYTHONPATH", &module_dir)
+        .output()
+        .unwrap_or_else(|err| panic!("running Python ({}) failed: {}", python, err));
+    assert!(output.status.success(), "Python tests failed:\n{}", String::from_utf8_lossy(&output.stderr));
+}
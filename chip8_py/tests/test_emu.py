@@ -0,0 +1,106 @@
+"""Tests for the Python bindings, run by tests/python.rs or with `python -m unittest discover tests`"""
+
+import unittest
+
+import numpy
+
+import chip8
+
+# Starts the sound timer and draws a "0" at (5, 0)
+ROM = bytes([
+    0x60, 0x05,  # 200: LD V0, 5
+    0xF0, 0x18,  # 202: LD ST, V0
+    0x61, 0x00,  # 204: LD V1, 0
+    0xF1, 0x29,  # 206: LD F, V1
+    0xD0, 0x15,  # 208: DRW V0, V1, 5
+    0x12, 0x0A,  # 20A: JP 0x20A
+])
+
+
+class EmuTest(unittest.TestCase):
+    def test_step(self):
+        emu = chip8.Emu(ROM, seed=0)
+        self.assertEqual(emu.pc, 0x200)
+        emu.step(2)
+        self.assertEqual(emu.pc, 0x204)
+        self.assertEqual(emu.registers[0], 5)
+        self.assertEqual(emu.timers, (0, 5))
+
+    def test_run_frames(self):
+        emu = chip8.Emu(ROM)
+        emu.run_frames(3)
+        self.assertEqual(emu.pc, 0x20A)
+        self.assertEqual(emu.timers, (0, 2))
+        self.assertEqual(emu.index, 0)
+
+    def test_memory(self):
+        emu = chip8.Emu(ROM)
+        self.assertEqual(emu.memory(0x200, 0x200 + len(ROM)), ROM)
+        self.assertEqual(len(emu.memory()), 4096)
+        self.assertEqual(emu.memory(4000, 5000), bytes(96))
+
+    def test_keys(self):
+        emu = chip8.Emu(bytes([0xF0, 0x0A, 0x12, 0x02]))  # LD V0, K; JP 0x202
+        emu.run_frames()
+        self.assertEqual(emu.pc, 0x200)  # Waiting
+        emu.keypress(0xB, True)
+        emu.run_frames()
+        emu.keypress(0xB, False)
+        emu.run_frames()
+        self.assertEqual(emu.registers[0], 0xB)
+        self.assertEqual(emu.pc, 0x202)
+        with self.assertRaises(ValueError):
+            emu.keypress(16, True)
+
+    def test_snapshot(self):
+        emu = chip8.Emu(ROM)
+        emu.step(2)
+        snapshot = emu.snapshot()
+        emu.run_frames(10)
+        self.assertEqual(emu.timers, (0, 0))
+        emu.restore(snapshot)
+        self.assertEqual(emu.timers, (0, 5))
+        self.assertEqual(emu.pc, 0x204)
+        self.assertEqual(emu.snapshot(), snapshot)
+
+    def test_quirks(self):
+        emu = chip8.Emu(ROM, quirks="chip8")
+        emu.set_quirks("xochip")
+        self.assertIn("schip", chip8.PRESETS)
+        with self.assertRaises(ValueError):
+            emu.set_quirks("nes")
+        with self.assertRaises(ValueError):
+            chip8.Emu(bytes(4000))
+
+    def test_rand_is_seeded(self):
+        rom = bytes([0xC0, 0xFF, 0xC1, 0xFF])  # RND V0, 0xFF; RND V1, 0xFF
+        first, second = chip8.Emu(rom, seed=7), chip8.Emu(rom, seed=7)
+        first.step(2)
+        second.step(2)
+        self.assertEqual(first.registers, second.registers)
+
+    def test_framebuffer(self):
+        emu = chip8.Emu(ROM)
+        emu.run_frames()
+        screen = emu.framebuffer()
+        self.assertEqual(screen.shape, (chip8.SCREEN_HEIGHT, chip8.SCREEN_WIDTH))
+        self.assertEqual(screen.dtype, numpy.uint8)
+        self.assertEqual(list(screen[0, 4:10]), [0, 1, 1, 1, 1, 0])
+        self.assertEqual(int(screen.sum()), 14)
+
+    def test_faults(self):
+        # Returns with an empty stack
+        emu = chip8.Emu(bytes([0x60, 0x01, 0x00, 0xEE]))
+        with self.assertRaises(chip8.Chip8Fault) as raised:
+            emu.step(2)
+        self.assertEqual(raised.exception.kind, "stack_underflow")
+        self.assertEqual(raised.exception.addr, 0x202)
+        # The emulator stays at the faulting instruction
+        self.assertEqual(emu.pc, 0x202)
+        self.assertEqual(emu.registers[0], 1)
+        with self.assertRaises(chip8.Chip8Fault):
+            emu.run_frames()
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,76 @@
+"""Tests for the gym environments, run by tests/python.rs or with `python -m unittest discover tests`"""
+
+import os
+import unittest
+
+import numpy
+
+import chip8
+
+ROMS = os.path.join(os.path.dirname(__file__), "..", "..", "roms")
+
+
+def rom(name):
+    with open(os.path.join(ROMS, name), "rb") as f:
+        return f.read()
+
+
+class EnvTest(unittest.TestCase):
+    def test_spec(self):
+        env = chip8.Env(rom("BRIX"), "BRIX")
+        self.assertEqual(env.num_actions, 3)
+        env = chip8.Env(rom("BRIX"), "actions = -, 4, 6, 4+6\nreward = V5")
+        self.assertEqual(env.num_actions, 4)
+        with self.assertRaises(ValueError):
+            chip8.Env(rom("BRIX"), "reward = V5 /")
+        with self.assertRaises(ValueError):
+            chip8.Env(rom("BRIX"), "BRIX", frame_skip=0)
+
+    def test_episode(self):
+        env = chip8.Env(rom("BRIX"), "BRIX")
+        observation = env.reset(seed=1)
+        self.assertEqual(observation.shape, (32, 64))
+        total, done, steps = 0.0, False, 0
+        while not done:
+            observation, reward, done, info = env.step(steps % 3)
+            total += reward
+            steps += 1
+        self.assertFalse(info["truncated"])
+        self.assertEqual(env.registers[0xE], 0)
+        self.assertEqual(total, env.registers[5] - 5)
+        with self.assertRaises(RuntimeError):
+            env.step(0)
+        with self.assertRaises(ValueError):
+            env.step(3)
+
+    def test_vec_env(self):
+        envs = chip8.VecEnv(rom("PONG"), "PONG", 8, seed=5, threads=3)
+        self.assertEqual(len(envs), 8)
+        self.assertEqual(envs.reset(seed=5).shape, (8, 32, 64))
+        for step in range(100):
+            observations, rewards, dones, infos = envs.step([step % 3] * 8)
+        self.assertEqual(observations.shape, (8, 32, 64))
+        self.assertEqual(rewards.dtype, numpy.float32)
+        self.assertEqual(dones.dtype, numpy.bool_)
+        self.assertEqual(infos[0]["frame"], 400)
+        with self.assertRaises(ValueError):
+            envs.step([0])
+
+    def test_faults(self):
+        # Returns with an empty stack while key 0 is held
+        rom = bytes([0xE0, 0xA1, 0x00, 0xEE, 0x12, 0x00])
+        env = chip8.Env(rom, "actions = -, 0")
+        env.step(0)
+        with self.assertRaises(chip8.Chip8Fault) as raised:
+            env.step(1)
+        self.assertEqual((raised.exception.kind, raised.exception.addr), ("stack_underflow", 0x202))
+        env.reset()
+        envs = chip8.VecEnv(rom, "actions = -, 0", 3)
+        with self.assertRaises(chip8.Chip8Fault) as raised:
+            envs.step([0, 1, 0])
+        self.assertIn("environment 1", str(raised.exception))
+        self.assertEqual(raised.exception.addr, 0x202)
+
+
+if __name__ == "__main__":
+    unittest.main()
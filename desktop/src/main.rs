@@ -1,21 +1,178 @@
-use chip8_core::Emu;
+#![allow(clippy::needless_return)]
+
+use chip8_core::backend::{Backend, BlockJit, Interpreter};
+use chip8_core::lockstep::{self, Side};
+use chip8_core::{Cheat, Emu, Quirks};
+use chip8_core::{analysis, aot, octo};
 use std::env;
 use std::io::Read;
 
 fn main() {
     let args: Vec<_> = env::args().collect();
-    // If rom path not specified, or too many args are supplied
-    if args.len() != 2 {
-        println!("Usage: cargo run path/to/rom");
-        return;
+    match (args.len(), args.get(1).map(String::as_str)) {
+        (3, Some("info")) => info(&args[2]),
+        (3, Some("lint")) => lint(&args[2]),
+        (3, Some("cfg")) => cfg(&args[2], None),
+        (4, Some("cfg")) => cfg(&args[2], Some(&args[3])),
+        (3, Some("callgraph")) => call_graph(&args[2]),
+        (3, Some("decompile")) => decompile(&args[2]),
+        (4, Some("compile")) => compile(&args[2], &args[3]),
+        (4, Some("translate")) => translate(&args[2], &args[3]),
+        (5, Some("diff")) => diff(&args[2], &args[3], &args[4], None),
+        (6, Some("diff")) => diff(&args[2], &args[3], &args[4], Some(&args[5])),
+        (2, _) => run(&args[1]),
+        // If rom path not specified, or too many args are supplied
+        _ => {
+            println!("Usage: cargo run path/to/rom");
+            println!("       cargo run info path/to/rom");
+            println!("       cargo run lint path/to/rom");
+            println!("       cargo run cfg path/to/rom [subroutine address]");
+            println!("       cargo run callgraph path/to/rom");
+            println!("       cargo run decompile path/to/rom");
+            println!("       cargo run compile path/to/source.8o path/to/rom");
+            println!("       cargo run translate path/to/rom path/to/module.rs|path/to/crate");
+            println!("       cargo run diff path/to/rom preset[:backend] preset[:backend] [frames]");
+        },
+    }
+}
+
+fn read_rom(path: &str) -> Vec<u8> {
+    let mut rom = std::fs::File::open(path).expect("Unable to open rom");
+    let mut buffer = Vec::new();
+    rom.read_to_end(&mut buffer).unwrap();
+    return buffer;
+}
+
+/// Print an overview of what the rom needs
+fn info(path: &str) {
+    let report = analysis::lint(&read_rom(path));
+    print!("{}", report.summary());
+}
+
+/// Print everything questionable about the rom
+fn lint(path: &str) {
+    let report = analysis::lint(&read_rom(path));
+    print!("{}", report);
+    if !report.is_clean() {
+        std::process::exit(1);
+    }
+}
+
+/// Print the rom's control-flow graph as Graphviz DOT
+/// Either the whole rom, or a single subroutine (e.g. `2F6`)
+fn cfg(path: &str, subroutine: Option<&str>) {
+    let rom = read_rom(path);
+    let cfg = analysis::Cfg::build(&analysis::Rom::new(&rom));
+    match subroutine {
+        None => print!("{}", cfg.to_dot()),
+        Some(addr) => {
+            let addr = u16::from_str_radix(addr.trim_start_matches("0x"), 16).expect("Invalid subroutine address");
+            match cfg.subroutine_dot(addr) {
+                Some(dot) => print!("{}", dot),
+                None => eprintln!("No subroutine at {:#05X}", addr),
+            }
+        },
+    }
+}
+
+/// Print the rom's call graph as Graphviz DOT
+fn call_graph(path: &str) {
+    let rom = read_rom(path);
+    let cfg = analysis::Cfg::build(&analysis::Rom::new(&rom));
+    print!("{}", cfg.call_graph_dot());
+}
+
+/// Print the rom as structured pseudocode
+fn decompile(path: &str) {
+    let rom = read_rom(path);
+    print!("{}", analysis::decompile(&analysis::Rom::new(&rom)));
+}
+
+/// Compile an Octo program into a rom, with its symbol table alongside (`path/to/rom.sym`)
+fn compile(source: &str, output: &str) {
+    let source = std::fs::read_to_string(source).expect("Unable to open source");
+    match octo::compile(&source) {
+        Ok(program) => {
+            std::fs::write(output, &program.rom).expect("Unable to write rom");
+            std::fs::write(format!("{}.sym", output), program.symbol_table()).expect("Unable to write symbols");
+        },
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        },
+    }
+}
+
+/// Translate a rom into a Rust module (`path/to/module.rs`), or else a crate to build it into a binary
+/// (see `chip8_core::aot`)
+fn translate(path: &str, output: &str) {
+    let rom = read_rom(path);
+    let rom = analysis::Rom::new(&rom);
+    let name = std::path::Path::new(path).file_name().map_or(path.into(), |n| n.to_string_lossy());
+    if output.ends_with(".rs") {
+        std::fs::write(output, aot::translate(&rom, &name)).expect("Unable to write module");
+    } else {
+        let core = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../chip8_core");
+        let core = core.canonicalize().expect("Unable to find chip8_core");
+        aot::translate_crate(&rom, &name, &core).write(std::path::Path::new(output)).expect("Unable to write crate");
     }
+}
+
+/// Run a rom under two configurations (`preset`, `preset:interpreter` or `preset:jit`) and report where they diverge
+fn diff(path: &str, left: &str, right: &str, frames: Option<&str>) {
+    let frames = frames.map_or(3600, |f| f.parse().expect("Invalid frame count"));
+    let rom = read_rom(path);
+    let side = |spec: &str| {
+        let (preset, backend) = spec.split_once(':').unwrap_or((spec, "interpreter"));
+        let Some(quirks) = Quirks::preset(preset) else {
+            eprintln!("Unknown preset `{}`, expected one of: {}", preset, Quirks::PRESETS.map(|(name, _)| name).join(", "));
+            std::process::exit(1);
+        };
+        let backend: Box<dyn Backend> = match backend {
+            "interpreter" => Box::new(Interpreter),
+            "jit" => Box::new(BlockJit::new()),
+            _ => {
+                eprintln!("Unknown backend `{}`, expected one of: interpreter, jit", backend);
+                std::process::exit(1);
+            },
+        };
+        let mut emu = Emu::new();
+        emu.seed_rng(0);
+        emu.set_quirks(quirks);
+        if let Err(error) = emu.load_rom(&rom) {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+        Side { name: spec.to_string(), emu, backend }
+    };
+    let (mut left, mut right) = (side(left), side(right));
 
+    // A different key held every 8 frames, so input paths get exercised too
+    match lockstep::lockstep(&mut left, &mut right, frames, |frame| 1 << (frame / 8 % 16)) {
+        Some(divergence) => {
+            print!("{}", divergence);
+            std::process::exit(1);
+        },
+        None => println!("No divergence in {} frames", frames),
+    }
+}
+
+fn run(path: &str) {
     let mut chip8 = Emu::new();
 
-    { // Load the rom
-        let mut rom = std::fs::File::open(&args[1]).expect("Unable to open rom");
-        let mut buffer = Vec::new();
-        rom.read_to_end(&mut buffer).unwrap();
-        chip8.load_rom(&buffer);
+    // Load the rom
+    if let Err(error) = chip8.load_rom(&read_rom(path)) {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+
+    { // Load the rom's cheats, if it has any (`path/to/rom.cht`)
+        let path = format!("{}.cht", path);
+        if let Ok(text) = std::fs::read_to_string(&path) {
+            let cheats = Cheat::parse_file(&text).expect("Unable to parse cheat file");
+            for cheat in cheats {
+                chip8.add_cheat(cheat);
+            }
+        }
     }
 }